@@ -0,0 +1,1195 @@
+use std::{
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::Resource;
+use rand::{seq::SliceRandom, Rng};
+use serde::Deserialize;
+
+pub use book::{book_move, transform};
+pub use threats::{find_forced_win, threat_made};
+
+use crate::{
+    rules::{self, RuleSet, Variant},
+    BoardState, StoneColor,
+};
+use table::{Bound, CachedEval, TranspositionTable};
+
+mod book;
+mod table;
+mod threats;
+
+pub const DEFAULT_DEPTH: u8 = 3;
+pub const MAX_DEPTH: u8 = 8;
+pub const DEFAULT_RADIUS: usize = 2;
+pub const DEFAULT_FORCED_WIN_DEPTH: u8 = 3;
+pub const MAX_FORCED_WIN_DEPTH: u8 = 4;
+/// How long the computer may think about a move unless `--think` says otherwise.
+pub const DEFAULT_THINK_TIME: Duration = Duration::from_secs(1);
+
+/// Only the most promising replies are searched at each node, which keeps a depth-3 search
+/// well under a second even on a 19x19 board.
+const BEAM_WIDTH: usize = 12;
+
+const WIN_SCORE: i32 = 1_000_000;
+const INFINITY: i32 = i32::MAX;
+
+/// How hard the computer tries, chosen with the 1-3 keys.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AiDifficulty {
+    /// One ply over nearby cells, and now and then a random decent-looking move instead.
+    Easy,
+    #[default]
+    Medium,
+    /// Up to eight plies as time allows, searching deeper still after moves that make a four,
+    /// and a longer hunt for forced wins.
+    Hard,
+}
+
+/// What the computer values more when it weighs a position, chosen with the personality key
+/// or `ai_personality` in `gomoku.toml`.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AiPersonality {
+    #[default]
+    Balanced,
+    /// Builds its own shapes and leaves the opponent's alone for longer.
+    Aggressive,
+    /// Breaks up the opponent's shapes before building its own.
+    Defensive,
+}
+
+impl AiPersonality {
+    pub fn name(self) -> &'static str {
+        match self {
+            AiPersonality::Balanced => "balanced",
+            AiPersonality::Aggressive => "aggressive",
+            AiPersonality::Defensive => "defensive",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "balanced" => Some(AiPersonality::Balanced),
+            "aggressive" => Some(AiPersonality::Aggressive),
+            "defensive" => Some(AiPersonality::Defensive),
+            _ => None,
+        }
+    }
+
+    /// The personality after this one, for cycling through them.
+    pub fn next(self) -> Self {
+        match self {
+            AiPersonality::Balanced => AiPersonality::Aggressive,
+            AiPersonality::Aggressive => AiPersonality::Defensive,
+            AiPersonality::Defensive => AiPersonality::Balanced,
+        }
+    }
+
+    /// Percentages the own shapes and the opponent's are counted at.
+    fn weights(self) -> (i64, i64) {
+        match self {
+            AiPersonality::Balanced => (100, 100),
+            AiPersonality::Aggressive => (150, 75),
+            AiPersonality::Defensive => (75, 150),
+        }
+    }
+}
+
+/// How often Easy plays a random move from its shortlist instead of its best one.
+const EASY_BLUNDER_CHANCE: f64 = 0.3;
+const EASY_SHORTLIST: usize = 5;
+
+impl AiDifficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            AiDifficulty::Easy => "Easy",
+            AiDifficulty::Medium => "Medium",
+            AiDifficulty::Hard => "Hard",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "easy" => Some(AiDifficulty::Easy),
+            "medium" => Some(AiDifficulty::Medium),
+            "hard" => Some(AiDifficulty::Hard),
+            _ => None,
+        }
+    }
+
+    fn search(self, rules: &RuleSet, variant: Variant, personality: AiPersonality) -> Search {
+        let search = match self {
+            AiDifficulty::Easy => Search {
+                depth: 1,
+                radius: 1,
+                forced_win_depth: 0,
+                ..Search::default()
+            },
+            AiDifficulty::Medium => Search::default(),
+            AiDifficulty::Hard => Search {
+                depth: MAX_DEPTH,
+                threat_extensions: 2,
+                forced_win_depth: MAX_FORCED_WIN_DEPTH,
+                ..Search::default()
+            },
+        };
+        Search {
+            rules: *rules,
+            variant,
+            personality,
+            ..search
+        }
+    }
+}
+
+/// How much work a search did.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchStats {
+    /// The deepest search that finished in time; 0 if none was needed.
+    pub depth: u8,
+    /// Positions visited by the alpha-beta search.
+    pub nodes: u64,
+    /// Of those, how many were answered from the transposition table instead of searched.
+    pub table_hits: u64,
+}
+
+/// The move an alpha-beta search at `difficulty`, weighing positions the way `personality`
+/// does, rates best for `color`, searching one ply
+/// deeper at a time for as long as `think` allows, followed by the replies the search
+/// expects from both sides in turn (the principal variation). The line is never empty; it
+/// is cut short where the search didn't look further.
+///
+/// Falls back to the center if the board has no empty intersection near the stones.
+pub fn best_move(
+    board: &BoardState,
+    color: StoneColor,
+    difficulty: AiDifficulty,
+    personality: AiPersonality,
+    rules: &RuleSet,
+    variant: Variant,
+    think: Duration,
+) -> (Vec<(usize, usize)>, SearchStats) {
+    let search = Search {
+        player: color,
+        ..difficulty.search(rules, variant, personality)
+    };
+    search.best_move(board, color, think)
+}
+
+/// Picks a move for `color` the way `difficulty` plays, which for Easy is not always the best,
+/// as the first of the line `best_move` returns.
+pub fn choose_move(
+    board: &BoardState,
+    color: StoneColor,
+    difficulty: AiDifficulty,
+    personality: AiPersonality,
+    rules: &RuleSet,
+    variant: Variant,
+    think: Duration,
+    rng: &mut impl Rng,
+) -> (Vec<(usize, usize)>, SearchStats) {
+    if difficulty == AiDifficulty::Easy && rng.gen_bool(EASY_BLUNDER_CHANCE) {
+        let moves = difficulty
+            .search(rules, variant, personality)
+            .moves(board, color);
+        if let Some(&cell) = moves[..moves.len().min(EASY_SHORTLIST)].choose(rng) {
+            return (vec![cell], SearchStats::default());
+        }
+    }
+    best_move(board, color, difficulty, personality, rules, variant, think)
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Search {
+    /// The deepest the iterative deepening goes, time permitting.
+    depth: u8,
+    radius: usize,
+    /// Plies that may be added on top of `depth` when a move makes a four, so forcing
+    /// sequences aren't cut off just before they pay off.
+    threat_extensions: u8,
+    /// Own moves the threat-space search may use to find a forced win before the full search
+    /// runs; 0 skips it.
+    forced_win_depth: u8,
+    rules: RuleSet,
+    /// Only for `rules::variant_move_legal`; the search itself plays every variant as
+    /// `Standard`.
+    variant: Variant,
+    personality: AiPersonality,
+    /// Whose side the personality is on, which is whoever the search is choosing a move for.
+    player: StoneColor,
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Search {
+            depth: DEFAULT_DEPTH,
+            radius: DEFAULT_RADIUS,
+            threat_extensions: 0,
+            forced_win_depth: DEFAULT_FORCED_WIN_DEPTH,
+            rules: RuleSet::default(),
+            variant: Variant::Standard,
+            personality: AiPersonality::default(),
+            player: StoneColor::Black,
+        }
+    }
+}
+
+/// What one move's searches share as they deepen.
+struct SearchRun {
+    /// Kept from one depth to the next, since a position left with the same depth to go
+    /// scores the same however deep the search around it is.
+    table: TranspositionTable,
+    stats: SearchStats,
+    /// When to give up on the depth being searched; not set for the first, so there is
+    /// always a move to play.
+    deadline: Option<Instant>,
+    timed_out: bool,
+}
+
+impl SearchRun {
+    fn out_of_time(&mut self) -> bool {
+        if !self.timed_out {
+            self.timed_out = self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline);
+        }
+        self.timed_out
+    }
+}
+
+impl Search {
+    fn best_move(
+        &self,
+        board: &BoardState,
+        color: StoneColor,
+        think: Duration,
+    ) -> (Vec<(usize, usize)>, SearchStats) {
+        // The hunt for a forced win comes out of the same budget
+        let deadline = Instant::now() + think;
+        // A forced win beats anything the heuristic search would rate highest. Its line
+        // leaves out the defender's replies, so only the first move is passed on
+        if self.forced_win_depth > 0 {
+            if let Some(&(col, row)) =
+                find_forced_win(board, color, self.forced_win_depth, &self.rules)
+                    .as_deref()
+                    .and_then(<[_]>::first)
+            {
+                if rules::variant_move_legal(board, col, row, color, self.variant) {
+                    return (vec![(col, row)], SearchStats::default());
+                }
+            }
+        }
+
+        let mut board = board.clone();
+        let mut moves = self.moves(&board, color);
+        let Some(&first) = moves.first() else {
+            let center = board.size() / 2;
+            return (vec![(center, center)], SearchStats::default());
+        };
+
+        // Positions from the previous move's search are mostly unreachable now, so start afresh
+        let mut run = SearchRun {
+            table: TranspositionTable::default(),
+            stats: SearchStats::default(),
+            deadline: None,
+            timed_out: false,
+        };
+        let mut line = vec![first];
+        for depth in 1..=self.depth.clamp(1, MAX_DEPTH) {
+            let Some((best, score)) = self.search_root(&mut board, color, depth, &moves, &mut run)
+            else {
+                break;
+            };
+            run.stats.depth = depth;
+            // Read now, before a deeper search that may not finish overwrites the entries
+            line = self.principal_variation(&mut board, color, best, &run);
+            if score >= WIN_SCORE {
+                break;
+            }
+            // The best move so far is the likeliest to stay best, and searching it first
+            // narrows the window for the rest
+            let index = moves.iter().position(|&cell| cell == best).unwrap_or(0);
+            moves[..=index].rotate_right(1);
+            run.deadline = Some(deadline);
+        }
+        (line, run.stats)
+    }
+
+    /// `first` followed by the best replies the search to `run.stats.depth` found, read back
+    /// from the transposition table until it runs out or the line ends in a win.
+    fn principal_variation(
+        &self,
+        board: &mut BoardState,
+        color: StoneColor,
+        first: (usize, usize),
+        run: &SearchRun,
+    ) -> Vec<(usize, usize)> {
+        let mut line = vec![first];
+        let (mut color, mut depth, mut extensions) =
+            (color, run.stats.depth, self.threat_extensions);
+        let mut cell = first;
+        loop {
+            let (col, row) = cell;
+            board.set(col, row, color);
+            if depth == 0 || rules::check_win(board, col, row, color, &self.rules).is_some() {
+                break;
+            }
+            (depth, extensions) = self.child_depth(board, col, row, color, depth, extensions);
+            color = color.opponent();
+            let key = TranspositionTable::key(board, color);
+            // An occupied point can only come from a hash collision
+            match run
+                .table
+                .probe(key, depth, extensions)
+                .and_then(|entry| entry.best)
+            {
+                Some(next) if board.get(next.0, next.1).is_none() => {
+                    line.push(next);
+                    cell = next;
+                }
+                _ => break,
+            }
+        }
+        for &(col, row) in &line {
+            board.remove(col, row);
+        }
+        line
+    }
+
+    /// The best of `moves` at `depth` and its score, or `None` if time ran out first.
+    fn search_root(
+        &self,
+        board: &mut BoardState,
+        color: StoneColor,
+        depth: u8,
+        moves: &[(usize, usize)],
+        run: &mut SearchRun,
+    ) -> Option<((usize, usize), i32)> {
+        let mut best = None;
+        let mut alpha = -INFINITY;
+        for &(col, row) in moves {
+            board.set(col, row, color);
+            let score = if rules::check_win(board, col, row, color, &self.rules).is_some() {
+                WIN_SCORE + depth as i32
+            } else {
+                let (depth, extensions) =
+                    self.child_depth(board, col, row, color, depth, self.threat_extensions);
+                -self.negamax(
+                    board,
+                    color.opponent(),
+                    depth,
+                    extensions,
+                    -INFINITY,
+                    -alpha,
+                    run,
+                )
+            };
+            board.remove(col, row);
+            if run.timed_out {
+                return None;
+            }
+
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some((col, row));
+            }
+        }
+        best.map(|cell| (cell, alpha))
+    }
+
+    /// The score of the position for `color`. Once `run` is out of time this returns early
+    /// with a meaningless score, which the caller must throw away.
+    fn negamax(
+        &self,
+        board: &mut BoardState,
+        color: StoneColor,
+        depth: u8,
+        extensions: u8,
+        mut alpha: i32,
+        beta: i32,
+        run: &mut SearchRun,
+    ) -> i32 {
+        if run.out_of_time() {
+            return 0;
+        }
+        run.stats.nodes += 1;
+        let key = TranspositionTable::key(board, color);
+        if let Some(entry) = run.table.probe(key, depth, extensions) {
+            let usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            };
+            if usable {
+                run.stats.table_hits += 1;
+                return entry.score;
+            }
+        }
+
+        let original_alpha = alpha;
+        let mut best_cell = None;
+        let best = if depth == 0 {
+            // The weighting belongs to the player, so the opponent's view is the negation
+            let score =
+                weighted_evaluate(board, self.player, self.personality, self.rules.win_length);
+            if color == self.player {
+                score
+            } else {
+                -score
+            }
+        } else {
+            let moves = self.moves(board, color);
+            let mut best = if moves.is_empty() { 0 } else { -INFINITY };
+            for (col, row) in moves {
+                board.set(col, row, color);
+                // Wins found sooner score higher so the search doesn't dawdle
+                let score = if rules::check_win(board, col, row, color, &self.rules).is_some() {
+                    WIN_SCORE + depth as i32
+                } else {
+                    let (depth, extensions) =
+                        self.child_depth(board, col, row, color, depth, extensions);
+                    -self.negamax(
+                        board,
+                        color.opponent(),
+                        depth,
+                        extensions,
+                        -beta,
+                        -alpha,
+                        run,
+                    )
+                };
+                board.remove(col, row);
+                if run.timed_out {
+                    return 0;
+                }
+
+                if score > best {
+                    best = score;
+                    best_cell = Some((col, row));
+                }
+                alpha = alpha.max(score);
+                if alpha >= beta {
+                    break;
+                }
+            }
+            best
+        };
+
+        let bound = if depth == 0 {
+            Bound::Exact
+        } else if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        run.table.store(
+            key,
+            CachedEval {
+                depth,
+                extensions,
+                score: best,
+                bound,
+                best: best_cell,
+            },
+        );
+        best
+    }
+
+    /// The moves worth searching for `color`, leaving out any the rules or the variant
+    /// forbid.
+    fn moves(&self, board: &BoardState, color: StoneColor) -> Vec<(usize, usize)> {
+        let legal = |&(col, row): &(usize, usize)| {
+            rules::forbidden_move(board, col, row, color, &self.rules).is_none()
+                && rules::variant_move_legal(board, col, row, color, self.variant)
+        };
+        let moves = ordered_moves(board, color, self.radius, self.rules.win_length, legal);
+        if moves.is_empty() && self.variant == Variant::Pente {
+            // Pente's opening rule can rule out every point near the stones
+            return ordered_moves(
+                board,
+                color,
+                rules::PENTE_OPENING_DISTANCE,
+                self.rules.win_length,
+                legal,
+            );
+        }
+        moves
+    }
+
+    /// Depth and remaining extensions for the reply to the move just played at `(col, row)`.
+    fn child_depth(
+        &self,
+        board: &BoardState,
+        col: usize,
+        row: usize,
+        color: StoneColor,
+        depth: u8,
+        extensions: u8,
+    ) -> (u8, u8) {
+        if extensions > 0 && makes_four(board, col, row, color, self.rules.win_length) {
+            (depth, extensions - 1)
+        } else {
+            (depth - 1, extensions)
+        }
+    }
+}
+
+/// Scores the position for `color` in a game won by `win_length` in a row; positive means
+/// `color` is ahead.
+///
+/// Every row, column and diagonal is matched against the shapes `patterns` gives, and the
+/// opponent's shapes are subtracted, so
+/// `evaluate(board, c, n) == -evaluate(board, c.opponent(), n)`.
+pub fn evaluate(board: &BoardState, color: StoneColor, win_length: usize) -> i32 {
+    weighted_evaluate(board, color, AiPersonality::Balanced, win_length)
+}
+
+/// `evaluate` with `color`'s shapes and the opponent's counted at the weights `personality`
+/// gives them.
+fn weighted_evaluate(
+    board: &BoardState,
+    color: StoneColor,
+    personality: AiPersonality,
+    win_length: usize,
+) -> i32 {
+    let patterns = patterns(win_length);
+    let (mut own, mut theirs) = (0i64, 0i64);
+    for line in board_lines(board, win_length) {
+        own += i64::from(pattern_score(&encode_line(board, &line, color), patterns));
+        theirs += i64::from(pattern_score(
+            &encode_line(board, &line, color.opponent()),
+            patterns,
+        ));
+    }
+    let (attack, defense) = personality.weights();
+    let score = (own * attack - theirs * defense) / 100;
+    score.clamp(-i64::from(i32::MAX - 1), i64::from(i32::MAX - 1)) as i32
+}
+
+/// A shape in pattern notation and what it is worth.
+type Pattern = (Vec<u8>, i32);
+
+/// The shapes worth scoring when `win_length` in a row wins, built once for each length in
+/// `rules::WIN_LENGTHS`.
+fn patterns(win_length: usize) -> &'static [Pattern] {
+    static TABLES: OnceLock<Vec<Vec<Pattern>>> = OnceLock::new();
+    let tables = TABLES.get_or_init(|| rules::WIN_LENGTHS.map(build_patterns).collect());
+    &tables[win_length - rules::WIN_LENGTHS.start()]
+}
+
+/// Shapes in priority order: `X` is an own stone, `.` an empty cell and `O` an opponent stone
+/// or the board edge. For five in a row these are five, the open four `.XXXX.`, the simple
+/// fours such as `XX.XX`, the open threes such as `.XX.X.`, the closed threes such as `XXX..`
+/// and the open twos `.XX.` and `.X.X.`; other lengths get the same shapes one, two and three
+/// stones short of a win.
+fn build_patterns(win_length: usize) -> Vec<Pattern> {
+    // Every way of spreading `stones` stones over `length` points
+    let fills = |length: usize, stones: usize| -> Vec<Vec<u8>> {
+        (0u32..1 << length)
+            .filter(|mask| mask.count_ones() as usize == stones)
+            .map(|mask| {
+                (0..length)
+                    .map(|i| if mask >> i & 1 == 1 { b'X' } else { b'.' })
+                    .collect()
+            })
+            .collect()
+    };
+    // `stones` stones in a row, or with one gap, set between two empty points: one more
+    // stone makes them an open line one longer
+    let open = |stones: usize| -> Vec<Vec<u8>> {
+        (0..stones)
+            .map(|gap| {
+                let mut shape = vec![b'X'; stones];
+                if gap > 0 {
+                    shape.insert(gap, b'.');
+                }
+                [&b"."[..], &shape, b"."].concat()
+            })
+            .collect()
+    };
+
+    let mut patterns = vec![
+        (vec![b'X'; win_length], WIN_SCORE),
+        (open(win_length - 1).swap_remove(0), 100_000),
+    ];
+    let mut add = |shapes: Vec<Vec<u8>>, value| {
+        for shape in shapes {
+            if !patterns.iter().any(|(pattern, _)| *pattern == shape) {
+                patterns.push((shape, value));
+            }
+        }
+    };
+    add(fills(win_length, win_length - 1), 10_000);
+    add(open(win_length - 2), 5_000);
+    add(fills(win_length, win_length - 2), 500);
+    add(open(win_length - 3), 200);
+    patterns
+}
+
+/// Sums the shapes found in `line`. Stronger shapes claim their stones first, so a group is
+/// only counted once, as the best shape it forms.
+fn pattern_score(line: &[u8], patterns: &'static [Pattern]) -> i32 {
+    if !line.contains(&b'X') {
+        return 0;
+    }
+
+    let mut matches: Vec<(i32, usize, &[u8])> = Vec::new();
+    for start in 0..line.len() {
+        for (pattern, value) in patterns {
+            if line[start..].starts_with(pattern) {
+                matches.push((*value, start, pattern));
+            }
+        }
+    }
+    matches.sort_by_key(|&(value, start, _)| (std::cmp::Reverse(value), start));
+
+    let mut claimed = vec![false; line.len()];
+    let mut score = 0;
+    for (value, start, pattern) in matches {
+        let stones: Vec<_> = (start..start + pattern.len())
+            .filter(|&i| line[i] == b'X')
+            .collect();
+        if stones.iter().any(|&i| claimed[i]) {
+            continue;
+        }
+        stones.iter().for_each(|&i| claimed[i] = true);
+        score += value;
+    }
+    score
+}
+
+/// Every row, column and diagonal long enough to hold `win_length` stones, as lists of cells.
+fn board_lines(board: &BoardState, win_length: usize) -> Vec<Vec<(usize, usize)>> {
+    let size = board.size() as i32;
+    let mut lines = Vec::new();
+    if board.wraps() {
+        // Every line is a loop through `size` points, and each loop crosses the bottom row,
+        // or for rows the left column, exactly once
+        for &(dx, dy) in &rules::DIRECTIONS {
+            for start in 0..board.size() {
+                let (col, row) = if dy == 0 { (0, start) } else { (start, 0) };
+                let line = (0..size)
+                    .filter_map(|step| board.offset(col, row, dx, dy, step))
+                    .collect();
+                lines.push(line);
+            }
+        }
+        return lines;
+    }
+    for &(dx, dy) in &rules::DIRECTIONS {
+        for start_row in 0..size {
+            for start_col in 0..size {
+                // Only start where stepping back would leave the board
+                if in_bounds(size as usize, start_col - dx, start_row - dy) {
+                    continue;
+                }
+                let mut line = Vec::new();
+                let (mut x, mut y) = (start_col, start_row);
+                while in_bounds(size as usize, x, y) {
+                    line.push((x as usize, y as usize));
+                    x += dx;
+                    y += dy;
+                }
+                if line.len() >= win_length {
+                    lines.push(line);
+                }
+            }
+        }
+    }
+    lines
+}
+
+/// Writes `line` in pattern notation from `color`'s point of view, walled in by the edges.
+fn encode_line(board: &BoardState, line: &[(usize, usize)], color: StoneColor) -> Vec<u8> {
+    let cells: Vec<u8> = line
+        .iter()
+        .map(|&(col, row)| match board.get(col, row) {
+            Some(stone) if stone == color => b'X',
+            Some(_) => b'O',
+            None => b'.',
+        })
+        .collect();
+    if board.wraps() {
+        return encode_loop(cells);
+    }
+    let mut encoded = Vec::with_capacity(line.len() + 2);
+    encoded.push(b'O');
+    encoded.extend(cells);
+    encoded.push(b'O');
+    encoded
+}
+
+/// Encodes a line that loops round a wrapping board by cutting it open at an opponent stone,
+/// which then walls in both ends, so every shape is read whole and only once. A loop with
+/// no opponent stone is cut at an empty point, which appears at both ends; shapes using that
+/// point from both sides at once are missed.
+fn encode_loop(mut cells: Vec<u8>) -> Vec<u8> {
+    let Some(cut) = cells
+        .iter()
+        .position(|&cell| cell == b'O')
+        .or_else(|| cells.iter().position(|&cell| cell == b'.'))
+    else {
+        return cells;
+    };
+    cells.rotate_left(cut);
+    cells.push(cells[0]);
+    cells
+}
+
+/// The candidate moves that are `legal`, most forcing first, cut to the beam width.
+fn ordered_moves(
+    board: &BoardState,
+    color: StoneColor,
+    radius: usize,
+    win_length: usize,
+    legal: impl Fn(&(usize, usize)) -> bool,
+) -> Vec<(usize, usize)> {
+    let mut moves: Vec<_> = candidate_moves(board, radius)
+        .into_iter()
+        .filter(legal)
+        .map(|(col, row)| {
+            let priority = local_score(board, col, row, color, win_length)
+                + local_score(board, col, row, color.opponent(), win_length);
+            (priority, (col, row))
+        })
+        .collect();
+    moves.sort_by_key(|&(priority, _)| std::cmp::Reverse(priority));
+    moves.truncate(BEAM_WIDTH);
+    moves.into_iter().map(|(_, cell)| cell).collect()
+}
+
+/// Empty cells within `radius` (in both directions) of a stone, or just the center of an
+/// empty board. Moves far from every stone are almost never useful, so the search only
+/// considers these.
+pub fn candidate_moves(board: &BoardState, radius: usize) -> Vec<(usize, usize)> {
+    let size = board.size();
+    if board.stone_count() == 0 {
+        return vec![(size / 2, size / 2)];
+    }
+
+    let radius = radius as i32;
+    let mut cells = Vec::new();
+    for row in 0..size {
+        for col in 0..size {
+            if board.get(col, row).is_some() {
+                continue;
+            }
+            let near_stone = (-radius..=radius).any(|dy| {
+                (-radius..=radius).any(|dx| {
+                    board
+                        .offset(col, row, dx, dy, 1)
+                        .is_some_and(|(x, y)| board.get(x, y).is_some())
+                })
+            });
+            if near_stone {
+                cells.push((col, row));
+            }
+        }
+    }
+    cells
+}
+
+/// How strong the lines through `(col, row)` would be for `color` if it played there.
+fn local_score(
+    board: &BoardState,
+    col: usize,
+    row: usize,
+    color: StoneColor,
+    win_length: usize,
+) -> i32 {
+    rules::DIRECTIONS
+        .iter()
+        .map(|&(dx, dy)| {
+            let (ahead, open_ahead) = run_from(board, col, row, dx, dy, color);
+            let (behind, open_behind) = run_from(board, col, row, -dx, -dy, color);
+            shape_score(
+                1 + ahead + behind,
+                open_ahead as usize + open_behind as usize,
+                win_length,
+            )
+        })
+        .sum()
+}
+
+/// Whether the stone at `(col, row)` is part of a line one short of `win_length` with room
+/// to finish it.
+fn makes_four(
+    board: &BoardState,
+    col: usize,
+    row: usize,
+    color: StoneColor,
+    win_length: usize,
+) -> bool {
+    rules::DIRECTIONS.iter().any(|&(dx, dy)| {
+        let (ahead, open_ahead) = run_from(board, col, row, dx, dy, color);
+        let (behind, open_behind) = run_from(board, col, row, -dx, -dy, color);
+        1 + ahead + behind >= win_length - 1 && (open_ahead || open_behind)
+    })
+}
+
+/// Length of the `color` run starting next to `(col, row)`, and whether the cell past it is empty.
+fn run_from(
+    board: &BoardState,
+    col: usize,
+    row: usize,
+    dx: i32,
+    dy: i32,
+    color: StoneColor,
+) -> (usize, bool) {
+    // On a wrapping board a run can't go further than all the way round
+    let longest = board.size() - 1;
+    let at = |step: usize| board.offset(col, row, dx, dy, step as i32);
+    let mut length = 0;
+    while length < longest && at(length + 1).is_some_and(|(x, y)| board.get(x, y) == Some(color)) {
+        length += 1;
+    }
+    let open = at(length + 1).is_some_and(|(x, y)| board.get(x, y).is_none());
+    (length, open)
+}
+
+/// Weight of a run of `length` stones with `open_ends` (0-2) empty cells beside it.
+fn shape_score(length: usize, open_ends: usize, win_length: usize) -> i32 {
+    // How many stones the run is short of a win
+    match (win_length.saturating_sub(length), open_ends) {
+        (0, _) => WIN_SCORE,
+        (_, 0) => 0,
+        (1, 2) => 100_000,
+        (1, _) => 10_000,
+        (2, 2) => 5_000,
+        (2, _) => 500,
+        (3, 2) => 200,
+        (3, _) => 20,
+        _ => 1,
+    }
+}
+
+fn in_bounds(size: usize, x: i32, y: i32) -> bool {
+    (0..size as i32).contains(&x) && (0..size as i32).contains(&y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// A 15x15 board with the given Black and White stones.
+    fn position(black: &[(usize, usize)], white: &[(usize, usize)]) -> BoardState {
+        let mut board = BoardState::new(15);
+        for &(col, row) in black {
+            board.set(col, row, StoneColor::Black);
+        }
+        for &(col, row) in white {
+            board.set(col, row, StoneColor::White);
+        }
+        board
+    }
+
+    /// The first move of the line Medium picks for `color`.
+    fn medium_move(board: &BoardState, color: StoneColor) -> (usize, usize) {
+        let (line, _) = best_move(
+            board,
+            color,
+            AiDifficulty::Medium,
+            AiPersonality::Balanced,
+            &RuleSet::default(),
+            Variant::Standard,
+            Duration::from_secs(5),
+        );
+        line[0]
+    }
+
+    #[test]
+    fn an_open_three_beats_an_empty_board() {
+        let empty = BoardState::new(15);
+        assert_eq!(
+            evaluate(&empty, StoneColor::Black, rules::DEFAULT_WIN_LENGTH),
+            0
+        );
+        let three = position(&[(6, 7), (7, 7), (8, 7)], &[]);
+        assert!(
+            evaluate(&three, StoneColor::Black, rules::DEFAULT_WIN_LENGTH)
+                > evaluate(&empty, StoneColor::Black, rules::DEFAULT_WIN_LENGTH)
+        );
+        let two = position(&[(6, 7), (7, 7)], &[]);
+        assert!(
+            evaluate(&three, StoneColor::Black, rules::DEFAULT_WIN_LENGTH)
+                > evaluate(&two, StoneColor::Black, rules::DEFAULT_WIN_LENGTH)
+        );
+    }
+
+    #[test]
+    fn an_opposing_open_four_scores_far_below_zero() {
+        let board = position(&[(9, 9)], &[(5, 7), (6, 7), (7, 7), (8, 7)]);
+        assert!(evaluate(&board, StoneColor::Black, rules::DEFAULT_WIN_LENGTH) <= -100_000);
+    }
+
+    #[test]
+    fn evaluation_is_symmetric_and_deterministic() {
+        let board = position(
+            &[(6, 7), (7, 7), (8, 8), (5, 5)],
+            &[(7, 8), (6, 6), (9, 9), (9, 7)],
+        );
+        let black = evaluate(&board, StoneColor::Black, rules::DEFAULT_WIN_LENGTH);
+        assert_eq!(
+            black,
+            -evaluate(&board, StoneColor::White, rules::DEFAULT_WIN_LENGTH)
+        );
+        assert_eq!(
+            black,
+            evaluate(&board.clone(), StoneColor::Black, rules::DEFAULT_WIN_LENGTH)
+        );
+        let mut swapped = board.clone();
+        swapped.swap_colors();
+        assert_eq!(
+            evaluate(&swapped, StoneColor::White, rules::DEFAULT_WIN_LENGTH),
+            black
+        );
+    }
+
+    #[test]
+    fn candidates_are_the_ring_around_a_lone_stone() {
+        let board = position(&[(7, 7)], &[]);
+        let mut expected = Vec::new();
+        for row in 5..=9 {
+            for col in 5..=9 {
+                if (col, row) != (7, 7) {
+                    expected.push((col, row));
+                }
+            }
+        }
+        assert_eq!(candidate_moves(&board, 2), expected);
+        assert_eq!(candidate_moves(&board, 1).len(), 8);
+        assert_eq!(candidate_moves(&BoardState::new(15), 2), vec![(7, 7)]);
+    }
+
+    #[test]
+    fn candidates_stop_at_the_edges() {
+        let board = position(&[(0, 0)], &[]);
+        assert_eq!(
+            candidate_moves(&board, 2),
+            vec![
+                (1, 0),
+                (2, 0),
+                (0, 1),
+                (1, 1),
+                (2, 1),
+                (0, 2),
+                (1, 2),
+                (2, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn takes_a_winning_move() {
+        // White's four along row 7 could also be blocked, but finishing wins at once
+        let board = position(
+            &[(5, 5), (6, 5), (7, 5), (8, 5)],
+            &[(4, 7), (5, 7), (6, 7), (7, 7), (4, 5)],
+        );
+        assert_eq!(medium_move(&board, StoneColor::Black), (9, 5));
+    }
+
+    #[test]
+    fn blocks_a_four() {
+        let board = position(
+            &[(4, 7), (5, 7), (6, 7), (7, 7), (3, 3)],
+            &[(3, 7), (9, 9), (10, 10)],
+        );
+        assert_eq!(medium_move(&board, StoneColor::White), (8, 7));
+    }
+
+    #[test]
+    fn blocks_an_open_three_before_it_becomes_an_open_four() {
+        let board = position(&[(6, 7), (7, 7), (8, 7)], &[(7, 9), (12, 12)]);
+        let block = medium_move(&board, StoneColor::White);
+        assert!(
+            [(5, 7), (9, 7), (4, 7), (10, 7)].contains(&block),
+            "{block:?}"
+        );
+    }
+
+    #[test]
+    fn pente_search_respects_the_opening_rule() {
+        let mut board = BoardState::for_variant(15, Variant::Pente);
+        board.set(7, 7, StoneColor::Black);
+        board.set(8, 8, StoneColor::White);
+        let rules = RuleSet::default();
+        let mut rng = StdRng::seed_from_u64(1);
+        for difficulty in [AiDifficulty::Easy, AiDifficulty::Medium, AiDifficulty::Hard] {
+            let book = book_move(
+                &board,
+                StoneColor::Black,
+                difficulty,
+                &rules,
+                Variant::Pente,
+            );
+            assert_eq!(book, None);
+            let (line, _) = choose_move(
+                &board,
+                StoneColor::Black,
+                difficulty,
+                AiPersonality::Balanced,
+                &rules,
+                Variant::Pente,
+                Duration::from_millis(200),
+                &mut rng,
+            );
+            let (col, row) = line[0];
+            assert!(rules::variant_move_legal(
+                &board,
+                col,
+                row,
+                StoneColor::Black,
+                Variant::Pente
+            ));
+        }
+    }
+
+    #[test]
+    fn five_in_a_row_scores_the_classic_shapes() {
+        let classic: &[(&[u8], i32)] = &[
+            (b"XXXXX", WIN_SCORE),
+            (b".XXXX.", 100_000),
+            (b"XXXX.", 10_000),
+            (b".XXXX", 10_000),
+            (b"XXX.X", 10_000),
+            (b"X.XXX", 10_000),
+            (b"XX.XX", 10_000),
+            (b".XXX.", 5_000),
+            (b".XX.X.", 5_000),
+            (b".X.XX.", 5_000),
+            (b"XXX..", 500),
+            (b"..XXX", 500),
+            (b"XX.X.", 500),
+            (b".X.XX", 500),
+            (b"X.XX.", 500),
+            (b".XX.X", 500),
+            (b"XX..X", 500),
+            (b"X..XX", 500),
+            (b"X.X.X", 500),
+            (b".XX.", 200),
+            (b".X.X.", 200),
+        ];
+        let mut expected: Vec<_> = classic
+            .iter()
+            .map(|&(shape, value)| (shape.to_vec(), value))
+            .collect();
+        let mut built = patterns(rules::DEFAULT_WIN_LENGTH).to_vec();
+        expected.sort();
+        built.sort();
+        assert_eq!(built, expected);
+    }
+
+    #[test]
+    fn only_lines_long_enough_to_win_on_are_scored() {
+        let board = BoardState::new(15);
+        // 15 rows and 15 columns, and in each direction the diagonals of at least that length
+        for (win_length, diagonals) in [(4, 23), (5, 21), (6, 19)] {
+            assert_eq!(board_lines(&board, win_length).len(), 30 + 2 * diagonals);
+        }
+    }
+
+    #[test]
+    fn four_in_a_row_wins_when_four_is_the_target() {
+        let four = position(&[(5, 7), (6, 7), (7, 7), (8, 7)], &[(4, 7), (9, 7)]);
+        assert!(evaluate(&four, StoneColor::Black, 4) >= WIN_SCORE);
+        assert!(evaluate(&four, StoneColor::Black, 5) < WIN_SCORE);
+
+        // One short of four with both ends open is the open four of a game of five
+        let three = position(&[(6, 7), (7, 7), (8, 7)], &[]);
+        assert!(evaluate(&three, StoneColor::Black, 4) >= 100_000);
+        assert!(evaluate(&three, StoneColor::Black, 5) < 100_000);
+    }
+
+    #[test]
+    fn five_in_a_row_falls_short_when_six_is_the_target() {
+        let five = position(&[(5, 7), (6, 7), (7, 7), (8, 7), (9, 7)], &[(4, 7)]);
+        assert!(evaluate(&five, StoneColor::Black, 5) >= WIN_SCORE);
+        let short = evaluate(&five, StoneColor::Black, 6);
+        assert!((10_000..WIN_SCORE).contains(&short));
+
+        let six = position(&[(5, 7), (6, 7), (7, 7), (8, 7), (9, 7), (10, 7)], &[]);
+        assert!(evaluate(&six, StoneColor::Black, 6) >= WIN_SCORE);
+    }
+
+    #[test]
+    fn the_search_blocks_a_three_when_four_wins() {
+        // Black's three is walled in at one end: harmless in a game of five, but one stone
+        // from a win in a game of four
+        let board = position(&[(6, 7), (7, 7), (8, 7)], &[(5, 7), (9, 9)]);
+        let rules = RuleSet {
+            win_length: 4,
+            ..RuleSet::default()
+        };
+        let (line, _) = best_move(
+            &board,
+            StoneColor::White,
+            AiDifficulty::Medium,
+            AiPersonality::Balanced,
+            &rules,
+            Variant::Standard,
+            Duration::from_secs(5),
+        );
+        assert_eq!(line[0], (9, 7));
+    }
+
+    #[test]
+    fn shapes_are_read_across_the_edge_of_a_toroidal_board() {
+        let stones = [(12, 7), (13, 7), (14, 7), (0, 7)];
+        let mut torus = BoardState::for_variant(15, Variant::Toroidal);
+        for &(col, row) in &stones {
+            torus.set(col, row, StoneColor::Black);
+        }
+        // An open four on the torus; against the edges only a closed three and a stone
+        assert!(evaluate(&torus, StoneColor::Black, rules::DEFAULT_WIN_LENGTH) >= 100_000);
+        let flat = position(&stones, &[]);
+        assert!(evaluate(&flat, StoneColor::Black, rules::DEFAULT_WIN_LENGTH) < 10_000);
+    }
+
+    #[test]
+    fn the_personality_decides_between_attack_and_defense() {
+        // Black's open two against White's open three: extending the two is worth more to an
+        // aggressive player, blocking the three to a defensive one
+        let board = position(&[(6, 7), (7, 7), (9, 9)], &[(7, 8), (8, 8), (9, 8)]);
+        let choice = |personality| {
+            let (line, _) = best_move(
+                &board,
+                StoneColor::Black,
+                AiDifficulty::Medium,
+                personality,
+                &RuleSet::default(),
+                Variant::Standard,
+                Duration::from_secs(5),
+            );
+            line[0]
+        };
+        assert_eq!(choice(AiPersonality::Aggressive), (8, 7));
+        assert!([(6, 8), (10, 8)].contains(&choice(AiPersonality::Defensive)));
+    }
+
+    #[test]
+    fn personalities_weigh_the_two_sides_apart() {
+        let board = position(&[(6, 7), (7, 7), (8, 7)], &[(3, 3), (4, 3)]);
+        let score = |personality| {
+            weighted_evaluate(
+                &board,
+                StoneColor::Black,
+                personality,
+                rules::DEFAULT_WIN_LENGTH,
+            )
+        };
+        let balanced = score(AiPersonality::Balanced);
+        assert_eq!(
+            balanced,
+            evaluate(&board, StoneColor::Black, rules::DEFAULT_WIN_LENGTH)
+        );
+        assert!(score(AiPersonality::Aggressive) > balanced);
+        assert!(score(AiPersonality::Defensive) < balanced);
+    }
+}
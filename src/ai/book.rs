@@ -0,0 +1,142 @@
+//! Replies to the first few moves of a game, looked up instead of searched.
+//!
+//! A position is keyed by its hash under whichever of the board's eight rotations and
+//! reflections gives the smallest one, so each line of the book also covers every position
+//! that is the same up to symmetry.
+
+use std::collections::HashMap;
+
+use crate::{
+    game_core::ZOBRIST_KEYS,
+    rules::{self, RuleSet, Variant},
+    BoardState, StoneColor,
+};
+
+use super::AiDifficulty;
+
+/// The stones so far as offsets from the center, Black's first and then alternating, and the
+/// reply to them.
+type BookLine = (&'static [(i32, i32)], (i32, i32));
+
+const LINES: [BookLine; 6] = [
+    // Black opens in the middle
+    (&[], (0, 0)),
+    // White answers diagonally, which is harder for Black to press than a direct reply
+    (&[(0, 0)], (1, 1)),
+    // Against the direct reply, the point diagonal to the center and next to White's stone
+    (&[(0, 0), (0, 1)], (1, 1)),
+    // Against the diagonal reply, a second diagonal that leaves White's stone outside it
+    (&[(0, 0), (1, 1)], (1, -1)),
+    // White caps the open end of Black's diagonal pair away from its own stone
+    (&[(0, 0), (0, 1), (1, 1)], (-1, -1)),
+    (&[(0, 0), (1, 1), (1, -1)], (-1, 1)),
+];
+
+/// Beyond this many stones the book has nothing to say.
+const MAX_BOOK_STONES: usize = 3;
+
+/// The book's reply for `color` on `board`, if the position is in it. Only for five in a row
+/// with Black moving first, and not on Easy, which is meant to be beatable from the start.
+/// A reply `variant` doesn't allow is left to the search.
+pub fn book_move(
+    board: &BoardState,
+    color: StoneColor,
+    difficulty: AiDifficulty,
+    rules: &RuleSet,
+    variant: Variant,
+) -> Option<(usize, usize)> {
+    let size = board.size();
+    let count = board.stone_count();
+    // Even boards have no center point for the symmetries to turn about
+    if difficulty == AiDifficulty::Easy
+        || rules.win_length != rules::DEFAULT_WIN_LENGTH
+        || size.is_multiple_of(2)
+        || count > MAX_BOOK_STONES
+        || color != move_color(count)
+    {
+        return None;
+    }
+
+    let center = (size / 2) as i32;
+    let mut stones = Vec::new();
+    for row in 0..size {
+        for col in 0..size {
+            if let Some(color) = board.get(col, row) {
+                stones.push(((col as i32 - center, row as i32 - center), color));
+            }
+        }
+    }
+    let (key, symmetry) = normalized_key(&stones, size);
+    let reply = transform(inverse(symmetry), *book(size).get(&key)?);
+    let (col, row) = ((reply.0 + center) as usize, (reply.1 + center) as usize);
+    let legal = board.get(col, row).is_none()
+        && rules::forbidden_move(board, col, row, color, rules).is_none()
+        && rules::variant_move_legal(board, col, row, color, variant);
+    legal.then_some((col, row))
+}
+
+/// The book for a `size` board, with each line's reply turned the way its position was to
+/// find its key.
+fn book(size: usize) -> HashMap<u64, (i32, i32)> {
+    LINES
+        .iter()
+        .map(|&(line, reply)| {
+            let stones: Vec<_> = line
+                .iter()
+                .enumerate()
+                .map(|(number, &offset)| (offset, move_color(number)))
+                .collect();
+            let (key, symmetry) = normalized_key(&stones, size);
+            (key, transform(symmetry, reply))
+        })
+        .collect()
+}
+
+/// Who plays the stone after `count` stones, with Black first.
+fn move_color(count: usize) -> StoneColor {
+    if count.is_multiple_of(2) {
+        StoneColor::Black
+    } else {
+        StoneColor::White
+    }
+}
+
+/// The smallest Zobrist hash of `stones`, given as offsets from the center of a `size`
+/// board, under any of the eight symmetries, and the symmetry that gives it.
+fn normalized_key(stones: &[((i32, i32), StoneColor)], size: usize) -> (u64, usize) {
+    let center = (size / 2) as i32;
+    (0..8)
+        .map(|symmetry| {
+            let key = stones.iter().fold(0, |key, &(offset, color)| {
+                let (x, y) = transform(symmetry, offset);
+                key ^ ZOBRIST_KEYS.key((x + center) as usize, (y + center) as usize, color)
+            });
+            (key, symmetry)
+        })
+        .min()
+        .expect("there are eight symmetries")
+}
+
+/// Applies one of the square's symmetries to an offset from the center: the four rotations
+/// by a quarter turn, then the four reflections.
+pub fn transform(symmetry: usize, (x, y): (i32, i32)) -> (i32, i32) {
+    match symmetry {
+        0 => (x, y),
+        1 => (-y, x),
+        2 => (-x, -y),
+        3 => (y, -x),
+        4 => (-x, y),
+        5 => (x, -y),
+        6 => (y, x),
+        _ => (-y, -x),
+    }
+}
+
+/// The symmetry that undoes `symmetry`; only the quarter turns aren't their own inverse.
+fn inverse(symmetry: usize) -> usize {
+    match symmetry {
+        1 => 3,
+        3 => 1,
+        other => other,
+    }
+}
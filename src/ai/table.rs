@@ -0,0 +1,176 @@
+//! Scores of positions the search has already been through, so a position reached by moves
+//! in a different order is only searched once.
+
+use std::collections::HashMap;
+
+use crate::{BoardState, StoneColor};
+
+/// How a cached score relates to the position's true value, given the alpha-beta window it
+/// was searched with.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bound {
+    Exact,
+    /// The search failed high: the value is at least the score.
+    Lower,
+    /// The search failed low: the value is at most the score.
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CachedEval {
+    pub depth: u8,
+    pub extensions: u8,
+    pub score: i32,
+    pub bound: Bound,
+    /// The reply that scored best, from which the expected line of play is read back; `None`
+    /// at the leaves.
+    pub best: Option<(usize, usize)>,
+}
+
+/// Cached results for one move's search; a new one is made for every move.
+#[derive(Default)]
+pub struct TranspositionTable {
+    entries: HashMap<u64, CachedEval>,
+}
+
+impl TranspositionTable {
+    /// The same stones score differently depending on whose turn it is.
+    pub fn key(board: &BoardState, color: StoneColor) -> u64 {
+        board.hash_to_move(color)
+    }
+
+    /// The score cached for `key`, if it came from a search of exactly this depth and these
+    /// extensions; wins score by how soon they come, so another depth could give another
+    /// score.
+    pub fn probe(&self, key: u64, depth: u8, extensions: u8) -> Option<CachedEval> {
+        self.entries
+            .get(&key)
+            .filter(|entry| entry.depth == depth && entry.extensions == extensions)
+            .copied()
+    }
+
+    pub fn store(&mut self, key: u64, entry: CachedEval) {
+        self.entries.insert(key, entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{Search, SearchRun, SearchStats, INFINITY};
+
+    fn fresh_run() -> SearchRun {
+        SearchRun {
+            table: TranspositionTable::default(),
+            stats: SearchStats::default(),
+            deadline: None,
+            timed_out: false,
+        }
+    }
+
+    /// A small middle game with Black to move.
+    fn position() -> BoardState {
+        let mut board = BoardState::new(15);
+        for (col, row) in [(7, 7), (8, 8), (6, 8)] {
+            board.set(col, row, StoneColor::Black);
+        }
+        for (col, row) in [(8, 7), (7, 8), (9, 6)] {
+            board.set(col, row, StoneColor::White);
+        }
+        board
+    }
+
+    #[test]
+    fn key_tells_the_player_to_move_apart() {
+        let board = position();
+        assert_eq!(
+            TranspositionTable::key(&board, StoneColor::Black),
+            board.hash()
+        );
+        assert_ne!(
+            TranspositionTable::key(&board, StoneColor::Black),
+            TranspositionTable::key(&board, StoneColor::White)
+        );
+        // The same key the repetition count uses
+        for color in [StoneColor::Black, StoneColor::White] {
+            assert_eq!(
+                TranspositionTable::key(&board, color),
+                board.hash_to_move(color)
+            );
+        }
+    }
+
+    #[test]
+    fn probe_needs_the_same_depth() {
+        let mut table = TranspositionTable::default();
+        let entry = CachedEval {
+            depth: 2,
+            extensions: 0,
+            score: 40,
+            bound: Bound::Exact,
+            best: Some((7, 7)),
+        };
+        table.store(1, entry);
+        assert_eq!(table.probe(1, 2, 0).map(|entry| entry.score), Some(40));
+        assert!(table.probe(1, 3, 0).is_none());
+        assert!(table.probe(1, 2, 1).is_none());
+        assert!(table.probe(2, 2, 0).is_none());
+    }
+
+    #[test]
+    fn cached_scores_match_a_fresh_search() {
+        let search = Search {
+            depth: 3,
+            threat_extensions: 0,
+            forced_win_depth: 0,
+            ..Search::default()
+        };
+        let mut board = position();
+        let color = StoneColor::Black;
+        let mut run = fresh_run();
+        let score = search.negamax(&mut board, color, 3, 0, -INFINITY, INFINITY, &mut run);
+
+        let root = run
+            .table
+            .probe(TranspositionTable::key(&board, color), 3, 0)
+            .expect("the root is cached");
+        assert_eq!(root.bound, Bound::Exact);
+        assert_eq!(root.score, score);
+        let mut again = fresh_run();
+        let fresh = search.negamax(&mut board, color, 3, 0, -INFINITY, INFINITY, &mut again);
+        assert_eq!(fresh, score);
+
+        // Each reply searched below the root was cached with bounds a fresh search keeps to
+        let mut checked = 0;
+        for (col, row) in search.moves(&board, color) {
+            board.set(col, row, color);
+            let key = TranspositionTable::key(&board, color.opponent());
+            if let Some(entry) = run.table.probe(key, 2, 0) {
+                let mut own = fresh_run();
+                let fresh = search.negamax(
+                    &mut board,
+                    color.opponent(),
+                    2,
+                    0,
+                    -INFINITY,
+                    INFINITY,
+                    &mut own,
+                );
+                match entry.bound {
+                    Bound::Exact => assert_eq!(fresh, entry.score),
+                    Bound::Lower => assert!(fresh >= entry.score),
+                    Bound::Upper => assert!(fresh <= entry.score),
+                }
+                checked += 1;
+            }
+            board.remove(col, row);
+        }
+        assert!(checked > 0);
+
+        // Searching again with the table filled answers the root straight from it
+        let hits = run.stats.table_hits;
+        let cached = search.negamax(&mut board, color, 3, 0, -INFINITY, INFINITY, &mut run);
+        assert_eq!(cached, score);
+        assert_eq!(run.stats.table_hits, hits + 1);
+    }
+}
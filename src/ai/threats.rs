@@ -0,0 +1,357 @@
+//! Threat-space search: looks for a forced win built only from fours and open threes.
+//!
+//! Each attacking move must leave a threat the defender has to answer, so only the few
+//! replies that stop it (plus the defender's own fours, which demand an answer in turn) need
+//! checking. That keeps the tree narrow enough to read much deeper than the full search.
+
+use crate::{
+    rules::{self, RuleSet},
+    BoardState, StoneColor,
+};
+
+/// How far along a line a threat's stones and empty points can reach from the stone making it.
+const REACH: i32 = 4;
+
+/// Searches for a win `color` can force, moving next, with at most `max_depth` of its own
+/// moves, each making a four or an open three. Returns those moves along the main line, the
+/// last being the five.
+pub fn find_forced_win(
+    board: &BoardState,
+    color: StoneColor,
+    max_depth: u8,
+    rules: &RuleSet,
+) -> Option<Vec<(usize, usize)>> {
+    let mut board = board.clone();
+    ThreatSearch { rules: *rules }.attack(&mut board, color, max_depth)
+}
+
+/// The kinds of threat the players are warned about.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ThreatKind {
+    /// One more stone makes an open four.
+    OpenThree,
+    /// One more stone makes five, at a point the opponent can block.
+    Four,
+    /// Two points make five; the opponent can't block both.
+    OpenFour,
+}
+
+impl ThreatKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ThreatKind::OpenThree => "an open three",
+            ThreatKind::Four => "a four",
+            ThreatKind::OpenFour => "an open four",
+        }
+    }
+}
+
+/// The strongest threat made by the `color` stone just placed at `(col, row)`, if any.
+pub fn threat_made(
+    board: &BoardState,
+    col: usize,
+    row: usize,
+    color: StoneColor,
+    rules: &RuleSet,
+) -> Option<ThreatKind> {
+    let mut board = board.clone();
+    let threat = ThreatSearch { rules: *rules }.threat(&mut board, col, row, color)?;
+    Some(match threat {
+        Threat::Four(points) if points.len() >= 2 => ThreatKind::OpenFour,
+        Threat::Four(_) => ThreatKind::Four,
+        Threat::OpenThree { .. } => ThreatKind::OpenThree,
+    })
+}
+
+/// What a freshly placed stone threatens.
+enum Threat {
+    /// The points where the next stone makes five; two of them can't both be blocked.
+    Four(Vec<(usize, usize)>),
+    /// One more stone along `(dx, dy)` makes an open four.
+    OpenThree { dx: i32, dy: i32 },
+}
+
+struct ThreatSearch {
+    rules: RuleSet,
+}
+
+impl ThreatSearch {
+    /// A forced win for `color`, to move, within `depth` moves.
+    fn attack(
+        &self,
+        board: &mut BoardState,
+        color: StoneColor,
+        depth: u8,
+    ) -> Option<Vec<(usize, usize)>> {
+        if depth == 0 {
+            return None;
+        }
+
+        let candidates = super::candidate_moves(board, 2);
+        if let Some(&cell) = candidates
+            .iter()
+            .find(|&&(col, row)| self.wins_at(board, col, row, color))
+        {
+            return Some(vec![cell]);
+        }
+
+        // A four of the opponent's must be blocked, and the block has to threaten in turn
+        let blocks: Vec<_> = candidates
+            .iter()
+            .copied()
+            .filter(|&(col, row)| self.wins_at(board, col, row, color.opponent()))
+            .collect();
+        let moves = match blocks.len() {
+            0 => candidates
+                .into_iter()
+                .filter(|&(col, row)| {
+                    rules::forbidden_move(board, col, row, color, &self.rules).is_none()
+                        && has_line_support(board, col, row, color)
+                })
+                .collect(),
+            1 => blocks,
+            _ => return None,
+        };
+
+        for (col, row) in moves {
+            board.set(col, row, color);
+            let line = self
+                .threat(board, col, row, color)
+                .and_then(|threat| self.defend(board, color, col, row, threat, depth - 1));
+            board.remove(col, row);
+            if let Some(mut line) = line {
+                line.insert(0, (col, row));
+                return Some(line);
+            }
+        }
+        None
+    }
+
+    /// Whether `attacker` still wins within `depth` moves whatever the defender does about
+    /// `threat`, made by the stone at `(col, row)`. Returns the attacker's continuation.
+    fn defend(
+        &self,
+        board: &mut BoardState,
+        attacker: StoneColor,
+        col: usize,
+        row: usize,
+        threat: Threat,
+        depth: u8,
+    ) -> Option<Vec<(usize, usize)>> {
+        let defender = attacker.opponent();
+        let candidates = super::candidate_moves(board, 2);
+        if candidates
+            .iter()
+            .any(|&(c, r)| self.wins_at(board, c, r, defender))
+        {
+            return None;
+        }
+
+        let replies = match threat {
+            Threat::Four(points) if points.len() >= 2 => {
+                return (depth > 0).then(|| points[..1].to_vec())
+            }
+            Threat::Four(points) => points,
+            // An open four takes two more moves, and the defender may block anywhere along the
+            // line or counter with a four of their own
+            Threat::OpenThree { .. } if depth < 2 => return None,
+            Threat::OpenThree { dx, dy } => {
+                let mut replies: Vec<_> = line_points(board, col, row, dx, dy)
+                    .filter(|&(c, r)| board.get(c, r).is_none())
+                    .collect();
+                let counters: Vec<_> = candidates
+                    .into_iter()
+                    .filter(|&(c, r)| {
+                        !replies.contains(&(c, r)) && self.makes_four(board, c, r, defender)
+                    })
+                    .collect();
+                replies.extend(counters);
+                replies
+            }
+        };
+
+        let mut main_line = None;
+        for (c, r) in replies {
+            if rules::forbidden_move(board, c, r, defender, &self.rules).is_some() {
+                continue;
+            }
+            board.set(c, r, defender);
+            let line = self.attack(board, attacker, depth);
+            board.remove(c, r);
+            main_line.get_or_insert(line?);
+        }
+        // With every answer forbidden to them, the defender can't stop the threat at all
+        main_line.or_else(|| self.attack(board, attacker, depth))
+    }
+
+    /// The threat made by the `color` stone just placed at `(col, row)`, if any.
+    fn threat(
+        &self,
+        board: &mut BoardState,
+        col: usize,
+        row: usize,
+        color: StoneColor,
+    ) -> Option<Threat> {
+        // A four needs three more stones along its line and a three two
+        let supported = |board: &BoardState, dx, dy, needed| {
+            line_points(board, col, row, dx, dy)
+                .filter(|&(c, r)| board.get(c, r) == Some(color))
+                .count()
+                >= needed
+        };
+
+        let mut fives = Vec::new();
+        for &(dx, dy) in &rules::DIRECTIONS {
+            if !supported(board, dx, dy, 3) {
+                continue;
+            }
+            for (c, r) in line_points(board, col, row, dx, dy) {
+                if !fives.contains(&(c, r)) && self.wins_at(board, c, r, color) {
+                    fives.push((c, r));
+                }
+            }
+        }
+        if !fives.is_empty() {
+            return Some(Threat::Four(fives));
+        }
+
+        rules::DIRECTIONS.iter().find_map(|&(dx, dy)| {
+            if !supported(board, dx, dy, 2) {
+                return None;
+            }
+            let points: Vec<_> = line_points(board, col, row, dx, dy).collect();
+            let makes_open_four = points.iter().any(|&(c, r)| {
+                if board.get(c, r).is_some()
+                    || rules::forbidden_move(board, c, r, color, &self.rules).is_some()
+                {
+                    return false;
+                }
+                board.set(c, r, color);
+                let fives = line_points(board, col, row, dx, dy)
+                    .filter(|&(x, y)| self.wins_at(board, x, y, color))
+                    .count();
+                board.remove(c, r);
+                fives >= 2
+            });
+            makes_open_four.then_some(Threat::OpenThree { dx, dy })
+        })
+    }
+
+    /// Whether `color` playing at `(col, row)` would leave a point where it makes five.
+    fn makes_four(
+        &self,
+        board: &mut BoardState,
+        col: usize,
+        row: usize,
+        color: StoneColor,
+    ) -> bool {
+        if rules::forbidden_move(board, col, row, color, &self.rules).is_some() {
+            return false;
+        }
+        board.set(col, row, color);
+        let four = rules::DIRECTIONS.iter().any(|&(dx, dy)| {
+            line_points(board, col, row, dx, dy).any(|(c, r)| self.wins_at(board, c, r, color))
+        });
+        board.remove(col, row);
+        four
+    }
+
+    /// Whether `color` may play at the empty point `(col, row)` and make five there.
+    fn wins_at(&self, board: &mut BoardState, col: usize, row: usize, color: StoneColor) -> bool {
+        if board.get(col, row).is_some()
+            || rules::forbidden_move(board, col, row, color, &self.rules).is_some()
+        {
+            return false;
+        }
+        board.set(col, row, color);
+        let five = rules::check_win(board, col, row, color, &self.rules).is_some();
+        board.remove(col, row);
+        five
+    }
+}
+
+/// The on-board points within `REACH` of `(col, row)` along `(dx, dy)`, itself excluded.
+fn line_points(
+    board: &BoardState,
+    col: usize,
+    row: usize,
+    dx: i32,
+    dy: i32,
+) -> impl Iterator<Item = (usize, usize)> {
+    // Collected, so the board is free to be played on while the points are gone through
+    let points: Vec<_> = (-REACH..=REACH)
+        .filter(|&step| step != 0)
+        .filter_map(|step| board.offset(col, row, dx, dy, step))
+        .collect();
+    points.into_iter()
+}
+
+/// Cheap filter for attacking moves: a four or three needs at least two `color` stones
+/// already on one of the lines through `(col, row)`.
+fn has_line_support(board: &BoardState, col: usize, row: usize, color: StoneColor) -> bool {
+    rules::DIRECTIONS.iter().any(|&(dx, dy)| {
+        line_points(board, col, row, dx, dy)
+            .filter(|&(c, r)| board.get(c, r) == Some(color))
+            .count()
+            >= 2
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 15x15 board with the given Black and White stones.
+    fn position(black: &[(usize, usize)], white: &[(usize, usize)]) -> BoardState {
+        let mut board = BoardState::new(15);
+        for &(col, row) in black {
+            board.set(col, row, StoneColor::Black);
+        }
+        for &(col, row) in white {
+            board.set(col, row, StoneColor::White);
+        }
+        board
+    }
+
+    #[test]
+    fn finds_a_four_three() {
+        // (8, 7) makes a four along the row, blocked at one end, and an open three up the column
+        let board = position(
+            &[(5, 7), (6, 7), (7, 7), (8, 8), (8, 9)],
+            &[(4, 7), (6, 6), (7, 8)],
+        );
+        let rules = RuleSet::default();
+        let line = find_forced_win(&board, StoneColor::Black, 4, &rules).expect("a forced win");
+        assert_eq!(line[0], (8, 7));
+
+        // Black's moves alone, played out, have to end in five
+        let mut played = board.clone();
+        for &(col, row) in &line {
+            assert_eq!(played.get(col, row), None);
+            played.set(col, row, StoneColor::Black);
+        }
+        let &(col, row) = line.last().unwrap();
+        let five = rules::check_win(&played, col, row, StoneColor::Black, &rules).unwrap();
+        assert!(five.len() >= 5);
+    }
+
+    #[test]
+    fn no_forced_win_without_threats() {
+        let board = position(&[(7, 7), (8, 7)], &[(7, 8)]);
+        assert_eq!(
+            find_forced_win(&board, StoneColor::Black, 4, &RuleSet::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn an_open_four_against_comes_first() {
+        // Black's four-three is too slow against White's open four, which can't be blocked
+        let board = position(
+            &[(5, 7), (6, 7), (7, 7), (8, 8), (8, 9)],
+            &[(4, 7), (6, 6), (7, 8), (5, 3), (6, 3), (7, 3), (8, 3)],
+        );
+        let line = find_forced_win(&board, StoneColor::Black, 4, &RuleSet::default());
+        assert_eq!(line, None);
+    }
+}
@@ -0,0 +1,510 @@
+//! Aids for studying the position: the analysis overlay, hints, threat warnings, the marks
+//! on Black's forbidden points and the review of a finished game's mistakes.
+
+use std::{collections::HashMap, time::Duration};
+
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+
+use crate::{
+    ai, column_label, config, game_core, grid_to_world,
+    hud::{ButtonAction, MistakeText, Notice},
+    keys::{self, Action},
+    net,
+    replay::ReplayState,
+    rules, sgf, theme, AiOpponent, BoardConfig, BoardState, CurrentPlayer, GameStatus,
+    HistoryEntry, MoveHistory, StoneColor,
+};
+
+/// Whether empty cells are tinted by how much they'd help the player to move; toggled with V.
+#[derive(Resource)]
+pub struct AnalysisOverlay(pub bool);
+
+/// What the analysis overlay was last drawn for: whether it was on, the board size and hash,
+/// the player to move and the game status.
+pub type AnalyzedPosition = (bool, usize, u64, StoneColor, GameStatus);
+
+/// One tinted cell of the analysis overlay.
+#[derive(Component)]
+pub struct AnalysisMarker;
+
+/// Redraws the analysis overlay after every move, restart or toggle: each candidate cell is
+/// tinted green by how much `ai::evaluate` improves for the player to move if they play
+/// there, brightest for the best. Nothing is computed while the overlay is off, or when the
+/// board was only touched by a refused move and the position's hash is unchanged.
+pub fn analysis_system(
+    mut commands: Commands,
+    keys: keys::Keys,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<BoardConfig>,
+    board: Res<BoardState>,
+    rules: Res<rules::RuleSet>,
+    current_player: Res<CurrentPlayer>,
+    status: Res<GameStatus>,
+    mut overlay: ResMut<AnalysisOverlay>,
+    markers: Query<Entity, With<AnalysisMarker>>,
+    mut shown: Local<Option<AnalyzedPosition>>,
+) {
+    if keys.just_pressed(Action::Analysis) {
+        overlay.0 = !overlay.0;
+    }
+    let position = (
+        overlay.0,
+        board.size(),
+        board.hash(),
+        current_player.0,
+        *status,
+    );
+    if *shown == Some(position) {
+        return;
+    }
+    *shown = Some(position);
+
+    for entity in &markers {
+        commands.entity(entity).despawn();
+    }
+    if !overlay.0 || *status != GameStatus::InProgress {
+        return;
+    }
+
+    let color = current_player.0;
+    let mut board = board.clone();
+    let base = ai::evaluate(&board, color, rules.win_length);
+    let gains: Vec<_> = ai::candidate_moves(&board, ai::DEFAULT_RADIUS)
+        .into_iter()
+        .map(|(col, row)| {
+            board.set(col, row, color);
+            let gain = ai::evaluate(&board, color, rules.win_length) - base;
+            board.remove(col, row);
+            ((col, row), gain)
+        })
+        .filter(|&(_, gain)| gain > 0)
+        .collect();
+    let Some(best) = gains.iter().map(|&(_, gain)| gain).max() else {
+        return;
+    };
+
+    let side = config.cell_size() * 0.8;
+    let mesh = meshes.add(Mesh::from(shape::Quad::new(Vec2::splat(side))));
+    for ((col, row), gain) in gains {
+        let strength = gain as f32 / best as f32;
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: mesh.clone().into(),
+                transform: Transform::from_translation(
+                    grid_to_world(col, row, &config).extend(1.6),
+                ),
+                material: materials.add(ColorMaterial::from(Color::rgba(
+                    0.1,
+                    0.9,
+                    0.2,
+                    0.08 + 0.5 * strength,
+                ))),
+                ..Default::default()
+            },
+            AnalysisMarker,
+        ));
+    }
+}
+
+/// The move the search suggests for the player to move, blinking for a few seconds after H.
+#[derive(Component)]
+pub struct HintMarker {
+    pub timer: Timer,
+    /// The position it was worked out for; any move makes it stale.
+    pub hash: u64,
+}
+
+impl HintMarker {
+    pub const DURATION: f32 = 3.0;
+    /// How long the marker stays lit, and then dark, per blink.
+    pub const BLINK: f32 = 0.25;
+}
+
+/// Suggests a move on H or the Hint button when it's a human's turn here, using the Hard
+/// search the computer opponent would, and blinks it without placing anything.
+pub fn hint_system(
+    mut commands: Commands,
+    keys: keys::Keys,
+    time: Res<Time>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<BoardConfig>,
+    board: Res<BoardState>,
+    current_player: Res<CurrentPlayer>,
+    status: Res<GameStatus>,
+    (rules, variant): (Res<rules::RuleSet>, Res<rules::Variant>),
+    ai: Res<AiOpponent>,
+    replay: Res<ReplayState>,
+    role: Res<net::Role>,
+    mut notice: ResMut<Notice>,
+    mut hints: Query<(Entity, &mut HintMarker, &mut Visibility)>,
+    mut clicks: EventReader<ButtonAction>,
+) {
+    let clicked = clicks.iter().any(|&button| button == ButtonAction::Hint);
+    for (entity, mut hint, mut visibility) in &mut hints {
+        hint.timer.tick(time.delta());
+        if hint.timer.finished() || hint.hash != board.hash() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let lit = (hint.timer.elapsed_secs() / (2.0 * HintMarker::BLINK)).fract() < 0.5;
+        *visibility = if lit {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+
+    let color = current_player.0;
+    if !(keys.just_pressed(Action::Hint) || clicked)
+        || *status != GameStatus::InProgress
+        || replay.reviewing()
+        || ai.color == Some(color)
+        || !role.may_play(color)
+    {
+        return;
+    }
+
+    for (entity, ..) in &hints {
+        commands.entity(entity).despawn();
+    }
+    let (line, _) = ai::best_move(
+        &board,
+        color,
+        ai::AiDifficulty::Hard,
+        ai::AiPersonality::Balanced,
+        &rules,
+        *variant,
+        ai.think,
+    );
+    let (col, row) = line[0];
+    notice.show(format!("Hint: {}{}", column_label(col), row + 1));
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes
+                .add(Mesh::from(shape::Circle {
+                    radius: config.cell_size() * 0.3,
+                    ..Default::default()
+                }))
+                .into(),
+            material: materials.add(ColorMaterial::from(Color::rgba(0.1, 0.8, 1.0, 0.8))),
+            transform: Transform::from_translation(grid_to_world(col, row, &config).extend(2.55)),
+            ..Default::default()
+        },
+        HintMarker {
+            timer: Timer::from_seconds(HintMarker::DURATION, TimerMode::Once),
+            hash: board.hash(),
+        },
+    ));
+}
+
+/// Whether the player to move is told about a threat the last stone made; toggled with I.
+#[derive(Resource)]
+pub struct ThreatWarnings(pub bool);
+
+/// Flashes "Threat!" when the stone just played makes an open three or a four, so a beginner
+/// sees what they have to answer. Only a player who moves at this screen is warned.
+pub fn threat_warning_system(
+    keys: keys::Keys,
+    history: Res<MoveHistory>,
+    board: Res<BoardState>,
+    rules: Res<rules::RuleSet>,
+    current_player: Res<CurrentPlayer>,
+    status: Res<GameStatus>,
+    ai: Res<AiOpponent>,
+    role: Res<net::Role>,
+    replay: Res<ReplayState>,
+    mut warnings: ResMut<ThreatWarnings>,
+    mut notice: ResMut<Notice>,
+    mut shown_moves: Local<usize>,
+) {
+    if keys.just_pressed(Action::ThreatWarnings) {
+        warnings.0 = !warnings.0;
+        notice.show(if warnings.0 {
+            "Threat warnings on"
+        } else {
+            "Threat warnings off"
+        });
+    }
+
+    let placed = history.0.len() > *shown_moves;
+    *shown_moves = history.0.len();
+    let defender = current_player.0;
+    if !placed
+        || !warnings.0
+        || *status != GameStatus::InProgress
+        || replay.reviewing()
+        || ai.color == Some(defender)
+        || !role.may_play(defender)
+    {
+        return;
+    }
+    let Some(record) = history.0.last().and_then(HistoryEntry::stone) else {
+        return;
+    };
+    if record.color == defender {
+        return;
+    }
+    if let Some(kind) = ai::threat_made(&board, record.col, record.row, record.color, &rules) {
+        notice.show(format!(
+            "Threat! {} has {}",
+            record.color.name(),
+            kind.name()
+        ));
+    }
+}
+
+/// Half of the red X over a point the player to move may not play.
+#[derive(Component)]
+pub struct ForbiddenMark;
+
+/// Marks every point forbidden to the player to move with a red X, under Renju rules for
+/// Black and wherever an overline is barred to them, redrawn after each move since the
+/// forbidden points shift with the position.
+pub fn forbidden_marks_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<BoardConfig>,
+    board: Res<BoardState>,
+    current_player: Res<CurrentPlayer>,
+    status: Res<GameStatus>,
+    rules: Res<rules::RuleSet>,
+    marks: Query<Entity, With<ForbiddenMark>>,
+) {
+    let changed = board.is_changed() || current_player.is_changed() || status.is_changed();
+    if !changed || !rules.renju && rules.overline_forbidden == [false; 2] {
+        return;
+    }
+
+    for entity in &marks {
+        commands.entity(entity).despawn();
+    }
+    if *status != GameStatus::InProgress {
+        return;
+    }
+
+    let cell_size = config.cell_size();
+    let mesh = meshes.add(Mesh::from(shape::Quad::new(Vec2::new(
+        cell_size * 0.45,
+        cell_size * 0.08,
+    ))));
+    let material = materials.add(ColorMaterial::from(Color::rgb(0.9, 0.1, 0.1)));
+    for col in 0..config.size {
+        for row in 0..config.size {
+            if board.get(col, row).is_some()
+                || rules::forbidden_move(&board, col, row, current_player.0, &rules).is_none()
+            {
+                continue;
+            }
+            let translation = grid_to_world(col, row, &config).extend(1.6);
+            for angle in [std::f32::consts::FRAC_PI_4, -std::f32::consts::FRAC_PI_4] {
+                commands.spawn((
+                    MaterialMesh2dBundle {
+                        mesh: mesh.clone().into(),
+                        transform: Transform::from_translation(translation)
+                            .with_rotation(Quat::from_rotation_z(angle)),
+                        material: material.clone(),
+                        ..Default::default()
+                    },
+                    ForbiddenMark,
+                ));
+            }
+        }
+    }
+}
+
+/// While on, stepping through a review rates each move against the one the Hard search
+/// prefers, flagging those that gave away `MISTAKE_DROP` or more and showing the better move
+/// as a ghost stone. Toggled with B.
+#[derive(Resource, Default)]
+pub struct MistakeReview {
+    pub enabled: bool,
+    /// The game the verdicts are for; reviewing another starts over.
+    pub game: Vec<sgf::SgfMove>,
+    /// Verdicts by move index, worked out the first time the review steps onto each move.
+    pub verdicts: HashMap<usize, MoveVerdict>,
+}
+
+/// How a move compared with the search's choice from the same position.
+#[derive(Clone, Copy, Debug)]
+pub struct MoveVerdict {
+    pub best: (usize, usize),
+    /// How much lower `ai::evaluate` rates the position after the move than after `best`,
+    /// for the player who made it.
+    pub drop: i32,
+}
+
+/// An evaluation drop worth flagging: more than a closed three, less than an open three.
+pub const MISTAKE_DROP: i32 = 3_000;
+
+/// The search's move, as a translucent stone, where a flagged move should have gone.
+#[derive(Component)]
+pub struct MistakeGhost;
+
+/// Rates the move just stepped onto while the mistake review is on, and shows the verdict
+/// and, for a mistake, the better move.
+pub fn mistake_review_system(
+    mut commands: Commands,
+    keys: keys::Keys,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<BoardConfig>,
+    rules: Res<rules::RuleSet>,
+    variant: Res<rules::Variant>,
+    ai: Res<AiOpponent>,
+    theme: Res<theme::Theme>,
+    appearance: Res<config::Appearance>,
+    replay: Res<ReplayState>,
+    mut review: ResMut<MistakeReview>,
+    mut notice: ResMut<Notice>,
+    mut text: Query<&mut Text, With<MistakeText>>,
+    ghosts: Query<Entity, With<MistakeGhost>>,
+    mut drawn: Local<Option<(bool, usize, usize)>>,
+) {
+    if keys.just_pressed(Action::ReviewMistakes) {
+        review.enabled = !review.enabled;
+        notice.show(if review.enabled {
+            "Mistake review on: step through the game with Ctrl+Left, then the arrow keys"
+        } else {
+            "Mistake review off"
+        });
+    }
+    let state = (review.enabled, replay.cursor, replay.moves.len());
+    if *drawn == Some(state) {
+        return;
+    }
+    *drawn = Some(state);
+
+    for entity in &ghosts {
+        commands.entity(entity).despawn();
+    }
+    let text = &mut text.single_mut().sections[0].value;
+    text.clear();
+    // Once the review has stepped back onto the last move, play goes on from there
+    if !review.enabled || !replay.reviewing() || replay.cursor == 0 {
+        return;
+    }
+    if review.game != replay.moves {
+        review.game = replay.moves.clone();
+        review.verdicts.clear();
+    }
+
+    let index = replay.cursor - 1;
+    let verdict = match review.verdicts.get(&index) {
+        Some(&verdict) => Some(verdict),
+        None => {
+            let verdict = rate_move(
+                &replay.moves,
+                index,
+                config.size,
+                &rules,
+                *variant,
+                ai.think,
+            );
+            if let Some(verdict) = verdict {
+                review.verdicts.insert(index, verdict);
+            }
+            verdict
+        }
+    };
+    let Some(verdict) = verdict else {
+        return;
+    };
+
+    let (col, row, color) = replay.moves[index];
+    let played = format!("{}{}", column_label(col), row + 1);
+    if verdict.drop < MISTAKE_DROP {
+        *text = format!("Move {} {played}: fine", index + 1);
+        return;
+    }
+    let (best_col, best_row) = verdict.best;
+    *text = format!(
+        "Move {} {played}: mistake, gave away {}; better {}{}",
+        index + 1,
+        verdict.drop,
+        column_label(best_col),
+        best_row + 1
+    );
+    let stone = theme.stone(color);
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes
+                .add(Mesh::from(shape::Circle {
+                    radius: config.cell_size() * appearance.stone_radius_ratio,
+                    ..Default::default()
+                }))
+                .into(),
+            material: materials.add(ColorMaterial::from(stone.with_a(0.5))),
+            transform: Transform::from_translation(
+                grid_to_world(best_col, best_row, &config).extend(2.4),
+            ),
+            ..Default::default()
+        },
+        MistakeGhost,
+    ));
+}
+
+/// Sets up the position before `moves[index]` and compares that move with the Hard search's
+/// choice there. `None` if the moves can't be replayed.
+pub fn rate_move(
+    moves: &[sgf::SgfMove],
+    index: usize,
+    size: usize,
+    rules: &rules::RuleSet,
+    variant: rules::Variant,
+    think: Duration,
+) -> Option<MoveVerdict> {
+    let mut board = BoardState::for_variant(size, variant);
+    // Pairs captured by Black and by White, for Pente
+    let mut pairs = [0, 0];
+    for &(col, row, color) in &moves[..index] {
+        let placement = game_core::apply_move(
+            &mut board,
+            col,
+            row,
+            color,
+            rules,
+            variant,
+            pairs[color as usize],
+        )
+        .ok()?;
+        pairs[color as usize] += placement.captured.len() / 2;
+    }
+
+    let (col, row, color) = moves[index];
+    let (line, _) = ai::best_move(
+        &board,
+        color,
+        ai::AiDifficulty::Hard,
+        ai::AiPersonality::Balanced,
+        rules,
+        variant,
+        think,
+    );
+    let best = line[0];
+    let score = |(col, row)| {
+        let mut after = board.clone();
+        game_core::apply_move(
+            &mut after,
+            col,
+            row,
+            color,
+            rules,
+            variant,
+            pairs[color as usize],
+        )
+        .ok()
+        .map(|_| ai::evaluate(&after, color, rules.win_length))
+    };
+    let drop = if best == (col, row) {
+        0
+    } else {
+        score(best)? - score((col, row))?
+    };
+    Some(MoveVerdict {
+        best,
+        drop: drop.max(0),
+    })
+}
@@ -0,0 +1,227 @@
+//! The archive of finished games: each one is written to `archive/` as an SGF record when it
+//! ends, unless `auto_archive = false` in `gomoku.toml`. `--analyze-dir <path>` replays every
+//! SGF record in a directory, such as the archive, without opening a window and prints a
+//! summary of each game, for going through a collection of games.
+
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::warn;
+
+use crate::{
+    ai, column_label,
+    game_core::{self, BoardState, Outcome, PositionCounts, StoneColor},
+    rules::{RuleSet, Variant},
+    save, sgf, MoveHistory,
+};
+
+/// Where finished games are archived, relative to the working directory.
+pub const ARCHIVE_DIR: &str = "archive";
+
+/// Writes the finished game in `history`, played on a `size` board under `rules` and
+/// `variant`, to a new record in `ARCHIVE_DIR` named by the time, such as
+/// `game-20261014-153000.sgf`, and returns its path. The record gives the result from
+/// `winner`, the rules and the date. A second game in the same second gets a numbered name
+/// instead of overwriting the first.
+pub fn save_game(
+    history: &MoveHistory,
+    size: usize,
+    rules: &RuleSet,
+    variant: Variant,
+    winner: Option<StoneColor>,
+) -> io::Result<PathBuf> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (year, month, day, ..) = save::civil_time(now);
+    let info = sgf::GameInfo {
+        result: sgf::result_value(winner, history.resigned().is_some()),
+        date: format!("{year:04}-{month:02}-{day:02}"),
+    };
+    let record = sgf::to_sgf_with_info(history, size, rules, variant, &info);
+
+    std::fs::create_dir_all(ARCHIVE_DIR)?;
+    let name = save::default_slot_name();
+    for number in 1.. {
+        let file_name = if number == 1 {
+            format!("{name}.sgf")
+        } else {
+            format!("{name}-{number}.sgf")
+        };
+        let path = Path::new(ARCHIVE_DIR).join(file_name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(record.as_bytes())?;
+                return Ok(path);
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    unreachable!("some numbered name is free")
+}
+
+/// How far the evaluation must move in one move, from Black's side, for the move to be
+/// called a critical moment: an open four or more.
+const CRITICAL_SWING: i32 = 50_000;
+/// The most critical moments listed per game, biggest swing first.
+const MAX_CRITICAL_MOMENTS: usize = 3;
+
+/// A move after which the evaluation jumped.
+struct CriticalMoment {
+    /// 1-based.
+    number: usize,
+    col: usize,
+    row: usize,
+    color: StoneColor,
+    before: i32,
+    after: i32,
+}
+
+/// Prints a summary of every `.sgf` file in `dir`, in name order, replayed under the rules
+/// each record gives, or `rules` and `variant` for one that gives none. Files that can't be
+/// read or replayed are reported and skipped.
+pub fn run(dir: &Path, rules: &RuleSet, variant: Variant) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            warn!("Could not read {}: {err}", dir.display());
+            return;
+        }
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("sgf"))
+        })
+        .collect();
+    paths.sort();
+
+    let mut analysed = 0;
+    for path in &paths {
+        if analyse(path, rules, variant) {
+            analysed += 1;
+        }
+    }
+    println!(
+        "Analysed {analysed} of {} SGF files in {}",
+        paths.len(),
+        dir.display()
+    );
+}
+
+/// Replays the record at `path` and prints its summary. Returns false, having said why, if
+/// the record couldn't be read or one of its moves isn't legal.
+fn analyse(path: &Path, rules: &RuleSet, variant: Variant) -> bool {
+    let name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => {
+            warn!("Skipping {name}: {err}");
+            return false;
+        }
+    };
+    let record = sgf::board_size(&text).and_then(|size| {
+        let recorded_rules = sgf::recorded_rules(&text)?;
+        Ok((
+            size,
+            sgf::from_sgf(&text)?,
+            sgf::recorded_result(&text)?,
+            recorded_rules,
+        ))
+    });
+    let (size, moves, recorded, recorded_rules) = match record {
+        Ok(record) => record,
+        Err(err) => {
+            warn!("Skipping {name}: {err}");
+            return false;
+        }
+    };
+    let (rules, variant) = recorded_rules.unwrap_or((*rules, variant));
+    let rules = &rules;
+
+    let mut board = BoardState::for_variant(size, variant);
+    // Pairs captured by Black and by White, for Pente
+    let mut pairs = [0, 0];
+    let mut positions = PositionCounts::default();
+    if let Some(&(_, _, first)) = moves.first() {
+        positions.start(&board, first);
+    }
+    let mut outcome = Outcome::Continues;
+    let mut moments = Vec::new();
+    for (index, &(col, row, color)) in moves.iter().enumerate() {
+        if outcome != Outcome::Continues {
+            warn!("Skipping {name}: it goes on after the game ended at move {index}");
+            return false;
+        }
+        let before = ai::evaluate(&board, StoneColor::Black, rules.win_length);
+        let mut placement = match game_core::apply_move(
+            &mut board,
+            col,
+            row,
+            color,
+            rules,
+            variant,
+            pairs[color as usize],
+        ) {
+            Ok(placement) => placement,
+            Err(err) => {
+                warn!(
+                    "Skipping {name}: move {} at {}{} is not allowed ({err:?})",
+                    index + 1,
+                    column_label(col),
+                    row + 1
+                );
+                return false;
+            }
+        };
+        placement.count_position(&mut positions, &board, color, rules);
+        pairs[color as usize] += placement.captured.len() / 2;
+        outcome = placement.outcome;
+
+        let after = ai::evaluate(&board, StoneColor::Black, rules.win_length);
+        // A finished game has nothing more to weigh
+        if outcome == Outcome::Continues && (after - before).abs() >= CRITICAL_SWING {
+            moments.push(CriticalMoment {
+                number: index + 1,
+                col,
+                row,
+                color,
+                before,
+                after,
+            });
+        }
+    }
+
+    let result = match outcome {
+        Outcome::Won { winner, .. } => format!("{} won", winner.name()),
+        Outcome::Draw => "drawn".to_string(),
+        Outcome::Continues => match recorded {
+            Some(result) => format!("no five on the board, recorded as {result}"),
+            None => "unfinished".to_string(),
+        },
+    };
+    println!("{name}: {} moves on {size}x{size}, {result}", moves.len());
+
+    moments.sort_by_key(|moment| std::cmp::Reverse((moment.after - moment.before).abs()));
+    for moment in moments.iter().take(MAX_CRITICAL_MOMENTS) {
+        println!(
+            "  move {} ({} {}{}): evaluation {:+} -> {:+}",
+            moment.number,
+            moment.color.name(),
+            column_label(moment.col),
+            moment.row + 1,
+            moment.before,
+            moment.after
+        );
+    }
+    true
+}
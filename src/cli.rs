@@ -0,0 +1,292 @@
+use std::{path::PathBuf, time::Duration};
+
+use bevy::prelude::warn;
+
+use crate::{
+    ai,
+    config::GameConfig,
+    rules::{self, HANDICAPS, WIN_LENGTHS},
+    save, StoneColor, DEFAULT_BOARD_SIZE, SUPPORTED_BOARD_SIZES,
+};
+
+/// Options read from the command line; anything missing keeps its default.
+#[derive(Clone, Debug)]
+pub struct Args {
+    /// `--size <n>`: lines per side.
+    pub size: usize,
+    /// `--ai`: let the computer play White.
+    pub ai: bool,
+    /// `--difficulty <easy|medium|hard>`: how strongly the computer plays.
+    pub difficulty: ai::AiDifficulty,
+    /// `--personality <balanced|aggressive|defensive>`: what the computer favors.
+    pub personality: ai::AiPersonality,
+    /// `--win-length <n>`: how many stones in a row win.
+    pub win_length: usize,
+    /// `--no-overline`: neither color may make a line longer than `win_length`;
+    /// `--no-overline-for <black|white>` bars only that color. Indexed by `StoneColor as usize`.
+    pub overline_forbidden: [bool; 2],
+    /// `--renju`: forbid Black's double threes, double fours and overlines.
+    pub renju: bool,
+    /// `--center-opening`: the first stone must go on the center point.
+    pub first_move_center: bool,
+    /// `--early-draw`: call the game drawn once neither side can make a line any more.
+    pub early_draw: bool,
+    /// `--repetition-draw`: call the game drawn when a position comes up a third time, which
+    /// only Pente captures can bring about.
+    pub repetition_draw: bool,
+    /// `--handicap <n>`: Black starts with `n` stones on the star points and White moves
+    /// first.
+    pub handicap: usize,
+    /// `--swap2`: open with the swap2 protocol; two human players only.
+    pub swap2: bool,
+    /// `--pie`: after the first stone the second player may swap colors; two human
+    /// players only.
+    pub pie: bool,
+    /// `--confirm`: place a stone only on a second tap at the same spot, for touchscreens.
+    pub confirm: bool,
+    /// `--drag`: place a stone where the mouse button is released rather than pressed, so a
+    /// press can be dragged to the exact point first.
+    pub drag: bool,
+    /// `--pente`: flanking two opposing stones captures them; five captures win. Black's
+    /// second stone goes at least three points from the center.
+    pub pente: bool,
+    /// `--torus`: play on a board whose edges join up, so lines wrap round.
+    pub toroidal: bool,
+    /// `--drill`: practice finding the right move in tactical positions instead of playing
+    /// a game.
+    pub drill: bool,
+    /// `--load <file>`: continue a game saved as SGF.
+    pub load: Option<PathBuf>,
+    /// `--load-save <name>`: continue the game saved under `saves/` as `name`.
+    pub load_save: Option<String>,
+    /// `--save-as <name>`: the name F5 saves the game under; one from the date without it.
+    pub save_as: Option<String>,
+    /// `--saves`: list the games under `saves/` and exit.
+    pub list_saves: bool,
+    /// `--event-log <file>`: write the timeline of the session's events there as JSON each
+    /// time a game ends.
+    pub event_log: Option<PathBuf>,
+    /// `--time <minutes>`: give each player this much thinking time; untimed without it.
+    pub time: Option<Duration>,
+    /// `--think <seconds>`: how long the computer may spend on each move.
+    pub think: Duration,
+    /// `--selfplay <n>`: let the computer play itself `n` times without a window and print
+    /// the results.
+    pub selfplay: Option<usize>,
+    /// `--analyze-dir <path>`: replay every SGF record in a directory without a window and
+    /// print a summary of each.
+    pub analyze_dir: Option<PathBuf>,
+    /// `--seed <n>`: seed the computer's random choices, to replay a game exactly.
+    pub seed: Option<u64>,
+    /// `--host <port>`: wait for a player to connect over the network and play first.
+    pub host: Option<u16>,
+    /// `--connect <address:port>`: join a game hosted with `--host`, as the other player or,
+    /// once that seat is taken, a spectator.
+    pub connect: Option<String>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            size: DEFAULT_BOARD_SIZE,
+            ai: false,
+            difficulty: ai::AiDifficulty::default(),
+            personality: ai::AiPersonality::default(),
+            win_length: rules::DEFAULT_WIN_LENGTH,
+            overline_forbidden: [false; 2],
+            renju: false,
+            first_move_center: false,
+            early_draw: false,
+            repetition_draw: false,
+            handicap: 0,
+            swap2: false,
+            pie: false,
+            confirm: false,
+            drag: false,
+            pente: false,
+            toroidal: false,
+            drill: false,
+            load: None,
+            load_save: None,
+            save_as: None,
+            list_saves: false,
+            event_log: None,
+            time: None,
+            think: ai::DEFAULT_THINK_TIME,
+            selfplay: None,
+            analyze_dir: None,
+            seed: None,
+            host: None,
+            connect: None,
+        }
+    }
+}
+
+impl Args {
+    /// Reads `args` over the defaults from `config`.
+    pub fn parse(config: &GameConfig, mut args: impl Iterator<Item = String>) -> Self {
+        let mut parsed = Args {
+            size: config.size,
+            difficulty: config.difficulty,
+            personality: config.ai_personality,
+            win_length: config.win_length,
+            overline_forbidden: config.overline_forbidden(),
+            renju: config.renju,
+            first_move_center: config.first_move_center,
+            early_draw: config.early_draw,
+            repetition_draw: config.repetition_draw,
+            handicap: config.handicap,
+            ..Args::default()
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--size" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(size) if SUPPORTED_BOARD_SIZES.contains(&size) => parsed.size = size,
+                    _ => warn!(
+                        "--size must be one of {:?}, using {}",
+                        SUPPORTED_BOARD_SIZES, parsed.size
+                    ),
+                },
+                "--ai" => parsed.ai = true,
+                "--difficulty" => match args.next().as_deref().and_then(ai::AiDifficulty::parse) {
+                    Some(difficulty) => parsed.difficulty = difficulty,
+                    None => warn!(
+                        "--difficulty must be easy, medium or hard, using {}",
+                        parsed.difficulty.name()
+                    ),
+                },
+                "--personality" => {
+                    match args.next().as_deref().and_then(ai::AiPersonality::parse) {
+                        Some(personality) => parsed.personality = personality,
+                        None => warn!(
+                            "--personality must be balanced, aggressive or defensive, using {}",
+                            parsed.personality.name()
+                        ),
+                    }
+                }
+                "--win-length" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(length) if WIN_LENGTHS.contains(&length) => parsed.win_length = length,
+                    _ => warn!(
+                        "--win-length must be between {} and {}, using {}",
+                        WIN_LENGTHS.start(),
+                        WIN_LENGTHS.end(),
+                        parsed.win_length
+                    ),
+                },
+                "--no-overline" => parsed.overline_forbidden = [true; 2],
+                "--no-overline-for" => match args.next().as_deref() {
+                    Some("black") => parsed.overline_forbidden[StoneColor::Black as usize] = true,
+                    Some("white") => parsed.overline_forbidden[StoneColor::White as usize] = true,
+                    _ => warn!("--no-overline-for must be black or white, ignoring it"),
+                },
+                "--renju" => parsed.renju = true,
+                "--center-opening" => parsed.first_move_center = true,
+                "--early-draw" => parsed.early_draw = true,
+                "--repetition-draw" => parsed.repetition_draw = true,
+                "--handicap" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(stones) if stones == 0 || HANDICAPS.contains(&stones) => {
+                        parsed.handicap = stones;
+                    }
+                    _ => warn!(
+                        "--handicap must be 0 or between {} and {}, using {}",
+                        HANDICAPS.start(),
+                        HANDICAPS.end(),
+                        parsed.handicap
+                    ),
+                },
+                "--swap2" => parsed.swap2 = true,
+                "--pie" => parsed.pie = true,
+                "--confirm" => parsed.confirm = true,
+                "--drag" => parsed.drag = true,
+                "--pente" => parsed.pente = true,
+                "--torus" => parsed.toroidal = true,
+                "--drill" => parsed.drill = true,
+                "--load" => match args.next() {
+                    Some(path) => parsed.load = Some(path.into()),
+                    None => warn!("--load needs a file name"),
+                },
+                "--load-save" => match args.next() {
+                    Some(name) if save::is_valid_slot_name(&name) => {
+                        parsed.load_save = Some(name);
+                    }
+                    _ => warn!("--load-save needs the name of a save, see --saves"),
+                },
+                "--save-as" => match args.next() {
+                    Some(name) if save::is_valid_slot_name(&name) => parsed.save_as = Some(name),
+                    _ => warn!(
+                        "--save-as needs a name of letters, digits, - and _, naming the save by \
+                         date instead"
+                    ),
+                },
+                "--saves" => parsed.list_saves = true,
+                "--event-log" => match args.next() {
+                    Some(path) => parsed.event_log = Some(path.into()),
+                    None => warn!("--event-log needs a file name"),
+                },
+                "--time" => match args.next().and_then(|value| value.parse::<u64>().ok()) {
+                    Some(minutes) if minutes > 0 => {
+                        parsed.time = Some(Duration::from_secs(minutes * 60));
+                    }
+                    _ => warn!("--time must be a whole number of minutes, playing untimed"),
+                },
+                "--think" => match args.next().and_then(|value| value.parse::<f64>().ok()) {
+                    Some(seconds) if seconds > 0.0 && seconds.is_finite() => {
+                        parsed.think = Duration::from_secs_f64(seconds);
+                    }
+                    _ => warn!(
+                        "--think must be a number of seconds, using {:?}",
+                        parsed.think
+                    ),
+                },
+                "--selfplay" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(games) if games > 0 => parsed.selfplay = Some(games),
+                    _ => warn!("--selfplay needs a number of games, opening the board instead"),
+                },
+                "--analyze-dir" => match args.next() {
+                    Some(path) => parsed.analyze_dir = Some(path.into()),
+                    None => warn!("--analyze-dir needs a directory, opening the board instead"),
+                },
+                "--seed" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(seed) => parsed.seed = Some(seed),
+                    None => warn!("--seed must be a whole number, picking one at random"),
+                },
+                "--host" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(port) => parsed.host = Some(port),
+                    None => warn!("--host needs a port number, playing locally"),
+                },
+                "--connect" => match args.next() {
+                    Some(addr) => parsed.connect = Some(addr),
+                    None => warn!("--connect needs an address such as 192.168.0.2:7777"),
+                },
+                _ => warn!("Ignoring unknown argument {arg:?}"),
+            }
+        }
+        parsed
+    }
+
+    /// Takes on `rules` and `variant` in place of the rule flags given, as for a game loaded
+    /// with its own rules.
+    pub fn set_rules(&mut self, rules: &rules::RuleSet, variant: rules::Variant) {
+        self.win_length = rules.win_length;
+        self.overline_forbidden = rules.overline_forbidden;
+        self.renju = rules.renju;
+        self.swap2 = rules.swap2;
+        self.pie = rules.pie;
+        self.first_move_center = rules.first_move_center;
+        self.early_draw = rules.early_draw;
+        self.repetition_draw = rules.repetition_draw;
+        self.pente = variant == rules::Variant::Pente;
+        self.toroidal = variant == rules::Variant::Toroidal;
+    }
+
+    /// The rules family the flags ask for.
+    pub fn variant(&self) -> rules::Variant {
+        if self.pente {
+            rules::Variant::Pente
+        } else if self.toroidal {
+            rules::Variant::Toroidal
+        } else {
+            rules::Variant::Standard
+        }
+    }
+}
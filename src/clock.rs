@@ -0,0 +1,117 @@
+//! The players' clocks in a timed game, which stop while the window is out of focus.
+
+use std::time::Duration;
+
+use bevy::{prelude::*, window::WindowFocused};
+
+use crate::{
+    hud::{ClockText, Notice},
+    online::Online,
+    replay::ReplayState,
+    CurrentPlayer, GameOver, GameStatus, StoneColor,
+};
+
+/// Time each player has left on their clock.
+#[derive(Clone, Copy, Debug)]
+pub struct PlayerClocks {
+    pub black: Duration,
+    pub white: Duration,
+    /// What both clocks start from, kept for restarts.
+    pub main_time: Duration,
+}
+
+impl PlayerClocks {
+    pub fn new(main_time: Duration) -> Self {
+        PlayerClocks {
+            black: main_time,
+            white: main_time,
+            main_time,
+        }
+    }
+
+    pub fn remaining_mut(&mut self, color: StoneColor) -> &mut Duration {
+        match color {
+            StoneColor::Black => &mut self.black,
+            StoneColor::White => &mut self.white,
+        }
+    }
+}
+
+/// The clocks of a timed game; `None` when started without `--time`.
+#[derive(Resource)]
+pub struct GameClocks(pub Option<PlayerClocks>);
+
+/// Whether the window has the keyboard focus.
+#[derive(Resource)]
+pub struct WindowFocus(pub bool);
+
+/// A dark veil over the whole window while it is out of focus.
+#[derive(Component)]
+pub struct IdleDimmer;
+
+/// Dims the window while it is out of focus and lights it up again when it is back; the
+/// clocks stop meanwhile, see `clock_system`.
+pub fn focus_system(
+    mut focus_events: EventReader<WindowFocused>,
+    mut focus: ResMut<WindowFocus>,
+    mut dimmers: Query<&mut Visibility, With<IdleDimmer>>,
+) {
+    let Some(event) = focus_events.iter().last() else {
+        return;
+    };
+    focus.0 = event.focused;
+    for mut visibility in &mut dimmers {
+        *visibility = if focus.0 {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+}
+
+/// Runs the clock of the player to move, which switches whenever `CurrentPlayer` flips. A
+/// player whose clock reaches zero loses on time. The clocks stop while the window is out of
+/// focus, apart from online, where the other side's clocks would carry on regardless.
+pub fn clock_system(
+    time: Res<Time>,
+    current_player: Res<CurrentPlayer>,
+    replay: Res<ReplayState>,
+    focus: Res<WindowFocus>,
+    online: Res<Online>,
+    mut clocks: ResMut<GameClocks>,
+    mut status: ResMut<GameStatus>,
+    mut game_over_events: EventWriter<GameOver>,
+    mut notice: ResMut<Notice>,
+    mut text: Query<&mut Text, With<ClockText>>,
+) {
+    let Some(clocks) = &mut clocks.0 else {
+        return;
+    };
+
+    let paused = !focus.0 && online.0.is_none();
+    if *status == GameStatus::InProgress && !replay.reviewing() && !paused {
+        let player = current_player.0;
+        let remaining = clocks.remaining_mut(player);
+        *remaining = remaining.saturating_sub(time.delta());
+        if remaining.is_zero() {
+            *status = GameStatus::Won(player.opponent());
+            game_over_events.send(GameOver {
+                winner: Some(player.opponent()),
+                line: Vec::new(),
+            });
+            notice.show(format!("{} ran out of time", player.name()));
+        }
+    }
+
+    text.single_mut().sections[0].value = format!(
+        "Black {}  White {}",
+        format_clock(clocks.black),
+        format_clock(clocks.white)
+    );
+}
+
+/// Formats a clock reading as mm:ss.
+pub fn format_clock(remaining: Duration) -> String {
+    let seconds = remaining.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
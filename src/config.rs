@@ -0,0 +1,283 @@
+//! Match presets read from `gomoku.toml`, so a game can be set up without recompiling.
+//!
+//! Every key is optional; the command line overrides whatever the file sets. An example:
+//!
+//! ```toml
+//! size = 15
+//! win_length = 5
+//! allow_overline = false
+//! renju = true
+//! first_move_center = true
+//! early_draw = true
+//! repetition_draw = true
+//! handicap = 4
+//! difficulty = "hard"
+//! ai_personality = "defensive"
+//! first_player = "white"
+//! symbol_stones = true
+//! stone_animation = "drop"
+//! auto_archive = false
+//!
+//! [keys]
+//! undo = "Back"
+//! hint = "F2"
+//!
+//! [assets]
+//! board_texture = "textures/wood.png"
+//! font = "fonts/MyFont.ttf"
+//!
+//! [appearance]
+//! line_width = 3.0
+//! stone_radius_ratio = 0.45
+//! ```
+
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use bevy::prelude::{warn, Resource};
+use serde::Deserialize;
+
+use crate::{
+    ai::{AiDifficulty, AiPersonality},
+    rules, StoneColor, DEFAULT_BOARD_SIZE, SUPPORTED_BOARD_SIZES,
+};
+
+/// Where the presets are looked for, relative to the working directory.
+pub const CONFIG_PATH: &str = "gomoku.toml";
+
+/// Settings a match starts from.
+#[derive(Resource, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameConfig {
+    /// Lines per side; one of `SUPPORTED_BOARD_SIZES`.
+    pub size: usize,
+    /// Stones in a row that win; within `rules::WIN_LENGTHS`.
+    pub win_length: usize,
+    /// Whether six or more in a row wins; if not, neither color may make such a line.
+    pub allow_overline: bool,
+    /// A color barred from lines longer than `win_length` even when `allow_overline` is on.
+    pub no_overline_for: Option<StoneColor>,
+    /// Whether the Renju restrictions on Black apply.
+    pub renju: bool,
+    /// Whether the first stone must go on the center point.
+    pub first_move_center: bool,
+    /// Whether the game is drawn as soon as no line can be completed any more, rather than
+    /// when the board is full.
+    pub early_draw: bool,
+    /// Whether the game is drawn when the same position comes up a third time with the same
+    /// player to move.
+    pub repetition_draw: bool,
+    /// Black stones put on the star points before White's first move; 0 for none, otherwise
+    /// within `rules::HANDICAPS`.
+    pub handicap: usize,
+    /// How strongly the computer plays when there is one.
+    pub difficulty: AiDifficulty,
+    /// Whether the computer favors building its own shapes or blocking the opponent's.
+    pub ai_personality: AiPersonality,
+    /// Who moves first, also after a restart.
+    pub first_player: StoneColor,
+    /// Milliseconds after a click places a stone during which further clicks are ignored;
+    /// 0 turns this off.
+    pub placement_cooldown_ms: u64,
+    /// Whether a move that makes an open three or a four is pointed out to the other player.
+    pub threat_warnings: bool,
+    /// Whether stones carry a symbol as well as their color, a dot on Black and a diamond on
+    /// White.
+    pub symbol_stones: bool,
+    /// How a newly placed stone appears.
+    pub stone_animation: AnimStyle,
+    /// Whether every finished game is written to `archive/` as an SGF record.
+    pub auto_archive: bool,
+    /// Keys for actions, by the names in `keys::Action::name`; the rest keep their defaults.
+    pub keys: HashMap<String, String>,
+    pub assets: BoardAssets,
+    pub appearance: Appearance,
+}
+
+/// How a stone appears when it is placed, whoever placed it.
+#[derive(Resource, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimStyle {
+    /// At full size straight away.
+    Instant,
+    /// Grows from a dot.
+    #[default]
+    Pop,
+    /// Falls onto its point from above and bounces once.
+    #[serde(rename = "drop")]
+    DropIn,
+}
+
+impl AnimStyle {
+    pub fn name(self) -> &'static str {
+        match self {
+            AnimStyle::Instant => "instant",
+            AnimStyle::Pop => "pop-in",
+            AnimStyle::DropIn => "drop-in",
+        }
+    }
+
+    /// The style after this one, for cycling through them.
+    pub fn next(self) -> Self {
+        match self {
+            AnimStyle::Instant => AnimStyle::Pop,
+            AnimStyle::Pop => AnimStyle::DropIn,
+            AnimStyle::DropIn => AnimStyle::Instant,
+        }
+    }
+}
+
+/// Files under `assets/` to draw with instead of the built-in look; anything that is unset or
+/// fails to load keeps the default.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoardAssets {
+    /// An image stretched over the board in place of the theme's flat color.
+    pub board_texture: Option<String>,
+    /// The font for the HUD, the labels and the move list.
+    pub font: Option<String>,
+}
+
+/// Thicknesses the grid lines may be drawn at, in world units.
+pub const LINE_WIDTHS: RangeInclusive<f32> = 0.5..=6.0;
+/// Stone radii as a share of the distance between lines, up to where the last move's marker
+/// still shows around the stone.
+pub const STONE_RADIUS_RATIOS: RangeInclusive<f32> = 0.3..=0.45;
+
+/// How the board is drawn; also tuned while the visual settings are open.
+#[derive(Resource, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Appearance {
+    /// Thickness of the grid lines and the crosshair; within `LINE_WIDTHS`.
+    pub line_width: f32,
+    /// A stone's radius over the distance between lines; within `STONE_RADIUS_RATIOS`. Only
+    /// stones placed after a change take it up.
+    pub stone_radius_ratio: f32,
+}
+
+impl Default for Appearance {
+    fn default() -> Self {
+        Appearance {
+            line_width: 2.0,
+            stone_radius_ratio: 0.4,
+        }
+    }
+}
+
+impl Appearance {
+    /// The same with each value brought within its range; one that isn't a number at all
+    /// goes back to the default.
+    pub fn clamped(self) -> Self {
+        let default = Appearance::default();
+        let clamp = |value: f32, range: &RangeInclusive<f32>, default: f32| {
+            if value.is_nan() {
+                default
+            } else {
+                value.clamp(*range.start(), *range.end())
+            }
+        };
+        Appearance {
+            line_width: clamp(self.line_width, &LINE_WIDTHS, default.line_width),
+            stone_radius_ratio: clamp(
+                self.stone_radius_ratio,
+                &STONE_RADIUS_RATIOS,
+                default.stone_radius_ratio,
+            ),
+        }
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            size: DEFAULT_BOARD_SIZE,
+            win_length: rules::DEFAULT_WIN_LENGTH,
+            allow_overline: true,
+            no_overline_for: None,
+            renju: false,
+            first_move_center: false,
+            early_draw: false,
+            repetition_draw: false,
+            handicap: 0,
+            difficulty: AiDifficulty::default(),
+            ai_personality: AiPersonality::default(),
+            first_player: StoneColor::Black,
+            placement_cooldown_ms: 100,
+            threat_warnings: true,
+            symbol_stones: false,
+            stone_animation: AnimStyle::default(),
+            auto_archive: true,
+            keys: HashMap::new(),
+            assets: BoardAssets::default(),
+            appearance: Appearance::default(),
+        }
+    }
+}
+
+impl GameConfig {
+    /// Reads the presets at `path`, warning and falling back to the defaults if the file is
+    /// missing or can't be used.
+    pub fn load(path: &str) -> Self {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!("No presets read from {path} ({err}), using defaults");
+                return GameConfig::default();
+            }
+        };
+        let mut config = match toml::from_str::<GameConfig>(&text) {
+            Ok(config) => config,
+            Err(err) => {
+                warn!("Ignoring malformed {path}: {err}");
+                return GameConfig::default();
+            }
+        };
+        if !SUPPORTED_BOARD_SIZES.contains(&config.size) {
+            warn!(
+                "{path}: size must be one of {:?}, using {}",
+                SUPPORTED_BOARD_SIZES, DEFAULT_BOARD_SIZE
+            );
+            config.size = DEFAULT_BOARD_SIZE;
+        }
+        if !rules::WIN_LENGTHS.contains(&config.win_length) {
+            warn!(
+                "{path}: win_length must be between {} and {}, using {}",
+                rules::WIN_LENGTHS.start(),
+                rules::WIN_LENGTHS.end(),
+                rules::DEFAULT_WIN_LENGTH
+            );
+            config.win_length = rules::DEFAULT_WIN_LENGTH;
+        }
+        if config.handicap != 0 && !rules::HANDICAPS.contains(&config.handicap) {
+            warn!(
+                "{path}: handicap must be 0 or between {} and {}, playing without one",
+                rules::HANDICAPS.start(),
+                rules::HANDICAPS.end()
+            );
+            config.handicap = 0;
+        }
+        let appearance = config.appearance.clamped();
+        if appearance != config.appearance {
+            warn!(
+                "{path}: line_width must be between {} and {} and stone_radius_ratio between {} \
+                 and {}, using {} and {}",
+                LINE_WIDTHS.start(),
+                LINE_WIDTHS.end(),
+                STONE_RADIUS_RATIOS.start(),
+                STONE_RADIUS_RATIOS.end(),
+                appearance.line_width,
+                appearance.stone_radius_ratio
+            );
+            config.appearance = appearance;
+        }
+        config
+    }
+
+    /// Which colors may not make overlines, by `StoneColor as usize`, as `RuleSet` wants it.
+    pub fn overline_forbidden(&self) -> [bool; 2] {
+        let mut forbidden = [!self.allow_overline; 2];
+        if let Some(color) = self.no_overline_for {
+            forbidden[color as usize] = true;
+        }
+        forbidden
+    }
+}
@@ -0,0 +1,185 @@
+//! Practice drills: with `--drill` the board shows a tactical position, such as an open three
+//! to stop or a four to finish, and the player to move has to find the right reply.
+//!
+//! Each position is written as offsets from the center for the stones of the player who
+//! solves it and of their opponent, along with the moves that count as right. A drill is set
+//! up turned by a random one of the board's symmetries, so the same position doesn't always
+//! look the same, and either color may be asked to solve it.
+
+use bevy::prelude::Resource;
+
+use crate::{ai, StoneColor};
+
+/// A position and the moves that solve it.
+pub struct Drill {
+    /// What the player is asked to do.
+    pub prompt: &'static str,
+    /// The solver's stones.
+    mine: &'static [(i32, i32)],
+    /// The opponent's stones.
+    theirs: &'static [(i32, i32)],
+    answers: &'static [(i32, i32)],
+}
+
+pub const DRILLS: [Drill; 8] = [
+    Drill {
+        prompt: "Win in one move",
+        mine: &[(-2, 0), (-1, 0), (0, 0), (1, 0), (0, -1)],
+        theirs: &[(-3, 0), (0, 1), (1, 1), (-1, -1)],
+        answers: &[(2, 0)],
+    },
+    Drill {
+        prompt: "Stop the four",
+        mine: &[(-2, -2), (0, 1), (1, 0)],
+        theirs: &[(-1, -1), (0, 0), (1, 1), (2, 2)],
+        answers: &[(3, 3)],
+    },
+    // Blocking one square further out holds too, but only right next to the three keeps the
+    // opponent from a four
+    Drill {
+        prompt: "Stop the open three",
+        mine: &[(1, 0), (-1, 1)],
+        theirs: &[(0, -1), (0, 0), (0, 1)],
+        answers: &[(0, -2), (0, 2)],
+    },
+    Drill {
+        prompt: "Stop the split four",
+        mine: &[(0, 1), (1, 1), (-1, -1)],
+        theirs: &[(-2, 0), (-1, 0), (1, 0), (2, 0)],
+        answers: &[(0, 0)],
+    },
+    Drill {
+        prompt: "Stop the broken three",
+        mine: &[(0, 1), (1, -1)],
+        theirs: &[(-1, 0), (0, 0), (2, 0)],
+        answers: &[(1, 0), (-2, 0), (3, 0)],
+    },
+    Drill {
+        prompt: "Make a four that can't be stopped",
+        mine: &[(-1, 0), (0, 0), (1, 0)],
+        theirs: &[(0, 1), (-1, -1)],
+        answers: &[(-2, 0), (2, 0)],
+    },
+    // Blocking the opponent's four loses the tempo that finishes the solver's own
+    Drill {
+        prompt: "Both sides have a four: find the move",
+        mine: &[(-2, 1), (-1, 1), (0, 1), (1, 1), (-3, -1)],
+        theirs: &[(-3, 1), (-2, -1), (-1, -1), (0, -1), (1, -1)],
+        answers: &[(2, 1)],
+    },
+    // A closed four along the row and an open three up the column at once
+    Drill {
+        prompt: "Win with a four and a three at once",
+        mine: &[(-2, 0), (-1, 0), (0, 0), (1, 1), (1, 2)],
+        theirs: &[(-3, 0), (-1, -1), (0, 1)],
+        answers: &[(1, 0)],
+    },
+];
+
+/// A drill's stones and answers as points on the board.
+pub struct Layout {
+    pub mine: Vec<(usize, usize)>,
+    pub theirs: Vec<(usize, usize)>,
+    pub answers: Vec<(usize, usize)>,
+}
+
+impl Drill {
+    /// The drill on a `size` board, turned by `symmetry`, one of `ai::transform`'s eight.
+    /// Every board size is big enough for the stones around its center.
+    pub fn lay_out(&self, size: usize, symmetry: usize) -> Layout {
+        let center = (size / 2) as i32;
+        let place = |offsets: &[(i32, i32)]| -> Vec<(usize, usize)> {
+            offsets
+                .iter()
+                .map(|&offset| {
+                    let (x, y) = ai::transform(symmetry, offset);
+                    ((x + center) as usize, (y + center) as usize)
+                })
+                .collect()
+        };
+        Layout {
+            mine: place(self.mine),
+            theirs: place(self.theirs),
+            answers: place(self.answers),
+        }
+    }
+}
+
+/// Where the drills stand. Disabled outside `--drill`.
+#[derive(Resource, Debug)]
+pub struct DrillState {
+    pub enabled: bool,
+    /// The drill on the board, as an index into `DRILLS`, once one is set up.
+    pub current: Option<usize>,
+    /// Who has to find the move.
+    pub solver: StoneColor,
+    /// The moves that count as right.
+    pub answers: Vec<(usize, usize)>,
+    /// How many moves of the history set up the position; the one after is the answer.
+    pub setup: usize,
+    /// Whether the drill on the board has had its first answer, the only one scored.
+    pub answered: bool,
+    /// Whether the answer on the board has been judged, so taking it back and trying
+    /// another gets judged in turn.
+    pub judged: bool,
+    /// Drills solved at the first try, out of `tried`.
+    pub solved: usize,
+    pub tried: usize,
+}
+
+impl DrillState {
+    pub fn new(enabled: bool) -> Self {
+        DrillState {
+            enabled,
+            current: None,
+            solver: StoneColor::Black,
+            answers: Vec::new(),
+            setup: 0,
+            answered: false,
+            judged: false,
+            solved: 0,
+            tried: 0,
+        }
+    }
+
+    /// Starts on drill `index`, set up with the first `setup` moves of the history.
+    pub fn start(
+        &mut self,
+        index: usize,
+        solver: StoneColor,
+        answers: Vec<(usize, usize)>,
+        setup: usize,
+    ) {
+        self.current = Some(index);
+        self.solver = solver;
+        self.answers = answers;
+        self.setup = setup;
+        self.answered = false;
+        self.judged = false;
+    }
+
+    /// Judges an answer that `is_right`, scoring it if it is the drill's first.
+    pub fn judge(&mut self, is_right: bool) {
+        self.judged = true;
+        if !std::mem::replace(&mut self.answered, true) {
+            self.tried += 1;
+            if is_right {
+                self.solved += 1;
+            }
+        }
+    }
+
+    /// The HUD line for the drill on the board.
+    pub fn status(&self) -> String {
+        let Some(index) = self.current else {
+            return String::new();
+        };
+        format!(
+            "Drill: {} ({} to play) \u{2013} solved {} of {} at the first try",
+            DRILLS[index].prompt,
+            self.solver.name(),
+            self.solved,
+            self.tried
+        )
+    }
+}
@@ -0,0 +1,145 @@
+//! Edit mode, for setting up positions by taking stones off the board, and swapping the
+//! colors of the stones to see a position from the other side.
+
+use bevy::prelude::*;
+
+use crate::{
+    game_core,
+    hud::{GameOverPanel, Notice},
+    keys::{self, Action},
+    online::Online,
+    opening,
+    replay::ReplayState,
+    rules, BoardConfig, BoardLayout, BoardState, CurrentPlayer, GameOver, GameStatus, HistoryEntry,
+    MouseState, MoveHistory, MoveNumberLabel, RedoStack, StonePlacer,
+};
+
+/// While on, right-clicking a stone takes it off the board; toggled with X.
+#[derive(Resource)]
+pub struct EditMode(pub bool);
+
+pub fn edit_mode_system(
+    keys: keys::Keys,
+    online: Res<Online>,
+    mut edit_mode: ResMut<EditMode>,
+    mut notice: ResMut<Notice>,
+) {
+    if keys.just_pressed(Action::EditMode) && online.0.is_none() {
+        edit_mode.0 = !edit_mode.0;
+        notice.show(if edit_mode.0 {
+            "Edit mode: right-click removes stones"
+        } else {
+            "Edit mode off"
+        });
+    }
+}
+
+/// Removes the stone under the cursor on right-click in edit mode, then renumbers the stones
+/// played after it.
+pub fn remove_stone_system(
+    mut commands: Commands,
+    layout: Res<BoardLayout>,
+    mouse_button_inputs: Res<Input<MouseButton>>,
+    mouse_state: Res<MouseState>,
+    edit_mode: Res<EditMode>,
+    replay: Res<ReplayState>,
+    config: Res<BoardConfig>,
+    mut board: ResMut<BoardState>,
+    mut history: ResMut<MoveHistory>,
+    mut redo: ResMut<RedoStack>,
+    (mut positions, current_player): (ResMut<game_core::PositionCounts>, Res<CurrentPlayer>),
+    mut status: ResMut<GameStatus>,
+    rules: Res<rules::RuleSet>,
+    mut game_over_events: EventWriter<GameOver>,
+    mut labels: Query<(&Parent, &mut Text), With<MoveNumberLabel>>,
+    game_over_panels: Query<(), With<GameOverPanel>>,
+) {
+    if !edit_mode.0
+        || replay.reviewing()
+        || !game_over_panels.is_empty()
+        || !mouse_button_inputs.just_pressed(MouseButton::Right)
+    {
+        return;
+    }
+    let Some(pos) = mouse_state.cursor_pos else {
+        return;
+    };
+    let Some((col, row)) = layout.cursor_to_grid(pos, config.size) else {
+        return;
+    };
+    // The latest move at a point is the stone standing there, unless it was captured
+    let index = history.latest_at(col, row);
+    let Some(index) = index.filter(|_| board.get(col, row).is_some()) else {
+        return;
+    };
+
+    // Fixing up the position reopens the game, resigned or not
+    if history.resigned().is_some() {
+        history.0.pop();
+    }
+    let Some(record) = history.0.remove(index).stone().cloned() else {
+        return;
+    };
+    commands.entity(record.entity).despawn_recursive();
+    board.remove(col, row);
+    // The moves taken back were played on the position before the edit, and the ones
+    // counted didn't lead to the position after it
+    redo.0.clear();
+    positions.start(&board, current_player.0);
+    *status = GameStatus::InProgress;
+    // Taking the end off a forbidden overline can leave a five standing
+    if let Some((winner, line)) = rules::full_win_scan(&board, &rules) {
+        *status = GameStatus::Won(winner);
+        game_over_events.send(GameOver {
+            winner: Some(winner),
+            line,
+        });
+    }
+    for later in history.0.iter_mut().filter_map(HistoryEntry::stone_mut) {
+        later.captures.retain(|&captured| captured != index);
+        for captured in &mut later.captures {
+            if *captured > index {
+                *captured -= 1;
+            }
+        }
+    }
+
+    for (parent, mut text) in &mut labels {
+        let number = history.0.iter().position(|entry| {
+            entry
+                .stone()
+                .is_some_and(|record| record.entity == parent.get())
+        });
+        if let Some(number) = number {
+            text.sections[0].value = (number + 1).to_string();
+        }
+    }
+}
+
+/// Swaps the colors of all the stones with F9, for testing positions from the other side.
+/// Only between moves of a local game in progress.
+pub fn swap_colors_system(
+    keys: keys::Keys,
+    online: Res<Online>,
+    replay: Res<ReplayState>,
+    opening: Res<opening::Opening>,
+    mut notice: ResMut<Notice>,
+    mut placer: StonePlacer,
+) {
+    if !keys.just_pressed(Action::SwapColors) {
+        return;
+    }
+    if online.0.is_some()
+        || replay.reviewing()
+        || !opening.accepts_moves()
+        || *placer.status != GameStatus::InProgress
+    {
+        notice.show("Colors can only be swapped in a local game in progress");
+        return;
+    }
+    placer.swap_colors();
+    notice.show(format!(
+        "Colors swapped, {} to move",
+        placer.current_player.0.name()
+    ));
+}
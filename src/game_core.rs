@@ -0,0 +1,869 @@
+//! The board and the effect of a move on it, with no rendering or ECS involved.
+//!
+//! The Bevy systems keep the stones on screen and the history in step with what these
+//! functions decide; self-play and anything else that only needs the rules can call them
+//! directly.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rules::{self, renju::ForbiddenKind, RuleSet, Variant};
+
+/// Largest supported board; `BoardState` is always allocated at this size.
+pub const MAX_BOARD_SIZE: usize = 25;
+pub const SUPPORTED_BOARD_SIZES: [usize; 5] = [9, 13, 15, 19, 25];
+/// The board a game is played on unless another size is asked for.
+pub const DEFAULT_BOARD_SIZE: usize = 19;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoneColor {
+    Black,
+    White,
+}
+
+impl StoneColor {
+    pub fn opponent(self) -> StoneColor {
+        match self {
+            StoneColor::Black => StoneColor::White,
+            StoneColor::White => StoneColor::Black,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StoneColor::Black => "Black",
+            StoneColor::White => "White",
+        }
+    }
+}
+
+/// Every intersection of the board, indexed as `cells[row][col]`.
+/// Only the first `size` rows and columns are in play.
+#[derive(Clone, Debug)]
+pub struct BoardState {
+    size: usize,
+    cells: [[Option<StoneColor>; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
+    /// The Zobrist hash of `cells`, kept up to date by `set` and `remove`.
+    hash: u64,
+    /// Whether lines leaving one edge come back in at the opposite one, for
+    /// `Variant::Toroidal`; see `offset`.
+    wraps: bool,
+}
+
+impl BoardState {
+    pub fn new(size: usize) -> Self {
+        BoardState {
+            size,
+            cells: [[None; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
+            hash: 0,
+            wraps: false,
+        }
+    }
+
+    /// An empty board for playing `variant` on.
+    pub fn for_variant(size: usize, variant: Variant) -> Self {
+        BoardState {
+            wraps: variant == Variant::Toroidal,
+            ..BoardState::new(size)
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn wraps(&self) -> bool {
+        self.wraps
+    }
+
+    pub fn clear(&mut self) {
+        *self = BoardState {
+            wraps: self.wraps,
+            ..BoardState::new(self.size)
+        };
+    }
+
+    /// The point `steps` steps from `(col, row)` along `(dx, dy)`. Past an edge there is none,
+    /// unless the board wraps, when the count carries on from the opposite edge. Everything
+    /// that walks along lines goes through here, so that is the only place wrapping is
+    /// decided.
+    pub fn offset(
+        &self,
+        col: usize,
+        row: usize,
+        dx: i32,
+        dy: i32,
+        steps: i32,
+    ) -> Option<(usize, usize)> {
+        let size = self.size as i32;
+        let (x, y) = (col as i32 + steps * dx, row as i32 + steps * dy);
+        if self.wraps {
+            Some((x.rem_euclid(size) as usize, y.rem_euclid(size) as usize))
+        } else {
+            ((0..size).contains(&x) && (0..size).contains(&y)).then_some((x as usize, y as usize))
+        }
+    }
+
+    pub fn get(&self, col: usize, row: usize) -> Option<StoneColor> {
+        self.cells[row][col]
+    }
+
+    pub fn set(&mut self, col: usize, row: usize, color: StoneColor) {
+        self.remove(col, row);
+        self.cells[row][col] = Some(color);
+        self.hash ^= ZOBRIST_KEYS.key(col, row, color);
+    }
+
+    pub fn remove(&mut self, col: usize, row: usize) {
+        if let Some(color) = self.cells[row][col].take() {
+            self.hash ^= ZOBRIST_KEYS.key(col, row, color);
+        }
+    }
+
+    /// Turns every black stone white and every white one black.
+    pub fn swap_colors(&mut self) {
+        for row in 0..self.size {
+            for col in 0..self.size {
+                if let Some(color) = self.get(col, row) {
+                    self.set(col, row, color.opponent());
+                }
+            }
+        }
+    }
+
+    /// A 64-bit fingerprint of where the stones are, equal for equal positions however they
+    /// were reached. Different positions collide only by rare chance.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// `hash` for the position with `to_move` to play, telling the two turns apart.
+    pub fn hash_to_move(&self, to_move: StoneColor) -> u64 {
+        match to_move {
+            StoneColor::Black => self.hash,
+            StoneColor::White => self.hash ^ WHITE_TO_MOVE,
+        }
+    }
+
+    pub fn stone_count(&self) -> usize {
+        self.cells[..self.size]
+            .iter()
+            .flat_map(|row| &row[..self.size])
+            .filter(|cell| cell.is_some())
+            .count()
+    }
+}
+
+/// A random key for every color of stone on every intersection; a position's hash is the
+/// XOR of the keys of its stones.
+pub struct ZobristKeys {
+    keys: [[[u64; 2]; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
+}
+
+/// Mixed into a hash when White is to move, since the same stones are another position
+/// depending on whose turn it is; see `BoardState::hash_to_move`.
+const WHITE_TO_MOVE: u64 = 0x6a09_e667_f3bc_c908;
+
+/// The keys every `BoardState` hashes with. They come from a fixed seed, so a position hashes
+/// the same in every run and the same on both sides of an online game.
+///
+/// This is a `static` rather than a bevy resource: every `set` and `remove` updates the hash,
+/// and boards are also played outside the app, by the AI's search, self-play and the archive
+/// analysis, where there is no `World` to look a resource up in. Being fixed and built at
+/// compile time, the keys don't need one.
+pub static ZOBRIST_KEYS: ZobristKeys = ZobristKeys::new(0x9e37_79b9_7f4a_7c15);
+
+impl ZobristKeys {
+    /// Fills the table from `seed` with SplitMix64, which is simple enough to run at compile
+    /// time.
+    const fn new(seed: u64) -> Self {
+        const fn split_mix(state: &mut u64) -> u64 {
+            *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut key = *state;
+            key = (key ^ (key >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            key = (key ^ (key >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            key ^ (key >> 31)
+        }
+
+        let mut keys = [[[0; 2]; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+        let mut state = seed;
+        let mut row = 0;
+        while row < MAX_BOARD_SIZE {
+            let mut col = 0;
+            while col < MAX_BOARD_SIZE {
+                let mut color = 0;
+                while color < 2 {
+                    keys[row][col][color] = split_mix(&mut state);
+                    color += 1;
+                }
+                col += 1;
+            }
+            row += 1;
+        }
+        ZobristKeys { keys }
+    }
+
+    pub fn key(&self, col: usize, row: usize, color: StoneColor) -> u64 {
+        self.keys[row][col][color as usize]
+    }
+}
+
+/// How many times each position has come up in a game, with the same player to move, for
+/// `RuleSet::repetition_draw`. Only Pente captures can bring a position back; the pairs
+/// captured so far aren't part of it. A move counts the position it leads to, and taking the
+/// move back uncounts it; the position the game started from is counted by `start`.
+#[derive(Clone, Debug, Default)]
+pub struct PositionCounts(HashMap<u64, u8>);
+
+impl PositionCounts {
+    /// Counts from scratch, starting with the position on `board` with `to_move` to play.
+    /// For a new game, a loaded one, and a position changed other than by playing a move.
+    pub fn start(&mut self, board: &BoardState, to_move: StoneColor) {
+        self.0.clear();
+        self.record(board, to_move);
+    }
+
+    /// Counts the position on `board` with `to_move` to play, returning how many times it has
+    /// now come up.
+    pub fn record(&mut self, board: &BoardState, to_move: StoneColor) -> u8 {
+        let count = self.0.entry(board.hash_to_move(to_move)).or_insert(0);
+        *count = count.saturating_add(1);
+        *count
+    }
+
+    /// Uncounts the position on `board` with `to_move` to play, as the move that led to it is
+    /// taken back.
+    pub fn forget(&mut self, board: &BoardState, to_move: StoneColor) {
+        let key = board.hash_to_move(to_move);
+        if let Some(count) = self.0.get_mut(&key) {
+            *count -= 1;
+            if *count == 0 {
+                self.0.remove(&key);
+            }
+        }
+    }
+}
+
+/// Why a move was refused.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MoveError {
+    GameOver,
+    Occupied,
+    Forbidden(ForbiddenKind),
+    /// The point isn't on this board.
+    OffBoard,
+    /// It isn't this color's turn.
+    OutOfTurn,
+    /// The rules want the first stone on the center point; see `rules::is_center`.
+    OffCenter,
+    /// Pente keeps Black's second stone away from the center; see
+    /// `rules::variant_move_legal`.
+    NearCenter,
+}
+
+/// Checks a move that arrived over the network before it goes anywhere near the board: it
+/// must be on the board, by the player to move, and on an empty point. The rules are checked
+/// again when it is placed; this only catches what a local move can't get wrong.
+pub fn validate_remote_move(
+    board: &BoardState,
+    color: StoneColor,
+    col: usize,
+    row: usize,
+    current_player: StoneColor,
+) -> Result<(), MoveError> {
+    if col >= board.size() || row >= board.size() {
+        return Err(MoveError::OffBoard);
+    }
+    if color != current_player {
+        return Err(MoveError::OutOfTurn);
+    }
+    if board.get(col, row).is_some() {
+        return Err(MoveError::Occupied);
+    }
+    Ok(())
+}
+
+/// Whether a move ended the game.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Continues,
+    /// `winner` won, almost always the mover; `line` is the stones of the five from one end
+    /// to the other, empty for a win on Pente captures. The exception is a capture that
+    /// uncovers a five of the opponent's; see `rules::check_win`.
+    Won {
+        winner: StoneColor,
+        line: Vec<(usize, usize)>,
+    },
+    Draw,
+}
+
+/// What a stone did once it was on the board.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Placement {
+    /// Opposing stones taken off the board by a Pente capture, two per pair.
+    pub captured: Vec<(usize, usize)>,
+    pub outcome: Outcome,
+}
+
+impl Placement {
+    /// Counts the position a `color` move left on `board` in `counts`, and under
+    /// `rules.repetition_draw` makes a game that would go on a draw once that position has come
+    /// up `rules::REPETITIONS_TO_DRAW` times.
+    pub fn count_position(
+        &mut self,
+        counts: &mut PositionCounts,
+        board: &BoardState,
+        color: StoneColor,
+        rules: &RuleSet,
+    ) {
+        let seen = counts.record(board, color.opponent());
+        if rules.repetition_draw
+            && seen >= rules::REPETITIONS_TO_DRAW
+            && self.outcome == Outcome::Continues
+        {
+            self.outcome = Outcome::Draw;
+        }
+    }
+}
+
+/// Plays a `color` stone at `(col, row)` if the rules allow it, takes off whatever it
+/// captures, and decides whether the game is over. `captured_pairs` is how many pairs
+/// `color` had already captured, which only matters in Pente. Leaves the board untouched if
+/// the move is refused.
+pub fn apply_move(
+    board: &mut BoardState,
+    col: usize,
+    row: usize,
+    color: StoneColor,
+    rules: &RuleSet,
+    variant: Variant,
+    captured_pairs: usize,
+) -> Result<Placement, MoveError> {
+    if board.get(col, row).is_some() {
+        return Err(MoveError::Occupied);
+    }
+    if rules.first_move_center
+        && board.stone_count() == 0
+        && !rules::is_center(board.size(), col, row)
+    {
+        return Err(MoveError::OffCenter);
+    }
+    if let Some(kind) = rules::forbidden_move(board, col, row, color, rules) {
+        return Err(MoveError::Forbidden(kind));
+    }
+    if !rules::variant_move_legal(board, col, row, color, variant) {
+        return Err(MoveError::NearCenter);
+    }
+
+    board.set(col, row, color);
+    let captured = match variant {
+        Variant::Pente => rules::resolve_captures(board, col, row, color),
+        Variant::Standard | Variant::Toroidal => Vec::new(),
+    };
+
+    // A capture can take the end off an overline the opponent may not win with, leaving a
+    // five elsewhere on the board
+    let uncovered = if !captured.is_empty() && rules.overline_forbidden[color.opponent() as usize] {
+        rules::full_win_scan(board, rules)
+    } else {
+        None
+    };
+    let outcome = if let Some(line) = rules::check_win(board, col, row, color, rules) {
+        Outcome::Won {
+            winner: color,
+            line,
+        }
+    } else if variant == Variant::Pente
+        && captured_pairs + captured.len() / 2 >= rules::CAPTURES_TO_WIN
+    {
+        Outcome::Won {
+            winner: color,
+            line: Vec::new(),
+        }
+    } else if let Some((winner, line)) = uncovered {
+        Outcome::Won { winner, line }
+    } else if rules::is_draw(board)
+        || rules.early_draw
+            && variant != Variant::Pente
+            && board
+                .stone_count()
+                .is_multiple_of(rules::DEAD_DRAW_INTERVAL)
+            && rules::detect_dead_draw(board, rules.win_length)
+    {
+        Outcome::Draw
+    } else {
+        Outcome::Continues
+    };
+    Ok(Placement { captured, outcome })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plays `color` at `(col, row)` on a standard board under the default rules.
+    fn play(board: &mut BoardState, col: usize, row: usize, color: StoneColor) -> Placement {
+        apply_move(
+            board,
+            col,
+            row,
+            color,
+            &RuleSet::default(),
+            Variant::Standard,
+            0,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn a_move_goes_on_the_board() {
+        let mut board = BoardState::new(15);
+        let placement = play(&mut board, 7, 7, StoneColor::Black);
+        assert_eq!(placement.outcome, Outcome::Continues);
+        assert!(placement.captured.is_empty());
+        assert_eq!(board.get(7, 7), Some(StoneColor::Black));
+        assert_eq!(board.stone_count(), 1);
+    }
+
+    #[test]
+    fn an_occupied_point_is_refused() {
+        let mut board = BoardState::new(15);
+        play(&mut board, 7, 7, StoneColor::Black);
+        let hash = board.hash();
+        let result = apply_move(
+            &mut board,
+            7,
+            7,
+            StoneColor::White,
+            &RuleSet::default(),
+            Variant::Standard,
+            0,
+        );
+        assert_eq!(result, Err(MoveError::Occupied));
+        assert_eq!(board.get(7, 7), Some(StoneColor::Black));
+        assert_eq!(board.hash(), hash);
+    }
+
+    #[test]
+    fn the_first_stone_may_have_to_go_in_the_center() {
+        let rules = RuleSet {
+            first_move_center: true,
+            ..RuleSet::default()
+        };
+        let mut board = BoardState::new(15);
+        let result = apply_move(
+            &mut board,
+            3,
+            3,
+            StoneColor::Black,
+            &rules,
+            Variant::Standard,
+            0,
+        );
+        assert_eq!(result, Err(MoveError::OffCenter));
+        assert_eq!(board.stone_count(), 0);
+        assert!(apply_move(
+            &mut board,
+            7,
+            7,
+            StoneColor::Black,
+            &rules,
+            Variant::Standard,
+            0
+        )
+        .is_ok());
+        // Only the first stone is held to it
+        assert!(apply_move(
+            &mut board,
+            3,
+            3,
+            StoneColor::White,
+            &rules,
+            Variant::Standard,
+            0
+        )
+        .is_ok());
+        assert_eq!(board.stone_count(), 2);
+    }
+
+    #[test]
+    fn five_in_a_row_wins() {
+        let mut board = BoardState::new(15);
+        for col in 3..7 {
+            play(&mut board, col, 4, StoneColor::White);
+        }
+        let placement = play(&mut board, 7, 4, StoneColor::White);
+        let Outcome::Won { winner, mut line } = placement.outcome else {
+            panic!("expected a win, got {:?}", placement.outcome);
+        };
+        assert_eq!(winner, StoneColor::White);
+        line.sort();
+        assert_eq!(line, vec![(3, 4), (4, 4), (5, 4), (6, 4), (7, 4)]);
+    }
+
+    #[test]
+    fn filling_the_board_without_a_five_draws() {
+        // Pairs of columns alternate along each row, and each row starts on the other color,
+        // which leaves no five anywhere
+        let color = |col: usize, row: usize| {
+            if (col / 2 + row).is_multiple_of(2) {
+                StoneColor::Black
+            } else {
+                StoneColor::White
+            }
+        };
+        let mut board = BoardState::new(9);
+        for col in 0..9 {
+            for row in 0..9 {
+                if (col, row) != (8, 8) {
+                    board.set(col, row, color(col, row));
+                }
+            }
+        }
+        let placement = play(&mut board, 8, 8, color(8, 8));
+        assert_eq!(placement.outcome, Outcome::Draw);
+    }
+
+    #[test]
+    fn the_fifth_pair_wins_pente() {
+        let mut board = BoardState::for_variant(15, Variant::Pente);
+        board.set(4, 4, StoneColor::Black);
+        board.set(5, 4, StoneColor::White);
+        board.set(6, 4, StoneColor::White);
+        let rules = RuleSet::default();
+        let placement = apply_move(
+            &mut board,
+            7,
+            4,
+            StoneColor::Black,
+            &rules,
+            Variant::Pente,
+            4,
+        )
+        .unwrap();
+        assert_eq!(placement.captured.len(), 2);
+        assert_eq!(
+            placement.outcome,
+            Outcome::Won {
+                winner: StoneColor::Black,
+                line: Vec::new(),
+            }
+        );
+        assert_eq!(board.get(5, 4), None);
+        assert_eq!(board.get(6, 4), None);
+
+        // With fewer pairs taken before, the same capture only takes stones
+        let mut board = BoardState::for_variant(15, Variant::Pente);
+        board.set(4, 4, StoneColor::Black);
+        board.set(5, 4, StoneColor::White);
+        board.set(6, 4, StoneColor::White);
+        let placement = apply_move(
+            &mut board,
+            7,
+            4,
+            StoneColor::Black,
+            &rules,
+            Variant::Pente,
+            3,
+        )
+        .unwrap();
+        assert_eq!(placement.outcome, Outcome::Continues);
+    }
+
+    #[test]
+    fn removing_a_stone_restores_the_hash() {
+        let mut board = BoardState::new(15);
+        board.set(7, 7, StoneColor::Black);
+        board.set(8, 7, StoneColor::White);
+        let before = board.hash();
+        board.set(3, 11, StoneColor::Black);
+        assert_ne!(board.hash(), before);
+        board.remove(3, 11);
+        assert_eq!(board.hash(), before);
+
+        // Removing everything gets back to the empty board's hash
+        board.remove(7, 7);
+        board.remove(8, 7);
+        assert_eq!(board.hash(), BoardState::new(15).hash());
+    }
+
+    #[test]
+    fn a_position_hashes_the_same_however_it_was_reached() {
+        let mut one = BoardState::new(15);
+        one.set(7, 7, StoneColor::Black);
+        one.set(8, 8, StoneColor::White);
+        one.set(6, 6, StoneColor::Black);
+        let mut other = BoardState::new(15);
+        other.set(6, 6, StoneColor::Black);
+        other.set(8, 8, StoneColor::Black);
+        other.set(7, 7, StoneColor::Black);
+        // Overwriting a stone takes the old one's key out
+        other.set(8, 8, StoneColor::White);
+        assert_eq!(one.hash(), other.hash());
+    }
+
+    #[test]
+    fn different_positions_hash_differently() {
+        let mut black = BoardState::new(15);
+        black.set(7, 7, StoneColor::Black);
+        let mut white = BoardState::new(15);
+        white.set(7, 7, StoneColor::White);
+        let mut elsewhere = BoardState::new(15);
+        elsewhere.set(7, 8, StoneColor::Black);
+        let hashes = [
+            BoardState::new(15).hash(),
+            black.hash(),
+            white.hash(),
+            elsewhere.hash(),
+        ];
+        for (i, a) in hashes.iter().enumerate() {
+            for b in &hashes[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn remote_moves_are_checked() {
+        let mut board = BoardState::new(15);
+        board.set(7, 7, StoneColor::Black);
+        let check =
+            |col, row, color| validate_remote_move(&board, color, col, row, StoneColor::White);
+        assert_eq!(check(8, 7, StoneColor::White), Ok(()));
+        assert_eq!(check(7, 7, StoneColor::White), Err(MoveError::Occupied));
+        assert_eq!(check(8, 7, StoneColor::Black), Err(MoveError::OutOfTurn));
+        // Off the board, even where the storage behind a small board has room
+        assert_eq!(check(15, 3, StoneColor::White), Err(MoveError::OffBoard));
+        assert_eq!(check(3, 15, StoneColor::White), Err(MoveError::OffBoard));
+        assert_eq!(
+            check(usize::MAX, usize::MAX, StoneColor::White),
+            Err(MoveError::OffBoard)
+        );
+    }
+
+    #[test]
+    fn a_dead_position_is_drawn_early_when_asked() {
+        // No stretch of five is all one color, with four points left to fill
+        let color_at = |col: usize, row: usize| {
+            if (col / 2 + row).is_multiple_of(2) {
+                StoneColor::Black
+            } else {
+                StoneColor::White
+            }
+        };
+        let empty = [(0, 0), (7, 7), (14, 14), (0, 14)];
+        let mut board = BoardState::new(15);
+        for row in 0..15 {
+            for col in 0..15 {
+                if !empty.contains(&(col, row)) {
+                    board.set(col, row, color_at(col, row));
+                }
+            }
+        }
+
+        for early_draw in [false, true] {
+            let rules = RuleSet {
+                early_draw,
+                ..RuleSet::default()
+            };
+            let mut board = board.clone();
+            let outcomes: Vec<_> = empty[..3]
+                .iter()
+                .map(|&(col, row)| {
+                    let color = color_at(col, row);
+                    apply_move(&mut board, col, row, color, &rules, Variant::Standard, 0)
+                        .unwrap()
+                        .outcome
+                })
+                .collect();
+            // Only every DEAD_DRAW_INTERVAL stones is the board checked
+            assert_eq!(board.stone_count() % rules::DEAD_DRAW_INTERVAL, 0);
+            let last = if early_draw {
+                Outcome::Draw
+            } else {
+                Outcome::Continues
+            };
+            assert_eq!(
+                outcomes,
+                vec![Outcome::Continues, Outcome::Continues, last],
+                "early_draw: {early_draw}"
+            );
+        }
+    }
+
+    #[test]
+    fn swapping_colors_swaps_every_stone() {
+        let black = [(7, 7), (8, 8), (0, 14)];
+        let white = [(7, 8), (14, 0)];
+        let mut board = BoardState::new(15);
+        let mut swapped = BoardState::new(15);
+        for &(col, row) in &black {
+            board.set(col, row, StoneColor::Black);
+            swapped.set(col, row, StoneColor::White);
+        }
+        for &(col, row) in &white {
+            board.set(col, row, StoneColor::White);
+            swapped.set(col, row, StoneColor::Black);
+        }
+        let original = board.clone();
+
+        board.swap_colors();
+        for row in 0..15 {
+            for col in 0..15 {
+                assert_eq!(
+                    board.get(col, row),
+                    swapped.get(col, row),
+                    "at ({col}, {row})"
+                );
+            }
+        }
+        // The hash follows, as if the stones had gone down in their new colors
+        assert_eq!(board.hash(), swapped.hash());
+        assert_eq!(board.stone_count(), 5);
+
+        board.swap_colors();
+        assert_eq!(board.hash(), original.hash());
+        assert_eq!(board.get(7, 7), Some(StoneColor::Black));
+    }
+
+    #[test]
+    fn only_a_toroidal_board_wraps_round_its_edges() {
+        let flat = BoardState::new(15);
+        let torus = BoardState::for_variant(15, Variant::Toroidal);
+        assert!(!flat.wraps());
+        assert!(torus.wraps());
+        for board in [&flat, &torus] {
+            assert_eq!(board.offset(7, 7, 1, -1, 3), Some((10, 4)));
+        }
+        assert_eq!(flat.offset(14, 7, 1, 0, 1), None);
+        assert_eq!(torus.offset(14, 7, 1, 0, 1), Some((0, 7)));
+        assert_eq!(flat.offset(0, 0, -1, -1, 2), None);
+        assert_eq!(torus.offset(0, 0, -1, -1, 2), Some((13, 13)));
+        // Far enough round, a line comes back to where it started
+        assert_eq!(torus.offset(3, 5, 0, 1, 15), Some((3, 5)));
+    }
+
+    #[test]
+    fn the_side_to_move_is_part_of_the_position() {
+        let mut board = BoardState::new(15);
+        board.set(7, 7, StoneColor::Black);
+        assert_eq!(board.hash_to_move(StoneColor::Black), board.hash());
+        assert_ne!(
+            board.hash_to_move(StoneColor::White),
+            board.hash_to_move(StoneColor::Black)
+        );
+    }
+
+    #[test]
+    fn the_starting_position_is_counted() {
+        let mut board = BoardState::new(15);
+        let mut counts = PositionCounts::default();
+        counts.start(&board, StoneColor::Black);
+        assert_eq!(counts.record(&board, StoneColor::Black), 2);
+        // With the other player to move it is a position of its own
+        assert_eq!(counts.record(&board, StoneColor::White), 1);
+
+        // Starting again forgets the rest
+        board.set(7, 7, StoneColor::Black);
+        counts.record(&board, StoneColor::White);
+        counts.start(&board, StoneColor::White);
+        assert_eq!(counts.record(&board, StoneColor::White), 2);
+        board.remove(7, 7);
+        assert_eq!(counts.record(&board, StoneColor::Black), 1);
+    }
+
+    #[test]
+    fn undoing_a_move_uncounts_its_position() {
+        let mut board = BoardState::new(15);
+        let mut counts = PositionCounts::default();
+        counts.start(&board, StoneColor::Black);
+        board.set(7, 7, StoneColor::Black);
+        counts.record(&board, StoneColor::White);
+        counts.forget(&board, StoneColor::White);
+        assert_eq!(counts.record(&board, StoneColor::White), 1);
+        counts.forget(&board, StoneColor::White);
+        board.remove(7, 7);
+        assert_eq!(counts.record(&board, StoneColor::Black), 2);
+    }
+
+    /// Plays `moves` in Pente from `board`, counting each position in `counts`, and returns
+    /// the outcome of the last.
+    fn play_pente(
+        board: &mut BoardState,
+        counts: &mut PositionCounts,
+        pairs: &mut [usize; 2],
+        rules: &RuleSet,
+        moves: &[(usize, usize, StoneColor)],
+    ) -> Outcome {
+        let mut outcome = Outcome::Continues;
+        for &(col, row, color) in moves {
+            let mut placement = apply_move(
+                board,
+                col,
+                row,
+                color,
+                rules,
+                Variant::Pente,
+                pairs[color as usize],
+            )
+            .unwrap();
+            pairs[color as usize] += placement.captured.len() / 2;
+            placement.count_position(counts, board, color, rules);
+            outcome = placement.outcome;
+        }
+        outcome
+    }
+
+    #[test]
+    fn a_pente_position_coming_back_three_times_is_a_draw() {
+        use StoneColor::{Black, White};
+        let row = 5;
+        // Along the row: Black, two White, a gap, Black and White, Black to move
+        let setup = [
+            (0, row, Black),
+            (1, row, White),
+            (4, row, Black),
+            (2, row, White),
+            (10, 10, Black),
+            (5, row, White),
+        ];
+        // Black takes the White pair, White takes the Black pair this leaves, and both
+        // stones go back where they were
+        let cycle = [
+            (3, row, Black),
+            (2, row, White),
+            (4, row, Black),
+            (1, row, White),
+        ];
+
+        for repetition_draw in [false, true] {
+            let rules = RuleSet {
+                repetition_draw,
+                ..RuleSet::default()
+            };
+            let mut board = BoardState::for_variant(15, Variant::Pente);
+            let mut counts = PositionCounts::default();
+            counts.start(&board, Black);
+            let mut pairs = [0, 0];
+            let outcome = play_pente(&mut board, &mut counts, &mut pairs, &rules, &setup);
+            assert_eq!(outcome, Outcome::Continues);
+            let position = board.hash_to_move(Black);
+
+            let outcome = play_pente(&mut board, &mut counts, &mut pairs, &rules, &cycle);
+            assert_eq!(board.hash_to_move(Black), position);
+            assert_eq!(outcome, Outcome::Continues, "twice");
+            assert_eq!(pairs, [1, 1]);
+
+            // The third time, at move 14
+            let outcome = play_pente(&mut board, &mut counts, &mut pairs, &rules, &cycle);
+            let expected = if repetition_draw {
+                Outcome::Draw
+            } else {
+                Outcome::Continues
+            };
+            assert_eq!(outcome, expected, "repetition_draw: {repetition_draw}");
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! A timeline of everything that happened on the board, undos and restarts included, for
+//! reproducing reported bugs. Unlike the move history it never forgets a move that was taken
+//! back. With `--event-log <file>` it is written out as JSON whenever a game ends.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use serde::Serialize;
+
+use crate::{GameOver, StoneColor};
+
+/// Something that happened to the game. Stepping through a review shows up as the moves and
+/// undos it makes on the board.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum GameEvent {
+    MovePlaced {
+        col: usize,
+        row: usize,
+        color: StoneColor,
+        /// Opposing stones the move took off the board in Pente.
+        captured: usize,
+    },
+    TurnChanged {
+        to: StoneColor,
+    },
+    Won {
+        winner: StoneColor,
+    },
+    Draw,
+    Resigned {
+        color: StoneColor,
+    },
+    /// A move taken back, handing the turn back to `color`; without a point it was a
+    /// resignation.
+    Undo {
+        color: StoneColor,
+        point: Option<(usize, usize)>,
+    },
+    Restart,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LogEntry {
+    /// Milliseconds since 1970 UTC.
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub event: GameEvent,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct GameLog {
+    entries: Vec<LogEntry>,
+    /// Where the log is written when a game ends, if anywhere.
+    pub path: Option<PathBuf>,
+}
+
+impl GameLog {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        GameLog {
+            entries: Vec::new(),
+            path,
+        }
+    }
+
+    /// Appends `event`, stamped with the current time.
+    pub fn record(&mut self, event: GameEvent) {
+        let at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64);
+        self.entries.push(LogEntry { at_ms, event });
+    }
+
+    /// The whole log as a JSON array, oldest event first.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&self.entries)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_json()?)
+    }
+}
+
+/// Writes the event log to `--event-log`'s file whenever a game ends.
+pub fn game_log_system(mut game_over_events: EventReader<GameOver>, log: Res<GameLog>) {
+    if game_over_events.iter().count() == 0 {
+        return;
+    }
+    if let Some(path) = &log.path {
+        if let Err(err) = log.write(path) {
+            warn!("Could not write the event log to {}: {err}", path.display());
+        }
+    }
+}
@@ -0,0 +1,942 @@
+//! The heads-up display around the board: lines of text about the game, the toolbar, the
+//! evaluation bar, the move list and the result panel, and the systems that keep them current.
+
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+};
+
+use crate::{
+    ai, capture_count, column_label, cursor_over_node, drill,
+    keys::{self, Action},
+    online::Online,
+    rules,
+    theme::{self, Theme},
+    AiOpponent, AiStats, BoardConfig, BoardLayout, BoardState, CurrentPlayer, GameOver, GameStatus,
+    HistoryEntry, MouseState, MoveHistory, StoneColor, UiFont,
+};
+
+/// Spawns the HUD's lines of text, the toolbar, the evaluation bar and the move list.
+pub fn spawn(commands: &mut Commands, font: Handle<Font>, theme: &Theme) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(10.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        BloomText,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 24.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        TurnText,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.0),
+                right: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        DifficultyText,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 20.0,
+                color: Color::rgb(1.0, 0.4, 0.3),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(42.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        NoticeText,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(34.0),
+                right: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        ReplayText,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(58.0),
+                right: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        ClockText,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(70.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        OpeningText,
+    ));
+    // On the opening prompt's line, which a drill never needs
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 18.0,
+                color: Color::rgb(0.6, 0.9, 1.0),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(70.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        DrillText,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(94.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        CaptureText,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(118.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        NetText,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(142.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        HoverText,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(166.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        PerfText,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 18.0,
+                color: Color::rgb(1.0, 0.8, 0.3),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(40.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        MistakeText,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(10.0),
+                right: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        ScoreText,
+    ));
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(10.0),
+                    left: Val::Px(0.0),
+                    right: Val::Px(0.0),
+                    ..default()
+                },
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|toolbar| {
+            for (action, label) in ButtonAction::TOOLBAR {
+                spawn_button(toolbar, action, label, font.clone());
+            }
+        });
+    // Black's share fills the evaluation bar from the top, the rest is White's
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        top: Val::Px(90.0),
+                        right: Val::Px(190.0),
+                        bottom: Val::Px(40.0),
+                        ..default()
+                    },
+                    size: Size::width(Val::Px(14.0)),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                background_color: theme.white_stone.into(),
+                ..default()
+            },
+            EvalBarTrack,
+        ))
+        .with_children(|track| {
+            track.spawn((
+                NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Percent(50.0)),
+                        ..default()
+                    },
+                    background_color: theme.black_stone.into(),
+                    ..default()
+                },
+                EvalBar,
+            ));
+        });
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(90.0),
+                    right: Val::Px(10.0),
+                    bottom: Val::Px(40.0),
+                    ..default()
+                },
+                size: Size::width(Val::Px(170.0)),
+                flex_direction: FlexDirection::Column,
+                overflow: Overflow::Hidden,
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.25).into(),
+            ..default()
+        })
+        .with_children(|panel| {
+            panel.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        flex_shrink: 0.0,
+                        padding: UiRect::all(Val::Px(6.0)),
+                        ..default()
+                    },
+                    ..default()
+                },
+                MoveList::default(),
+            ));
+        });
+}
+
+#[derive(Component)]
+pub struct BloomText;
+
+#[derive(Component)]
+pub struct NoticeText;
+
+/// HUD line showing the AI strength, empty when nobody plays against the computer.
+#[derive(Component)]
+pub struct DifficultyText;
+
+/// HUD line showing the replay position while reviewing a game.
+#[derive(Component)]
+pub struct ReplayText;
+
+/// HUD line with both players' remaining time, empty in untimed games.
+#[derive(Component)]
+pub struct ClockText;
+
+/// HUD line prompting the players through the swap2 or pie rule opening.
+#[derive(Component)]
+pub struct OpeningText;
+
+/// HUD line with the drill to solve and the score so far, empty outside `--drill`.
+#[derive(Component)]
+pub struct DrillText;
+
+/// HUD line with each player's captured pairs in Pente, empty otherwise.
+#[derive(Component)]
+pub struct CaptureText;
+
+/// Shows how the online game's connection stands.
+#[derive(Component)]
+pub struct NetText;
+
+/// HUD line naming the intersection under the cursor, empty off the board.
+#[derive(Component)]
+pub struct HoverText;
+
+/// HUD line with the mistake review's verdict on the move just stepped to.
+#[derive(Component)]
+pub struct MistakeText;
+
+/// The performance HUD: frame rate and the computer's last search, empty while it's off.
+#[derive(Component)]
+pub struct PerfText;
+
+/// HUD line with the session's tally of wins and draws.
+#[derive(Component)]
+pub struct ScoreText;
+
+/// HUD line announcing whose turn it is, or the result once the game ends.
+#[derive(Component)]
+pub struct TurnText;
+
+/// The panel announcing the result once a game ends, over the middle of the board.
+#[derive(Component)]
+pub struct GameOverPanel;
+
+/// The evaluation bar beside the board, in White's color.
+#[derive(Component)]
+pub struct EvalBarTrack;
+
+/// Black's part of the evaluation bar, filled from the top.
+#[derive(Component)]
+pub struct EvalBar;
+
+/// What an on-screen button does; clicking one sends it as an event to the system that
+/// handles the same keyboard shortcut.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ButtonAction {
+    Restart,
+    Undo,
+    Hint,
+    Bloom,
+    /// Step back into the finished game, as Ctrl+Left does.
+    Review,
+}
+
+impl ButtonAction {
+    /// The row along the top of the window, with their labels.
+    pub const TOOLBAR: [(ButtonAction, &'static str); 4] = [
+        (ButtonAction::Restart, "Restart (R)"),
+        (ButtonAction::Undo, "Undo (U)"),
+        (ButtonAction::Hint, "Hint (H)"),
+        (ButtonAction::Bloom, "Bloom"),
+    ];
+    pub const IDLE: Color = Color::rgba(0.15, 0.15, 0.15, 0.8);
+    pub const HOVERED: Color = Color::rgba(0.3, 0.3, 0.3, 0.9);
+    pub const PRESSED: Color = Color::rgba(0.2, 0.45, 0.8, 0.9);
+}
+
+pub fn spawn_button(
+    parent: &mut ChildBuilder,
+    action: ButtonAction,
+    label: &str,
+    font: Handle<Font>,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    margin: UiRect::horizontal(Val::Px(4.0)),
+                    padding: UiRect::new(Val::Px(10.0), Val::Px(10.0), Val::Px(4.0), Val::Px(4.0)),
+                    ..default()
+                },
+                background_color: ButtonAction::IDLE.into(),
+                ..default()
+            },
+            action,
+        ))
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font,
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                },
+            ));
+        });
+}
+
+/// Shades the toolbar buttons as the mouse hovers over and presses them, and reports each
+/// press.
+pub fn toolbar_system(
+    mut buttons: Query<(&Interaction, &ButtonAction, &mut BackgroundColor), Changed<Interaction>>,
+    mut clicks: EventWriter<ButtonAction>,
+) {
+    for (interaction, &button, mut color) in &mut buttons {
+        color.0 = match interaction {
+            Interaction::Clicked => {
+                clicks.send(button);
+                ButtonAction::PRESSED
+            }
+            Interaction::Hovered => ButtonAction::HOVERED,
+            Interaction::None => ButtonAction::IDLE,
+        };
+    }
+}
+
+/// A short-lived HUD message, such as a warning about a refused move.
+#[derive(Resource, Default)]
+pub struct Notice {
+    pub message: String,
+    pub timer: Timer,
+}
+
+impl Notice {
+    pub const DURATION: f32 = 2.0;
+
+    pub fn show(&mut self, message: impl Into<String>) {
+        self.message = message.into();
+        self.timer = Timer::from_seconds(Self::DURATION, TimerMode::Once);
+    }
+}
+
+pub fn notice_system(
+    time: Res<Time>,
+    mut notice: ResMut<Notice>,
+    mut text: Query<&mut Text, With<NoticeText>>,
+) {
+    if notice.timer.finished() && notice.message.is_empty() {
+        return;
+    }
+
+    notice.timer.tick(time.delta());
+    if notice.timer.finished() {
+        notice.message.clear();
+    }
+    text.single_mut().sections[0].value = notice.message.clone();
+}
+
+pub fn turn_indicator_system(
+    current_player: Res<CurrentPlayer>,
+    mut text: Query<&mut Text, With<TurnText>>,
+) {
+    if current_player.is_changed() {
+        text.single_mut().sections[0].value = format!("{} to move", current_player.0.name());
+    }
+}
+
+/// Picks the AI strength with the 1, 2 and 3 keys, and cycles its personality.
+pub fn difficulty_system(
+    keycode: Res<Input<KeyCode>>,
+    keys: keys::Keys,
+    ai: Res<AiOpponent>,
+    mut difficulty: ResMut<ai::AiDifficulty>,
+    mut personality: ResMut<ai::AiPersonality>,
+    mut text: Query<&mut Text, With<DifficultyText>>,
+) {
+    for (key, level) in [
+        (KeyCode::Key1, ai::AiDifficulty::Easy),
+        (KeyCode::Key2, ai::AiDifficulty::Medium),
+        (KeyCode::Key3, ai::AiDifficulty::Hard),
+    ] {
+        if keycode.just_pressed(key) {
+            *difficulty = level;
+        }
+    }
+    if keys.just_pressed(keys::Action::AiPersonality) {
+        *personality = personality.next();
+    }
+
+    if difficulty.is_changed() || personality.is_changed() {
+        text.single_mut().sections[0].value = match ai.color {
+            Some(_) => format!("AI: {}, {} (1-3)", difficulty.name(), personality.name()),
+            None => String::new(),
+        };
+    }
+}
+
+/// Names the intersection under the cursor, such as K10, rewriting the HUD line only when
+/// the cursor moves onto another one.
+pub fn hover_coordinate_system(
+    layout: Res<BoardLayout>,
+    mouse_state: Res<MouseState>,
+    config: Res<BoardConfig>,
+    mut shown: Local<Option<(usize, usize)>>,
+    mut text: Query<&mut Text, With<HoverText>>,
+) {
+    let hovered = mouse_state
+        .cursor_pos
+        .and_then(|pos| layout.cursor_to_grid(pos, config.size));
+    if hovered == *shown {
+        return;
+    }
+    *shown = hovered;
+    text.single_mut().sections[0].value = hovered.map_or_else(String::new, |(col, row)| {
+        format!("{}{}", column_label(col), row + 1)
+    });
+}
+
+pub fn capture_count_system(
+    variant: Res<rules::Variant>,
+    history: Res<MoveHistory>,
+    mut text: Query<&mut Text, With<CaptureText>>,
+) {
+    if *variant != rules::Variant::Pente || !history.is_changed() {
+        return;
+    }
+    text.single_mut().sections[0].value = format!(
+        "Captures: Black {}  White {}",
+        capture_count(&history, StoneColor::Black),
+        capture_count(&history, StoneColor::White)
+    );
+}
+
+/// Shows the frame rate and the computer's last search on the HUD, toggled with F3 and off
+/// to begin with.
+pub fn perf_hud_system(
+    keys: keys::Keys,
+    diagnostics: Res<Diagnostics>,
+    ai_stats: Res<AiStats>,
+    mut shown: Local<bool>,
+    mut text: Query<&mut Text, With<PerfText>>,
+) {
+    if keys.just_pressed(Action::PerfHud) {
+        *shown = !*shown;
+        if !*shown {
+            text.single_mut().sections[0].value.clear();
+        }
+    }
+    if !*shown {
+        return;
+    }
+
+    let smoothed = |id| {
+        diagnostics
+            .get(id)
+            .and_then(|diagnostic| diagnostic.smoothed())
+            .unwrap_or(0.0)
+    };
+    let mut value = format!(
+        "{:.0} FPS, {:.1} ms a frame",
+        smoothed(FrameTimeDiagnosticsPlugin::FPS),
+        smoothed(FrameTimeDiagnosticsPlugin::FRAME_TIME)
+    );
+    if let Some((stats, took)) = ai_stats.last {
+        value.push_str(&format!(
+            "\nAI: {} nodes to depth {} in {took:.2?}, {} from the table",
+            stats.nodes, stats.depth, stats.table_hits
+        ));
+    }
+    text.single_mut().sections[0].value = value;
+}
+
+/// Scores are squashed into the bar with `tanh(score / EVAL_BAR_SCALE)`, so a lead worth a
+/// simple four fills most of it.
+pub const EVAL_BAR_SCALE: f32 = 10_000.0;
+
+/// Fills the evaluation bar by how far `ai::evaluate` rates Black ahead, after every move,
+/// undo or restart; an empty board shows it even.
+pub fn eval_bar_system(
+    board: Res<BoardState>,
+    rules: Res<rules::RuleSet>,
+    theme: Res<theme::Theme>,
+    mut shown: Local<Option<u64>>,
+    mut bars: Query<(&mut Style, &mut BackgroundColor), With<EvalBar>>,
+    mut tracks: Query<&mut BackgroundColor, (With<EvalBarTrack>, Without<EvalBar>)>,
+) {
+    if theme.is_changed() {
+        for (_, mut color) in &mut bars {
+            color.0 = theme.black_stone;
+        }
+        for mut color in &mut tracks {
+            color.0 = theme.white_stone;
+        }
+    }
+    if *shown == Some(board.hash()) {
+        return;
+    }
+    *shown = Some(board.hash());
+
+    let advantage =
+        (ai::evaluate(&board, StoneColor::Black, rules.win_length) as f32 / EVAL_BAR_SCALE).tanh();
+    for (mut style, _) in &mut bars {
+        style.size.height = Val::Percent(50.0 * (1.0 + advantage));
+    }
+}
+
+/// Games won by each side and drawn since the app started; a restart starts another game but
+/// keeps the tally.
+#[derive(Resource, Default)]
+pub struct Scoreboard {
+    pub black_wins: usize,
+    pub white_wins: usize,
+    pub draws: usize,
+    /// Whether the game on the board has been counted, so stepping back over its end and
+    /// forward again doesn't count it twice.
+    pub counted: bool,
+}
+
+/// Counts each game once as it ends and shows the tally, as "You" and "AI" against the
+/// computer.
+pub fn scoreboard_system(
+    ai: Res<AiOpponent>,
+    history: Res<MoveHistory>,
+    drill: Res<drill::DrillState>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut game_over_events: EventReader<GameOver>,
+    mut text: Query<&mut Text, With<ScoreText>>,
+) {
+    // A drill keeps its own score
+    if drill.enabled {
+        return;
+    }
+    if history.0.is_empty() && scoreboard.counted {
+        scoreboard.counted = false;
+    }
+    for event in game_over_events.iter() {
+        if scoreboard.counted {
+            continue;
+        }
+        scoreboard.counted = true;
+        match event.winner {
+            Some(StoneColor::Black) => scoreboard.black_wins += 1,
+            Some(StoneColor::White) => scoreboard.white_wins += 1,
+            None => scoreboard.draws += 1,
+        }
+    }
+
+    if scoreboard.is_changed() {
+        let (black, white) = match ai.color {
+            Some(StoneColor::White) => ("You", "AI"),
+            Some(StoneColor::Black) => ("AI", "You"),
+            None => ("Black", "White"),
+        };
+        text.single_mut().sections[0].value = format!(
+            "{black} {} \u{2013} {white} {} \u{2013} Draws {}",
+            scoreboard.black_wins, scoreboard.white_wins, scoreboard.draws
+        );
+    }
+}
+
+/// Announces the result in the HUD and in a panel offering to play again or review the game.
+/// The panel goes away once the game is back in progress, after a restart or a step back.
+pub fn game_over_system(
+    mut commands: Commands,
+    history: Res<MoveHistory>,
+    status: Res<GameStatus>,
+    online: Res<Online>,
+    font: Res<UiFont>,
+    mut game_over_events: EventReader<GameOver>,
+    mut text: Query<&mut Text, With<TurnText>>,
+    panels: Query<Entity, With<GameOverPanel>>,
+) {
+    if status.is_changed() && *status == GameStatus::InProgress {
+        for entity in &panels {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    for event in game_over_events.iter() {
+        let result = match (event.winner, history.resigned()) {
+            (Some(winner), Some(loser)) => {
+                format!("{} resigns \u{2014} {} wins", loser.name(), winner.name())
+            }
+            (Some(winner), None) => format!("{} wins!", winner.name()),
+            (None, _) => "Draw".to_string(),
+        };
+        text.single_mut().sections[0].value = result.clone();
+
+        for entity in &panels {
+            commands.entity(entity).despawn_recursive();
+        }
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect::all(Val::Px(0.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                },
+                GameOverPanel,
+            ))
+            .with_children(|overlay| {
+                overlay
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            padding: UiRect::all(Val::Px(20.0)),
+                            gap: Size::height(Val::Px(16.0)),
+                            ..default()
+                        },
+                        background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+                        ..default()
+                    })
+                    .with_children(|panel| {
+                        panel.spawn(TextBundle::from_section(
+                            result,
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 36.0,
+                                color: Color::WHITE,
+                            },
+                        ));
+                        panel.spawn(NodeBundle::default()).with_children(|buttons| {
+                            // An online game can't be restarted from one side
+                            if online.0.is_none() {
+                                spawn_button(
+                                    buttons,
+                                    ButtonAction::Restart,
+                                    "Play Again",
+                                    font.0.clone(),
+                                );
+                            }
+                            spawn_button(buttons, ButtonAction::Review, "Review", font.0.clone());
+                        });
+                    });
+            });
+    }
+}
+
+/// The side panel's column of moves. `position` is how far it is scrolled, from 0 down to
+/// minus the overflow, and `follow` keeps the latest move in view until the user scrolls up.
+#[derive(Component)]
+pub struct MoveList {
+    pub position: f32,
+    pub follow: bool,
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        MoveList {
+            position: 0.0,
+            follow: true,
+        }
+    }
+}
+
+/// Rebuilds the side panel's entries, such as "12. White K10", whenever `MoveHistory` changes.
+pub fn move_list_system(
+    mut commands: Commands,
+    history: Res<MoveHistory>,
+    font: Res<UiFont>,
+    mut lists: Query<(Entity, &mut MoveList)>,
+) {
+    if !history.is_changed() {
+        return;
+    }
+
+    let style = TextStyle {
+        font: font.0.clone(),
+        font_size: 16.0,
+        color: Color::WHITE,
+    };
+    for (entity, mut list) in &mut lists {
+        list.follow = true;
+        commands.entity(entity).despawn_descendants();
+        commands.entity(entity).with_children(|list| {
+            for (number, entry) in history.0.iter().enumerate() {
+                let step = match entry {
+                    HistoryEntry::Stone(record) => format!(
+                        "{} {}{}",
+                        record.color.name(),
+                        column_label(record.col),
+                        record.row + 1
+                    ),
+                    HistoryEntry::Resign(color) => format!("{} resigns", color.name()),
+                };
+                list.spawn(TextBundle::from_section(
+                    format!("{}. {step}", number + 1),
+                    style.clone(),
+                ));
+            }
+        });
+    }
+}
+
+/// Scrolls the side panel with the mouse wheel while the cursor is over it, or keeps it at
+/// the bottom while following.
+pub fn move_list_scroll_system(
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    windows: Query<&Window>,
+    mouse_state: Res<MouseState>,
+    mut lists: Query<(&mut MoveList, &mut Style, &Parent, &Node)>,
+    nodes: Query<(&Node, &GlobalTransform)>,
+) {
+    let scrolled: f32 = mouse_wheel_events
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y * 20.0,
+            MouseScrollUnit::Pixel => event.y,
+        })
+        .sum();
+    let window = windows.single();
+
+    for (mut list, mut style, parent, node) in &mut lists {
+        let Ok((panel, transform)) = nodes.get(parent.get()) else {
+            continue;
+        };
+        let hovered = mouse_state
+            .cursor_pos
+            .is_some_and(|cursor| cursor_over_node(cursor, window, panel, transform));
+        let scrolled = if hovered { scrolled } else { 0.0 };
+        let max_scroll = (node.size().y - panel.size().y).max(0.0);
+        if scrolled != 0.0 {
+            list.position = (list.position + scrolled).clamp(-max_scroll, 0.0);
+            list.follow = list.position <= -max_scroll;
+        } else if list.follow {
+            list.position = -max_scroll;
+        }
+        if style.position.top != Val::Px(list.position) {
+            style.position.top = Val::Px(list.position);
+        }
+    }
+}
@@ -0,0 +1,386 @@
+//! Which key triggers which action, read from the `[keys]` table of `gomoku.toml` over the
+//! defaults.
+//!
+//! The arrow keys that move the board cursor, the number keys of the difficulty and the
+//! openings, and the bloom tuning letters keep their keys. So does Ctrl+Z, which undoes as
+//! well as the undo key.
+
+use std::collections::HashMap;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+/// Something a key does. The ones marked Ctrl are triggered by their key held with Ctrl.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Undo,
+    /// Ctrl.
+    Redo,
+    Restart,
+    Hint,
+    Resign,
+    /// Plays the stone at the keyboard cursor.
+    Place,
+    /// A second key for `Place`, left to bloom while the visual settings are open.
+    PlaceAlternate,
+    ConfirmPlacement,
+    EditMode,
+    MoveNumbers,
+    SymbolStones,
+    StoneAnimation,
+    Recency,
+    Analysis,
+    ThreatWarnings,
+    PlanPreview,
+    Theme,
+    Mute,
+    Bloom,
+    VisualSettings,
+    ResetView,
+    SaveSlot,
+    /// Ctrl.
+    SaveSgf,
+    /// Ctrl to start a review; once one is under way, with or without.
+    ReviewBack,
+    /// Ctrl, or plain during a review.
+    ReviewForward,
+    /// Pauses and resumes a review's autoplay; with Ctrl, plays the game from the start.
+    Autoplay,
+    AutoplayFaster,
+    AutoplaySlower,
+    ReviewMistakes,
+    SwapColors,
+    Crosshair,
+    AiPersonality,
+    PerfHud,
+}
+
+impl Action {
+    pub const ALL: [Action; 33] = [
+        Action::Undo,
+        Action::Redo,
+        Action::Restart,
+        Action::Hint,
+        Action::Resign,
+        Action::Place,
+        Action::PlaceAlternate,
+        Action::ConfirmPlacement,
+        Action::EditMode,
+        Action::MoveNumbers,
+        Action::SymbolStones,
+        Action::StoneAnimation,
+        Action::Recency,
+        Action::Analysis,
+        Action::ThreatWarnings,
+        Action::PlanPreview,
+        Action::Theme,
+        Action::Mute,
+        Action::Bloom,
+        Action::VisualSettings,
+        Action::ResetView,
+        Action::SaveSlot,
+        Action::SaveSgf,
+        Action::ReviewBack,
+        Action::ReviewForward,
+        Action::Autoplay,
+        Action::AutoplayFaster,
+        Action::AutoplaySlower,
+        Action::ReviewMistakes,
+        Action::SwapColors,
+        Action::Crosshair,
+        Action::AiPersonality,
+        Action::PerfHud,
+    ];
+
+    /// The action's name in `gomoku.toml`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Restart => "restart",
+            Action::Hint => "hint",
+            Action::Resign => "resign",
+            Action::Place => "place",
+            Action::PlaceAlternate => "place_alternate",
+            Action::ConfirmPlacement => "confirm_placement",
+            Action::EditMode => "edit_mode",
+            Action::MoveNumbers => "move_numbers",
+            Action::SymbolStones => "symbol_stones",
+            Action::StoneAnimation => "stone_animation",
+            Action::Recency => "recency",
+            Action::Analysis => "analysis",
+            Action::ThreatWarnings => "threat_warnings",
+            Action::PlanPreview => "plan_preview",
+            Action::Theme => "theme",
+            Action::Mute => "mute",
+            Action::Bloom => "bloom",
+            Action::VisualSettings => "visual_settings",
+            Action::ResetView => "reset_view",
+            Action::SaveSlot => "save_slot",
+            Action::SaveSgf => "save_sgf",
+            Action::ReviewBack => "review_back",
+            Action::ReviewForward => "review_forward",
+            Action::Autoplay => "autoplay",
+            Action::AutoplayFaster => "autoplay_faster",
+            Action::AutoplaySlower => "autoplay_slower",
+            Action::ReviewMistakes => "review_mistakes",
+            Action::SwapColors => "swap_colors",
+            Action::Crosshair => "crosshair",
+            Action::AiPersonality => "ai_personality",
+            Action::PerfHud => "perf_hud",
+        }
+    }
+
+    fn default_key(self) -> KeyCode {
+        match self {
+            Action::Undo => KeyCode::U,
+            Action::Redo => KeyCode::Y,
+            Action::Restart => KeyCode::R,
+            Action::Hint => KeyCode::H,
+            Action::Resign => KeyCode::Escape,
+            Action::Place => KeyCode::Return,
+            Action::PlaceAlternate => KeyCode::Space,
+            Action::ConfirmPlacement => KeyCode::C,
+            Action::EditMode => KeyCode::X,
+            Action::MoveNumbers => KeyCode::N,
+            Action::SymbolStones => KeyCode::O,
+            Action::StoneAnimation => KeyCode::K,
+            Action::Recency => KeyCode::L,
+            Action::Analysis => KeyCode::V,
+            Action::ThreatWarnings => KeyCode::I,
+            Action::PlanPreview => KeyCode::G,
+            Action::Theme => KeyCode::P,
+            Action::Mute => KeyCode::M,
+            Action::Bloom => KeyCode::Space,
+            Action::VisualSettings => KeyCode::F1,
+            Action::ResetView => KeyCode::Home,
+            Action::SaveSlot => KeyCode::F5,
+            Action::SaveSgf => KeyCode::S,
+            Action::ReviewBack => KeyCode::Left,
+            Action::ReviewForward => KeyCode::Right,
+            Action::Autoplay => KeyCode::Space,
+            Action::AutoplayFaster => KeyCode::Equals,
+            Action::AutoplaySlower => KeyCode::Minus,
+            Action::ReviewMistakes => KeyCode::B,
+            Action::SwapColors => KeyCode::F9,
+            Action::Crosshair => KeyCode::F6,
+            Action::AiPersonality => KeyCode::F4,
+            Action::PerfHud => KeyCode::F3,
+        }
+    }
+
+    fn with_ctrl(self) -> bool {
+        matches!(
+            self,
+            Action::Redo | Action::SaveSgf | Action::ReviewBack | Action::ReviewForward
+        )
+    }
+}
+
+/// The key bound to every action.
+#[derive(Resource, Clone, Debug)]
+pub struct KeyBindings(HashMap<Action, KeyCode>);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings(
+            Action::ALL
+                .iter()
+                .map(|&action| (action, action.default_key()))
+                .collect(),
+        )
+    }
+}
+
+impl KeyBindings {
+    /// The defaults with the keys `config` names, by action name and key name, in their
+    /// place. Unknown actions and keys are reported and skipped. Two plain actions sharing a
+    /// key are reported too; see `clashes`.
+    pub fn from_config(config: &HashMap<String, String>) -> Self {
+        let mut bindings = KeyBindings::default();
+        for (name, key_name) in config {
+            let Some(action) = Action::ALL.into_iter().find(|action| action.name() == name) else {
+                warn!("Ignoring key binding for unknown action {name:?}");
+                continue;
+            };
+            match parse_key(key_name) {
+                Some(key) => {
+                    bindings.0.insert(action, key);
+                }
+                None => warn!("Ignoring unknown key {key_name:?} for {name}"),
+            }
+        }
+
+        for (first, second) in bindings.clashes() {
+            warn!(
+                "{} and {} are both on {:?}",
+                first.name(),
+                second.name(),
+                bindings.key(first)
+            );
+        }
+        bindings
+    }
+
+    /// Pairs of actions on the same key with the same modifiers, apart from the second place
+    /// key, bloom and autoplay, which take turns on Space: autoplay has it during a review,
+    /// bloom while the visual settings are open, and placing stones the rest of the time.
+    fn clashes(&self) -> Vec<(Action, Action)> {
+        let shared = [Action::PlaceAlternate, Action::Bloom, Action::Autoplay];
+        let mut clashes = Vec::new();
+        for (index, &first) in Action::ALL.iter().enumerate() {
+            for &second in &Action::ALL[index + 1..] {
+                if first.with_ctrl() == second.with_ctrl()
+                    && !(shared.contains(&first) && shared.contains(&second))
+                    && self.key(first) == self.key(second)
+                {
+                    clashes.push((first, second));
+                }
+            }
+        }
+        clashes
+    }
+
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.0[&action]
+    }
+}
+
+/// Keys that can be bound, looked up by their `KeyCode` name.
+const BINDABLE_KEYS: [KeyCode; 66] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Escape,
+    KeyCode::Space,
+    KeyCode::Return,
+    KeyCode::Tab,
+    KeyCode::Back,
+    KeyCode::Delete,
+    KeyCode::Insert,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Minus,
+    KeyCode::Equals,
+    KeyCode::Comma,
+];
+
+/// The key called `name`, case aside: a `KeyCode` name such as `F5` or `Return`, a digit, or
+/// `Enter`, `Esc` or `Backspace`.
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let name = match name.to_ascii_lowercase().as_str() {
+        "enter" => "return".to_string(),
+        "esc" => "escape".to_string(),
+        "backspace" => "back".to_string(),
+        digit if digit.len() == 1 && digit.as_bytes()[0].is_ascii_digit() => format!("key{digit}"),
+        other => other.to_string(),
+    };
+    BINDABLE_KEYS
+        .into_iter()
+        .find(|key| format!("{key:?}").to_ascii_lowercase() == name)
+}
+
+/// The keyboard as the bindings see it, for systems that react to actions.
+#[derive(SystemParam)]
+pub struct Keys<'w> {
+    pub input: Res<'w, Input<KeyCode>>,
+    pub bindings: Res<'w, KeyBindings>,
+}
+
+impl Keys<'_> {
+    /// Whether `action`'s key was just pressed, with Ctrl held for the Ctrl actions.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.input.just_pressed(self.bindings.key(action)) && (!action.with_ctrl() || self.ctrl())
+    }
+
+    pub fn ctrl(&self) -> bool {
+        self.input
+            .any_pressed([KeyCode::LControl, KeyCode::RControl])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_default_keys_dont_clash() {
+        assert_eq!(KeyBindings::default().clashes(), Vec::new());
+    }
+
+    #[test]
+    fn enter_and_space_both_place_a_stone() {
+        let bindings = KeyBindings::default();
+        assert_eq!(bindings.key(Action::Place), KeyCode::Return);
+        assert_eq!(bindings.key(Action::PlaceAlternate), KeyCode::Space);
+        assert_eq!(bindings.key(Action::Bloom), KeyCode::Space);
+    }
+
+    #[test]
+    fn the_debug_overlays_have_their_own_keys() {
+        let bindings = KeyBindings::default();
+        assert_eq!(bindings.key(Action::PerfHud), KeyCode::F3);
+        assert_eq!(bindings.key(Action::Crosshair), KeyCode::F6);
+    }
+
+    #[test]
+    fn a_configured_key_can_make_a_clash() {
+        let config = HashMap::from([("crosshair".to_string(), "F3".to_string())]);
+        let bindings = KeyBindings::from_config(&config);
+        assert_eq!(bindings.key(Action::Crosshair), KeyCode::F3);
+        assert_eq!(
+            bindings.clashes(),
+            vec![(Action::Crosshair, Action::PerfHud)]
+        );
+    }
+}
@@ -1,181 +1,3124 @@
 //! Illustrates bloom post-processing in 2d.
 
+// Bevy systems declare their dependencies as parameters
+#![allow(clippy::too_many_arguments)]
+
 use bevy::{
+    asset::LoadState,
     core_pipeline::{
         bloom::{BloomCompositeMode, BloomSettings},
         tonemapping::Tonemapping,
     },
+    diagnostic::FrameTimeDiagnosticsPlugin,
+    ecs::system::SystemParam,
+    input::{
+        mouse::{MouseScrollUnit, MouseWheel},
+        InputSystem,
+    },
+    log::LogPlugin,
     prelude::*,
-    sprite::MaterialMesh2dBundle,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    window::WindowResized,
+    winit::{UpdateMode, WinitSettings},
 };
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::time::{Duration, Instant};
+
+use analysis::{AnalysisOverlay, MistakeReview, ThreatWarnings};
+use clock::{GameClocks, IdleDimmer, PlayerClocks, WindowFocus};
+use edit::EditMode;
+use game_core::{BoardState, MoveError, StoneColor, DEFAULT_BOARD_SIZE, SUPPORTED_BOARD_SIZES};
+use hud::{ButtonAction, DrillText, GameOverPanel, MoveList, Notice, Scoreboard};
+use keys::Action;
+use online::{Online, Takeback};
+use opening::Handicap;
+use replay::{AutoplayTimer, ReplayState};
+use save::{AutoArchive, LoadedMoves, SaveSlot};
+
+mod ai;
+mod analysis;
+mod archive;
+mod cli;
+mod clock;
+mod config;
+mod drill;
+mod edit;
+mod game_core;
+mod game_log;
+mod hud;
+mod keys;
+mod net;
+mod online;
+mod opening;
+mod replay;
+mod rules;
+mod save;
+mod selfplay;
+mod sgf;
+mod theme;
+
+fn main() {
+    // Logging is set up before anything else, so the warnings about the config file and the
+    // arguments, and those of the modes without a window, reach it too
+    let mut app = App::new();
+    app.add_plugin(LogPlugin::default());
+    let mut game_config = config::GameConfig::load(config::CONFIG_PATH);
+    let mut args = cli::Args::parse(&game_config, std::env::args().skip(1));
+    if args.renju && args.win_length != rules::DEFAULT_WIN_LENGTH {
+        warn!("The Renju restrictions are only defined for five in a row, ignoring them");
+        args.renju = false;
+    }
+    if args.pente && args.toroidal {
+        warn!("Pente is played on a flat board, ignoring --torus");
+        args.toroidal = false;
+    }
+    let seed = args.seed.unwrap_or_else(rand::random);
+    if args.list_saves {
+        save::print_slots();
+        return;
+    }
+    // Without a window there is nobody to negotiate an opening with
+    let headless_rules = rules::RuleSet {
+        win_length: args.win_length,
+        overline_forbidden: args.overline_forbidden,
+        renju: args.renju,
+        swap2: false,
+        pie: false,
+        first_move_center: args.first_move_center,
+        early_draw: args.early_draw,
+        repetition_draw: args.repetition_draw,
+    };
+    if let Some(dir) = &args.analyze_dir {
+        archive::run(dir, &headless_rules, args.variant());
+        return;
+    }
+    info!("Random seed: {seed} (pass --seed {seed} to replay this game)");
+    if let Some(games) = args.selfplay {
+        let mut rng = StdRng::seed_from_u64(seed);
+        selfplay::run(
+            games,
+            args.size,
+            args.difficulty,
+            &headless_rules,
+            args.variant(),
+            args.think,
+            &mut rng,
+        );
+        return;
+    }
+    let (online, role) = online::connect(&mut args, &mut game_config);
+    if args.drill
+        && (online.is_some() || args.pente || args.win_length != rules::DEFAULT_WIN_LENGTH)
+    {
+        warn!("The drills are five in a row positions to solve alone, ignoring --drill");
+        args.drill = false;
+    }
+    if args.drill
+        && (args.ai || args.swap2 || args.pie || args.handicap > 0 || args.first_move_center)
+    {
+        warn!(
+            "A drill sets up its own position, ignoring --ai, --swap2, --pie, --handicap and \
+             --center-opening"
+        );
+        args.ai = false;
+        args.swap2 = false;
+        args.pie = false;
+        args.handicap = 0;
+        args.first_move_center = false;
+    }
+    // An explicit --load or --load-save wins over the game left from last time; online games
+    // and drills start fresh
+    let from_save = |game: save::SavedGame| LoadedMoves {
+        size: game.size,
+        moves: game.moves(),
+        next_player: Some(game.current_player),
+        resigned: game.resigned,
+        rules: game.rules(),
+    };
+    let loaded = match (args.load.as_deref(), args.load_save.as_deref()) {
+        _ if online.is_some() || args.drill => None,
+        (Some(path), _) => save::read_sgf_file(path),
+        (None, Some(name)) => {
+            let path = save::slot_path(name);
+            if !path.exists() {
+                warn!(
+                    "No save called {name} in {}/, starting a new game",
+                    save::SAVES_DIR
+                );
+            }
+            save::SavedGame::read(path).map(from_save)
+        }
+        (None, None) => save::SavedGame::read(save::SAVE_PATH).map(from_save),
+    };
+    // A loaded game goes on under the rules it was played with, when it says what they were
+    if let Some((rules, variant)) = loaded.as_ref().and_then(|loaded| loaded.rules) {
+        args.set_rules(&rules, variant);
+    }
+    let save_slot = SaveSlot {
+        name: args
+            .save_as
+            .clone()
+            .or_else(|| args.load_save.clone())
+            .unwrap_or_else(save::default_slot_name),
+        written: false,
+        asked_at: None,
+    };
+    let clocks = args.time.map(PlayerClocks::new);
+    let config = BoardConfig {
+        size: loaded.as_ref().map_or(args.size, |loaded| loaded.size),
+    };
+    if args.swap2 && args.ai {
+        warn!("--swap2 needs two players, ignoring it with --ai");
+    }
+    if args.pie && args.ai {
+        warn!("--pie needs two players, ignoring it with --ai");
+    } else if args.pie && args.swap2 {
+        warn!("--pie and --swap2 are both openings, using swap2");
+    }
+    if args.handicap > 0 && (args.swap2 || args.pie) && !args.ai {
+        warn!("A handicap game has no swap opening, ignoring --swap2 and --pie");
+        args.swap2 = false;
+        args.pie = false;
+    }
+    if args.handicap > 0 && args.first_move_center {
+        warn!("The handicap stones open the game, ignoring --center-opening");
+        args.first_move_center = false;
+    }
+    let rules = rules::RuleSet {
+        win_length: args.win_length,
+        overline_forbidden: args.overline_forbidden,
+        renju: args.renju,
+        swap2: args.swap2 && !args.ai,
+        pie: args.pie && !args.ai && !args.swap2,
+        first_move_center: args.first_move_center,
+        early_draw: args.early_draw,
+        repetition_draw: args.repetition_draw,
+    };
+    // A loaded game is already past its opening
+    let opening = match &loaded {
+        Some(loaded) if !loaded.moves.is_empty() => opening::Opening::Done,
+        _ => opening::Opening::start(&rules),
+    };
+
+    let theme = theme::Theme::default();
+
+    app.insert_resource(ClearColor(theme.background))
+        .insert_resource(theme)
+        .insert_resource(MouseState::default()) // 추가: MouseState 리소스 초기화
+        .insert_resource(BoardState::for_variant(config.size, args.variant()))
+        .insert_resource(config)
+        .insert_resource(CurrentPlayer(game_config.first_player))
+        .insert_resource(PlacementCooldown::new(Duration::from_millis(
+            game_config.placement_cooldown_ms,
+        )))
+        .insert_resource(ThreatWarnings(game_config.threat_warnings))
+        .insert_resource(keys::KeyBindings::from_config(&game_config.keys))
+        .insert_resource(Accessibility {
+            symbol_stones: game_config.symbol_stones,
+        })
+        .insert_resource(game_config.stone_animation)
+        .insert_resource(game_config.appearance)
+        .insert_resource(AutoArchive {
+            enabled: game_config.auto_archive && !args.drill,
+            archived: false,
+        })
+        .insert_resource(game_config)
+        .insert_resource(MoveHistory::default())
+        .insert_resource(game_core::PositionCounts::default())
+        .insert_resource(game_log::GameLog::new(args.event_log.clone()))
+        .insert_resource(RedoStack::default())
+        .insert_resource(GameStatus::InProgress)
+        .insert_resource(AiOpponent {
+            color: args.ai.then_some(StoneColor::White),
+            think: args.think,
+        })
+        .insert_resource(args.difficulty)
+        .insert_resource(args.personality)
+        .insert_resource(GameRng(StdRng::seed_from_u64(seed)))
+        .insert_resource(rules)
+        .insert_resource(args.variant())
+        .insert_resource(opening)
+        .insert_resource(Notice::default())
+        .insert_resource(MoveNumbers { visible: false })
+        .insert_resource(ReplayState::default())
+        .insert_resource(SoundEnabled(true))
+        .insert_resource(AiPlan::default())
+        .insert_resource(AiStats::default())
+        .insert_resource(VisualSettings::default())
+        .insert_resource(EditMode(false))
+        .insert_resource(RecencyTrail(false))
+        .insert_resource(AnalysisOverlay(false))
+        .insert_resource(ConfirmPlacement {
+            enabled: args.confirm,
+            pending: None,
+        })
+        .insert_resource(DragPlacement(args.drag))
+        .insert_resource(save_slot)
+        .insert_resource(Handicap {
+            stones: args.handicap,
+            pending: loaded.is_none(),
+        })
+        .insert_resource(BoardLayout::default())
+        .insert_resource(BoardView::default())
+        .insert_resource(AutoplayTimer::default())
+        .insert_resource(Scoreboard::default())
+        .insert_resource(MistakeReview::default())
+        .insert_resource(GameClocks(clocks))
+        .insert_resource(Online(online))
+        .insert_resource(Takeback::default())
+        .insert_resource(drill::DrillState::new(args.drill))
+        .insert_resource(role)
+        .insert_resource(BoardCursor {
+            col: config.size / 2,
+            row: config.size / 2,
+        })
+        .insert_resource(loaded.unwrap_or(LoadedMoves {
+            size: config.size,
+            moves: Vec::new(),
+            next_player: None,
+            resigned: None,
+            rules: None,
+        }))
+        .add_event::<GameOver>()
+        .add_event::<ButtonAction>()
+        .add_plugins(DefaultPlugins.build().disable::<LogPlugin>())
+        .add_plugin(FrameTimeDiagnosticsPlugin)
+        // Redraw only now and then while the player is elsewhere, but often enough for the
+        // computer's search and the network to keep going
+        .insert_resource(WinitSettings {
+            unfocused_mode: UpdateMode::ReactiveLowPower {
+                max_wait: Duration::from_millis(100),
+            },
+            ..default()
+        })
+        .insert_resource(WindowFocus(true))
+        .add_startup_systems(
+            (
+                setup,
+                apply_system_buffers,
+                save::replay_loaded_moves_system,
+            )
+                .chain(),
+        )
+        .add_system(
+            update_bloom_settings
+                .in_base_set(CoreSet::PreUpdate)
+                .after(InputSystem),
+        )
+        .add_system(
+            online::takeback_answer_system
+                .in_base_set(CoreSet::PreUpdate)
+                .after(InputSystem),
+        )
+        .add_system(board_view_system.before(layout_system))
+        .add_system(minimap_system.before(layout_system))
+        .add_system(layout_system)
+        .add_system(custom_assets_system)
+        .add_system(hud::toolbar_system)
+        .add_system(mouse_movement_system) // 추가: 마우스 이동 시스템
+        .add_system(mouse_click_system.after(layout_system))
+        // Before a review's last step ends it, so that Right doesn't move the cursor as well
+        .add_system(
+            keyboard_cursor_system
+                .before(ai_move_system)
+                .before(replay::replay_system),
+        )
+        .add_system(ai_move_system.after(mouse_click_system))
+        .add_system(hover_preview_system.after(ai_move_system))
+        .add_system(crosshair_system.after(layout_system))
+        .add_system(appearance_system)
+        // Before the click handler, so the press that starts a measurement places nothing
+        .add_system(
+            measure_system
+                .after(layout_system)
+                .before(mouse_click_system),
+        )
+        .add_system(hud::hover_coordinate_system.after(layout_system))
+        .add_system(restart_system)
+        .add_system(
+            opening::handicap_system
+                .after(restart_system)
+                .before(ai_move_system),
+        )
+        // After a restart, so the next drill goes straight onto the cleared board
+        .add_system(drill_system.after(restart_system))
+        // After the last move is recorded, so a request for it isn't stale on arrival
+        .add_system(undo_system.after(online::net_send_system))
+        .add_system(redo_system)
+        .add_system(resign_system)
+        .add_system(edit::edit_mode_system)
+        .add_system(edit::swap_colors_system)
+        .add_system(confirm_placement_system)
+        .add_system(edit::remove_stone_system.after(layout_system))
+        .add_system(save::save_sgf_system)
+        .add_system(save::save_on_exit_system)
+        .add_system(save::save_slot_system)
+        .add_system(game_log::game_log_system)
+        // Like the scoreboard, it needs to see the empty board a restart leaves
+        .add_system(
+            save::auto_archive_system
+                .after(restart_system)
+                .before(opening::handicap_system),
+        )
+        .add_system(online::net_send_system)
+        .add_system(online::net_receive_system)
+        .add_system(hud::difficulty_system)
+        .add_system(move_numbers_system.before(opening::opening_system))
+        .add_system(symbol_stones_system)
+        .add_system(stone_animation_system)
+        .add_system(replay::replay_system)
+        .add_system(analysis::mistake_review_system.after(replay::replay_system))
+        .add_system(mute_system)
+        .add_system(analysis::threat_warning_system)
+        .add_system(sound_system)
+        .add_system(stone_anim_system)
+        .add_system(recency_system)
+        .add_system(analysis::analysis_system)
+        .add_system(analysis::hint_system)
+        .add_system(plan_preview_system.after(ai_move_system))
+        .add_system(hud::eval_bar_system)
+        .add_system(hud::perf_hud_system.after(ai_move_system))
+        .add_system(analysis::forbidden_marks_system)
+        .add_system(theme_system)
+        .add_system(clock::focus_system.before(clock::clock_system))
+        .add_system(clock::clock_system.after(hover_preview_system))
+        .add_system(opening::opening_system)
+        .add_system(hud::capture_count_system)
+        .add_system(hud::turn_indicator_system)
+        .add_system(hud::notice_system)
+        .add_system(hud::game_over_system.after(hud::turn_indicator_system))
+        // Between the two, so it sees the empty board a restart leaves before any handicap
+        .add_system(
+            hud::scoreboard_system
+                .after(restart_system)
+                .before(opening::handicap_system),
+        )
+        .add_system(hud::move_list_system)
+        .add_system(hud::move_list_scroll_system.after(hud::move_list_system))
+        .add_system(highlight_win_system)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    config: Res<BoardConfig>,
+    game_config: Res<config::GameConfig>,
+    theme: Res<theme::Theme>,
+) {
+    let custom = &game_config.assets;
+    let board_texture = custom
+        .board_texture
+        .as_ref()
+        .map(|path| (path.clone(), asset_server.load(path.as_str())));
+    let custom_font = custom
+        .font
+        .as_ref()
+        .map(|path| (path.clone(), asset_server.load(path.as_str())));
+
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                hdr: true, // 1. HDR is required for bloom
+                ..default()
+            },
+            tonemapping: Tonemapping::TonyMcMapface, // 2. Using a tonemapper that desaturates to white is recommended
+            ..default()
+        },
+        BloomSettings {
+            intensity: 0.35,
+            ..BloomSettings::default()
+        }, // 3. Enable bloom for the camera
+    ));
+
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
+            transform: Transform::default().with_scale(Vec3::splat(BOARD_EXTENT)),
+            material: materials.add(match &board_texture {
+                Some((_, texture)) => ColorMaterial::from(texture.clone()),
+                None => ColorMaterial::from(theme.board),
+            }),
+            ..default()
+        },
+        BoardSurface,
+    ));
+
+    let appearance = game_config.appearance;
+    // Shared by the lines and star points, so a theme change recolors them all at once
+    let line_material = materials.add(ColorMaterial::from(theme.lines));
+    // A unit square stretched into each line, so a new width only has to rescale it
+    let line_mesh = meshes.add(Mesh::from(shape::Quad::default()));
+
+    for i in 0..config.size {
+        let position = grid_to_world(i, i, &config).x;
+        for horizontal in [true, false] {
+            let translation = if horizontal {
+                Vec3::new(0.0, position, 1.0)
+            } else {
+                Vec3::new(position, 0.0, 1.0)
+            };
+            commands.spawn((
+                MaterialMesh2dBundle {
+                    mesh: line_mesh.clone().into(),
+                    transform: Transform::from_translation(translation)
+                        .with_scale(line_scale(horizontal, appearance.line_width)),
+                    material: line_material.clone(),
+                    ..Default::default()
+                },
+                GridLine,
+                BoardLine { horizontal },
+            ));
+        }
+    }
+
+    let star_point_mesh = meshes.add(Mesh::from(shape::Circle {
+        radius: config.cell_size() * 0.12,
+        ..Default::default()
+    }));
+    for (col, row) in star_points(config.size) {
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: star_point_mesh.clone().into(),
+                transform: Transform::from_translation(
+                    grid_to_world(col, row, &config).extend(1.5),
+                ),
+                material: line_material.clone(),
+                ..Default::default()
+            },
+            GridLine,
+        ));
+    }
+
+    let font = match &custom_font {
+        Some((_, font)) => font.clone(),
+        None => asset_server.load(DEFAULT_FONT),
+    };
+    commands.insert_resource(UiFont(font.clone()));
+    commands.insert_resource(CustomAssets {
+        board_texture,
+        font: custom_font,
+    });
+    commands.insert_resource(Sounds {
+        place: asset_server.load("sounds/stone.ogg"),
+        victory: asset_server.load("sounds/victory.ogg"),
+    });
+    spawn_coordinate_labels(&mut commands, &config, font.clone());
+
+    let preview_material = materials.add(ColorMaterial::from(Color::NONE));
+    let preview = commands
+        .spawn(MaterialMesh2dBundle {
+            mesh: meshes
+                .add(Mesh::from(shape::Circle {
+                    radius: config.cell_size() * appearance.stone_radius_ratio,
+                    ..Default::default()
+                }))
+                .into(),
+            material: preview_material.clone(),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        })
+        .id();
+    // A "no entry" sign: a red disc crossed by a white bar
+    let off_board = commands
+        .spawn(MaterialMesh2dBundle {
+            mesh: meshes
+                .add(Mesh::from(shape::Circle {
+                    radius: config.cell_size() * 0.22,
+                    ..Default::default()
+                }))
+                .into(),
+            material: materials.add(ColorMaterial::from(Color::rgba(0.85, 0.15, 0.15, 0.7))),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        })
+        .with_children(|sign| {
+            sign.spawn(MaterialMesh2dBundle {
+                mesh: meshes
+                    .add(Mesh::from(shape::Quad::new(Vec2::new(
+                        config.cell_size() * 0.3,
+                        config.cell_size() * 0.08,
+                    ))))
+                    .into(),
+                transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                material: materials.add(ColorMaterial::from(Color::rgba(1.0, 1.0, 1.0, 0.8))),
+                ..Default::default()
+            });
+        })
+        .id();
+    commands.insert_resource(HoverPreview {
+        entity: preview,
+        material: preview_material,
+        off_board,
+    });
+
+    let crosshair_material =
+        materials.add(ColorMaterial::from(theme.lines.with_a(Crosshair::ALPHA)));
+    for horizontal in [true, false] {
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: line_mesh.clone().into(),
+                transform: Transform::default()
+                    .with_scale(line_scale(horizontal, appearance.line_width)),
+                material: crosshair_material.clone(),
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            CrosshairLine { horizontal },
+        ));
+    }
+    commands.insert_resource(Crosshair {
+        enabled: true,
+        material: crosshair_material,
+    });
+
+    let recency_mesh = meshes.add(Mesh::from(shape::Circle {
+        radius: config.cell_size() * 0.47,
+        ..Default::default()
+    }));
+    for (age, alpha) in RecencyMarker::ALPHAS.into_iter().enumerate() {
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: recency_mesh.clone().into(),
+                material: materials.add(ColorMaterial::from(Color::rgba(1.0, 0.35, 0.1, alpha))),
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            RecencyMarker { age },
+        ));
+    }
+
+    let cursor_material = materials.add(ColorMaterial::from(Color::rgb(0.2, 0.6, 1.0)));
+    let cursor_side = config.cell_size() * 0.9;
+    let cursor_edge = meshes.add(Mesh::from(shape::Quad {
+        size: Vec2::new(cursor_side, 2.0),
+        ..Default::default()
+    }));
+    commands
+        .spawn((
+            SpatialBundle {
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            BoardCursorHighlight,
+        ))
+        .with_children(|highlight| {
+            for (offset, angle) in [
+                (Vec2::new(0.0, 1.0), 0.0),
+                (Vec2::new(0.0, -1.0), 0.0),
+                (Vec2::new(1.0, 0.0), std::f32::consts::FRAC_PI_2),
+                (Vec2::new(-1.0, 0.0), std::f32::consts::FRAC_PI_2),
+            ] {
+                highlight.spawn(MaterialMesh2dBundle {
+                    mesh: cursor_edge.clone().into(),
+                    transform: Transform::from_translation(
+                        (offset * cursor_side / 2.0).extend(0.0),
+                    )
+                    .with_rotation(Quat::from_rotation_z(angle)),
+                    material: cursor_material.clone(),
+                    ..Default::default()
+                });
+            }
+        });
+
+    if config.size >= MINIMAP_MIN_SIZE {
+        spawn_minimap(&mut commands, theme.board);
+    }
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::all(Val::Percent(100.0)),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..default()
+        },
+        IdleDimmer,
+    ));
+    hud::spawn(&mut commands, font, &theme);
+}
+
+/// The marked intersections (hoshi) of a `size`x`size` board: nine on 19x19 and larger, the
+/// four corner points and the centre on smaller boards.
+fn star_points(size: usize) -> Vec<(usize, usize)> {
+    let center = size / 2;
+    let edge = match size {
+        19 | 25 => {
+            let lines = [3, center, size - 4];
+            return lines
+                .iter()
+                .flat_map(|&col| lines.map(|row| (col, row)))
+                .collect();
+        }
+        13 | 15 => 3,
+        9 => 2,
+        _ => return vec![(center, center)],
+    };
+    let far = size - 1 - edge;
+    vec![
+        (edge, edge),
+        (edge, far),
+        (far, edge),
+        (far, far),
+        (center, center),
+    ]
+}
+
+/// Column letters skip "I" so it can't be mistaken for "J" or the digit 1, the same
+/// convention Go and Gomoku boards use; a 13x13 board is labelled A-N and a 25x25 one A-Z.
+const COLUMN_LETTERS: &[u8] = b"ABCDEFGHJKLMNOPQRSTUVWXYZ";
+
+fn column_label(col: usize) -> char {
+    COLUMN_LETTERS[col] as char
+}
+
+/// Spawns column letters below the board and row numbers (1 at the bottom) to its left.
+fn spawn_coordinate_labels(commands: &mut Commands, config: &BoardConfig, font: Handle<Font>) {
+    let style = TextStyle {
+        font,
+        font_size: 16.0,
+        color: Color::WHITE,
+    };
+    let offset = BOARD_EXTENT / 2.0 + 14.0;
+
+    for i in 0..config.size {
+        let position = grid_to_world(i, i, config);
+        commands.spawn(Text2dBundle {
+            text: Text::from_section(column_label(i), style.clone()),
+            transform: Transform::from_xyz(position.x, -offset, 1.0),
+            ..default()
+        });
+        commands.spawn(Text2dBundle {
+            text: Text::from_section((i + 1).to_string(), style.clone()),
+            transform: Transform::from_xyz(-offset, position.y, 1.0),
+            ..default()
+        });
+    }
+}
+
+/// The font every label and HUD line is drawn with.
+#[derive(Resource)]
+struct UiFont(Handle<Font>);
+
+const DEFAULT_FONT: &str = "fonts/FiraMono-Medium.ttf";
+
+/// The board texture and font asked for in `gomoku.toml`, with their paths, until each has
+/// loaded or failed and been swapped for the default.
+#[derive(Resource)]
+struct CustomAssets {
+    board_texture: Option<(String, Handle<Image>)>,
+    font: Option<(String, Handle<Font>)>,
+}
+
+/// Falls back to the theme's flat board and the built-in font if the configured ones don't
+/// load, for example because the file isn't there.
+fn custom_assets_system(
+    asset_server: Res<AssetServer>,
+    theme: Res<theme::Theme>,
+    mut custom: ResMut<CustomAssets>,
+    mut ui_font: ResMut<UiFont>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    surfaces: Query<&Handle<ColorMaterial>, With<BoardSurface>>,
+    mut texts: Query<&mut Text>,
+) {
+    if let Some((path, texture)) = &custom.board_texture {
+        match asset_server.get_load_state(texture) {
+            LoadState::Loaded => custom.board_texture = None,
+            LoadState::Failed => {
+                warn!("Could not load the board texture {path}, using a plain board");
+                for handle in &surfaces {
+                    if let Some(material) = materials.get_mut(handle) {
+                        *material = ColorMaterial::from(theme.board);
+                    }
+                }
+                custom.board_texture = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((path, font)) = &custom.font {
+        match asset_server.get_load_state(font) {
+            LoadState::Loaded => custom.font = None,
+            LoadState::Failed => {
+                warn!("Could not load the font {path}, using {DEFAULT_FONT}");
+                let fallback = asset_server.load(DEFAULT_FONT);
+                for mut text in &mut texts {
+                    for section in &mut text.sections {
+                        if section.style.font == *font {
+                            section.style.font = fallback.clone();
+                        }
+                    }
+                }
+                ui_font.0 = fallback;
+                custom.font = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+#[derive(Default, Clone, Debug)]
+struct MouseState {
+    cursor_pos: Option<Vec2>,
+    /// Whether the left button went down on the board and is still held, in drag placement.
+    dragging: bool,
+}
+
+impl Resource for MouseState {}
+
+/// Side length of the board quad in world units.
+const BOARD_EXTENT: f32 = 600.0;
+/// How much narrower than the board the grid is, leaving room for lines of the default
+/// width at its edges. The grid keeps its size whatever width the lines are drawn at.
+const GRID_MARGIN: f32 = 2.0;
+
+#[derive(Resource, Clone, Copy, Debug)]
+struct BoardConfig {
+    size: usize,
+}
+
+impl BoardConfig {
+    /// Distance between two neighbouring lines.
+    fn cell_size(&self) -> f32 {
+        (BOARD_EXTENT - GRID_MARGIN) / (self.size - 1) as f32
+    }
+}
+
+/// The player whose stone the next click places.
+#[derive(Resource, Clone, Copy, Debug)]
+struct CurrentPlayer(StoneColor);
+
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+enum GameStatus {
+    InProgress,
+    Won(StoneColor),
+    Draw,
+}
+
+/// The color the computer plays, if any; its strength is the `AiDifficulty` resource.
+#[derive(Resource, Clone, Copy, Debug)]
+struct AiOpponent {
+    color: Option<StoneColor>,
+    /// How long it may think about each move.
+    think: Duration,
+}
+
+/// A placed stone together with the entity drawing it.
+#[derive(Clone, Debug)]
+struct MoveRecord {
+    col: usize,
+    row: usize,
+    color: StoneColor,
+    entity: Entity,
+    /// History indices of the stones this move captured in Pente; they stay in the history
+    /// but are off the board.
+    captures: Vec<usize>,
+}
+
+/// One step of the game: a stone going down, or the player to move giving up.
+#[derive(Clone, Debug)]
+enum HistoryEntry {
+    Stone(MoveRecord),
+    Resign(StoneColor),
+}
+
+impl HistoryEntry {
+    fn stone(&self) -> Option<&MoveRecord> {
+        match self {
+            HistoryEntry::Stone(record) => Some(record),
+            HistoryEntry::Resign(_) => None,
+        }
+    }
+
+    fn stone_mut(&mut self) -> Option<&mut MoveRecord> {
+        match self {
+            HistoryEntry::Stone(record) => Some(record),
+            HistoryEntry::Resign(_) => None,
+        }
+    }
+
+    /// Gives the step to the other color: the stone goes down in it, or it resigns.
+    fn swap_color(&mut self) {
+        match self {
+            HistoryEntry::Stone(record) => record.color = record.color.opponent(),
+            HistoryEntry::Resign(color) => *color = color.opponent(),
+        }
+    }
+}
+
+/// Moves taken back with undo, the latest on top, until a new move is played.
+#[derive(Resource, Default, Debug)]
+struct RedoStack(Vec<HistoryEntry>);
+
+/// Every step of the current game, oldest first. A resignation ends the game, so it can only
+/// be the last entry and the indices of the stones before it never shift.
+#[derive(Resource, Default, Debug)]
+struct MoveHistory(Vec<HistoryEntry>);
+
+impl MoveHistory {
+    /// The stones placed, oldest first, including any captured since.
+    fn stones(&self) -> impl DoubleEndedIterator<Item = &MoveRecord> {
+        self.0.iter().filter_map(HistoryEntry::stone)
+    }
+
+    /// The history index of the latest stone placed at `(col, row)`.
+    fn latest_at(&self, col: usize, row: usize) -> Option<usize> {
+        self.0.iter().rposition(|entry| {
+            entry
+                .stone()
+                .is_some_and(|record| (record.col, record.row) == (col, row))
+        })
+    }
+
+    /// The player who resigned, if that is how the game ended.
+    fn resigned(&self) -> Option<StoneColor> {
+        match self.0.last() {
+            Some(&HistoryEntry::Resign(color)) => Some(color),
+            _ => None,
+        }
+    }
+
+    /// How many entries undo takes back against a computer playing `ai`: its last reply along
+    /// with the move it answered, since taking back the reply alone would only have it play
+    /// again. One entry otherwise.
+    fn undo_plies(&self, ai: Option<StoneColor>) -> usize {
+        let ai_last = self
+            .0
+            .last()
+            .and_then(HistoryEntry::stone)
+            .is_some_and(|record| Some(record.color) == ai);
+        if ai_last && self.0.len() >= 2 {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+/// Marks the entities of stones placed on the board. The entity itself draws the rim, with
+/// the face and highlight as children.
+#[derive(Component)]
+struct Stone;
+
+/// The face of a stone, inset a little from its darker rim.
+#[derive(Component)]
+struct StoneFace;
+
+/// Translucent discs stacked toward a stone's top-left, which together read as a soft glossy
+/// highlight. Each is its radius and its offset up and to the left, both as fractions of the
+/// stone's radius, and its opacity.
+const STONE_HIGHLIGHTS: [(f32, f32, f32); 3] =
+    [(0.7, 0.12, 0.08), (0.45, 0.25, 0.12), (0.2, 0.38, 0.3)];
+
+/// The board's wooden (or themed) surface.
+#[derive(Component)]
+struct BoardSurface;
+
+/// A line or star point of the grid.
+#[derive(Component)]
+struct GridLine;
+
+/// One of the grid's lines, along a row if `horizontal`, else along a column; unlike the star
+/// points, which are `GridLine`s too, it is redrawn when the line width changes.
+#[derive(Component)]
+struct BoardLine {
+    horizontal: bool,
+}
+
+/// The scale that stretches the unit square into a line across the grid `width` thick, along
+/// a row if `horizontal`.
+fn line_scale(horizontal: bool, width: f32) -> Vec3 {
+    let length = BOARD_EXTENT - GRID_MARGIN;
+    if horizontal {
+        Vec3::new(length, width, 1.0)
+    } else {
+        Vec3::new(width, length, 1.0)
+    }
+}
+
+/// Redraws the grid lines, the crosshair and the hover preview when the appearance is tuned.
+/// Stones already on the board keep the size they were placed at.
+fn appearance_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    appearance: Res<config::Appearance>,
+    config: Res<BoardConfig>,
+    preview: Res<HoverPreview>,
+    mut lines: Query<(&BoardLine, &mut Transform), Without<CrosshairLine>>,
+    mut crosshair: Query<(&CrosshairLine, &mut Transform), Without<BoardLine>>,
+) {
+    if !appearance.is_changed() || appearance.is_added() {
+        return;
+    }
+    for (line, mut transform) in &mut lines {
+        transform.scale = line_scale(line.horizontal, appearance.line_width);
+    }
+    for (line, mut transform) in &mut crosshair {
+        transform.scale = line_scale(line.horizontal, appearance.line_width);
+    }
+    let mesh = meshes.add(Mesh::from(shape::Circle {
+        radius: config.cell_size() * appearance.stone_radius_ratio,
+        ..Default::default()
+    }));
+    commands.entity(preview.entity).insert(Mesh2dHandle(mesh));
+}
+
+/// The move number drawn on top of a stone, as a child of its entity.
+#[derive(Component)]
+struct MoveNumberLabel;
+
+/// Whether move numbers are shown on the stones, toggled with N.
+#[derive(Resource)]
+struct MoveNumbers {
+    visible: bool,
+}
+
+/// Aids for players who find the stones' colors hard to tell apart.
+#[derive(Resource)]
+struct Accessibility {
+    /// Whether each stone shows `StoneSymbol`, toggled with O.
+    symbol_stones: bool,
+}
+
+/// A mark in the middle of a stone that tells the colors apart by shape: a dot on Black, a
+/// diamond on White.
+#[derive(Component)]
+struct StoneSymbol;
+
+/// The translucent stone drawn under the cursor, spawned once in setup and reused, and the
+/// sign shown at the nearest edge point instead when the cursor is just off the board.
+#[derive(Resource)]
+struct HoverPreview {
+    entity: Entity,
+    material: Handle<ColorMaterial>,
+    off_board: Entity,
+}
+
+/// Faint guide lines across the board through the hovered intersection; toggled with F6.
+#[derive(Resource)]
+struct Crosshair {
+    enabled: bool,
+    /// Shared by both lines, in the theme's line color.
+    material: Handle<ColorMaterial>,
+}
+
+impl Crosshair {
+    const ALPHA: f32 = 0.3;
+}
+
+/// One of the two crosshair lines, running along a row if `horizontal`, else along a column.
+#[derive(Component)]
+struct CrosshairLine {
+    horizontal: bool,
+}
+
+/// Moves the crosshair onto the row and column of the intersection under the cursor, and
+/// hides it while the cursor is off the board.
+fn crosshair_system(
+    keys: keys::Keys,
+    layout: Res<BoardLayout>,
+    mouse_state: Res<MouseState>,
+    config: Res<BoardConfig>,
+    theme: Res<theme::Theme>,
+    mut crosshair: ResMut<Crosshair>,
+    mut notice: ResMut<Notice>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut lines: Query<(&CrosshairLine, &mut Transform, &mut Visibility)>,
+) {
+    if keys.just_pressed(Action::Crosshair) {
+        crosshair.enabled = !crosshair.enabled;
+        notice.show(if crosshair.enabled {
+            "Crosshair on"
+        } else {
+            "Crosshair off"
+        });
+    }
+    if theme.is_changed() {
+        if let Some(material) = materials.get_mut(&crosshair.material) {
+            material.color = theme.lines.with_a(Crosshair::ALPHA);
+        }
+    }
+
+    let hovered = mouse_state
+        .cursor_pos
+        .and_then(|pos| layout.cursor_to_grid(pos, config.size))
+        .filter(|_| crosshair.enabled);
+    for (line, mut transform, mut visibility) in &mut lines {
+        let Some((col, row)) = hovered else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let point = grid_to_world(col, row, &config);
+        // Over the grid lines, under the stones
+        transform.translation = if line.horizontal {
+            Vec3::new(0.0, point.y, 1.5)
+        } else {
+            Vec3::new(point.x, 0.0, 1.5)
+        };
+        *visibility = Visibility::Inherited;
+    }
+}
+
+/// The line and tally drawn while measuring with Alt and the left button held.
+#[derive(Component)]
+struct MeasureMark;
+
+/// A measurement as drawn: its two ends, and the position and player to move it tallied.
+type Measurement = ((usize, usize), (usize, usize), u64, StoneColor);
+
+/// `end` moved onto the nearest row, column or diagonal through `start`, kept on a `size`
+/// board.
+fn snap_to_line(start: (usize, usize), end: (usize, usize), size: usize) -> (usize, usize) {
+    let (dx, dy) = (end.0 as i32 - start.0 as i32, end.1 as i32 - start.1 as i32);
+    let steps = (dx.abs() + dy.abs() + 1) / 2;
+    let last = size as i32 - 1;
+    [(dx, 0), (0, dy), (steps * dx.signum(), steps * dy.signum())]
+        .into_iter()
+        .filter_map(|(x, y)| {
+            let (col, row) = (start.0 as i32 + x, start.1 as i32 + y);
+            ((0..=last).contains(&col) && (0..=last).contains(&row)).then_some((col, row))
+        })
+        .min_by_key(|&(col, row)| (col - end.0 as i32).pow(2) + (row - end.1 as i32).pow(2))
+        .map_or(start, |(col, row)| (col as usize, row as usize))
+}
+
+/// Holding Alt and dragging from one intersection to another draws the straight line between
+/// them, snapped to the nearest row, column or diagonal, and tallies the points along it for
+/// the player to move: their own stones, the opponent's and the empty points. Letting go
+/// clears it.
+fn measure_system(
+    mut commands: Commands,
+    mut mouse_buttons: ResMut<Input<MouseButton>>,
+    keycode: Res<Input<KeyCode>>,
+    layout: Res<BoardLayout>,
+    mouse_state: Res<MouseState>,
+    config: Res<BoardConfig>,
+    board: Res<BoardState>,
+    current_player: Res<CurrentPlayer>,
+    font: Res<UiFont>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    marks: Query<Entity, With<MeasureMark>>,
+    mut start: Local<Option<(usize, usize)>>,
+    mut drawn: Local<Option<Measurement>>,
+) {
+    let hovered = mouse_state
+        .cursor_pos
+        .and_then(|pos| layout.cursor_to_grid(pos, config.size));
+    let alt = keycode.any_pressed([KeyCode::LAlt, KeyCode::RAlt]);
+    if alt && mouse_buttons.just_pressed(MouseButton::Left) && hovered.is_some() {
+        mouse_buttons.clear_just_pressed(MouseButton::Left);
+        *start = hovered;
+    }
+    if !mouse_buttons.pressed(MouseButton::Left) {
+        *start = None;
+    }
+
+    let state = start.zip(hovered).map(|(from, to)| {
+        let to = snap_to_line(from, to, config.size);
+        (from, to, board.hash(), current_player.0)
+    });
+    if state == *drawn {
+        return;
+    }
+    *drawn = state;
+    for entity in &marks {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some((from, to, _, mover)) = state else {
+        return;
+    };
+
+    let (dx, dy) = (
+        (to.0 as i32 - from.0 as i32).signum(),
+        (to.1 as i32 - from.1 as i32).signum(),
+    );
+    let steps = (to.0 as i32 - from.0 as i32)
+        .abs()
+        .max((to.1 as i32 - from.1 as i32).abs());
+    let (mut own, mut theirs, mut empty) = (0, 0, 0);
+    for step in 0..=steps {
+        let (col, row) = (
+            (from.0 as i32 + step * dx) as usize,
+            (from.1 as i32 + step * dy) as usize,
+        );
+        match board.get(col, row) {
+            None => empty += 1,
+            Some(color) if color == mover => own += 1,
+            Some(_) => theirs += 1,
+        }
+    }
+
+    let color = Color::rgb(0.2, 0.8, 1.0);
+    let start_point = grid_to_world(from.0, from.1, &config);
+    let end_point = grid_to_world(to.0, to.1, &config);
+    let span = end_point - start_point;
+    let thickness = config.cell_size() * 0.1;
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes
+                .add(Mesh::from(shape::Quad {
+                    size: Vec2::new(span.length() + thickness, thickness),
+                    ..Default::default()
+                }))
+                .into(),
+            transform: Transform::from_translation(((start_point + end_point) / 2.0).extend(3.2))
+                .with_rotation(Quat::from_rotation_z(span.y.atan2(span.x))),
+            material: materials.add(ColorMaterial::from(color.with_a(0.7))),
+            ..Default::default()
+        },
+        MeasureMark,
+    ));
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                format!(
+                    "{} points: {own} {}, {theirs} {}, {empty} empty",
+                    steps + 1,
+                    mover.name(),
+                    mover.opponent().name()
+                ),
+                TextStyle {
+                    font: font.0.clone(),
+                    font_size: config.cell_size() * 0.5,
+                    color,
+                },
+            ),
+            transform: Transform::from_translation(
+                (end_point + Vec2::new(0.0, config.cell_size() * 0.7)).extend(3.3),
+            ),
+            ..Default::default()
+        },
+        MeasureMark,
+    ));
+}
+
+/// Intersection picked with the keyboard; arrows move it and Enter or Space plays there.
+#[derive(Resource, Clone, Copy, Debug)]
+struct BoardCursor {
+    col: usize,
+    row: usize,
+}
+
+/// Outline drawn around `BoardCursor`, shown once the arrow keys are used.
+#[derive(Component)]
+struct BoardCursorHighlight;
+
+/// Sent when a placement completes a winning line or fills the board, or a clock runs out;
+/// a draw has no winner.
+struct GameOver {
+    winner: Option<StoneColor>,
+    /// The stones of the winning line, from one end to the other; empty unless won on the board.
+    line: Vec<(usize, usize)>,
+}
+
+impl Resource for BoardState {}
+impl Resource for game_core::PositionCounts {}
+
+/// World units kept in view along the window's shorter side: the board plus its labels,
+/// shown 1:1 in the default 720px-high window.
+const VIEW_EXTENT: f32 = 720.0;
+
+/// Where the board's intersections appear in the window, in logical pixels. `origin` is the
+/// intersection at column 0, row 0 and `cell_size` the distance between lines.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+struct BoardLayout {
+    origin: Vec2,
+    cell_size: f32,
+}
+
+impl BoardLayout {
+    /// Lays out the board for a window of `window_size`, zoomed by `zoom` world units per pixel
+    /// around the world point `pan` in the middle of the window.
+    fn new(window_size: Vec2, zoom: f32, pan: Vec2, config: &BoardConfig) -> Self {
+        BoardLayout {
+            origin: window_size / 2.0 + (grid_to_world(0, 0, config) - pan) / zoom,
+            cell_size: config.cell_size() / zoom,
+        }
+    }
+
+    /// Snaps a cursor position to the nearest intersection, or `None` if that lies off the
+    /// board. Grid indices run from the bottom-left corner of the board.
+    ///
+    /// Bevy 0.10 already reports cursor positions with the origin at the bottom-left of the
+    /// window and Y growing upward, the same way rows count, so no flip is needed here.
+    /// Manual repro: clicking just below the top edge of the board lands on the top row.
+    ///
+    /// A point exactly half a cell past the outer line rounds away from the board and misses.
+    /// Before the first layout `cell_size` is zero and every index comes out infinite or NaN,
+    /// which must miss too rather than cast to column 0.
+    fn cursor_to_grid(&self, cursor_pos: Vec2, size: usize) -> Option<(usize, usize)> {
+        let index = ((cursor_pos - self.origin) / self.cell_size).round();
+        let on_board = 0.0..size as f32;
+        if !on_board.contains(&index.x) || !on_board.contains(&index.y) {
+            return None;
+        }
+        Some((index.x as usize, index.y as usize))
+    }
+
+    /// For a cursor off the board but within `OFF_BOARD_MARGIN` cells of it, the edge
+    /// intersection closest to it.
+    fn nearest_edge_point(&self, cursor_pos: Vec2, size: usize) -> Option<(usize, usize)> {
+        let index = (cursor_pos - self.origin) / self.cell_size;
+        let nearest = index.clamp(Vec2::ZERO, Vec2::splat((size - 1) as f32));
+        if !index.is_finite() || index.distance(nearest) > OFF_BOARD_MARGIN {
+            return None;
+        }
+        let nearest = nearest.round();
+        Some((nearest.x as usize, nearest.y as usize))
+    }
+}
+
+/// How far off the board, in cells, the cursor still gets the "no entry" sign.
+const OFF_BOARD_MARGIN: f32 = 1.5;
+
+/// Zooms the camera so the board fits the window, then by the player's own zoom and pan,
+/// and recomputes `BoardLayout` to match, at startup, whenever the window is resized and
+/// whenever the view moves.
+fn layout_system(
+    mut resized_events: EventReader<WindowResized>,
+    windows: Query<&Window>,
+    config: Res<BoardConfig>,
+    view: Res<BoardView>,
+    mut layout: ResMut<BoardLayout>,
+    mut cameras: Query<(&mut OrthographicProjection, &mut Transform), With<Camera>>,
+) {
+    if resized_events.iter().count() == 0 && !layout.is_added() && !view.is_changed() {
+        return;
+    }
+
+    let window = windows.single();
+    let size = Vec2::new(window.width(), window.height());
+    let zoom = view.scale(size);
+    for (mut projection, mut transform) in &mut cameras {
+        projection.scale = zoom;
+        transform.translation.x = view.pan.x;
+        transform.translation.y = view.pan.y;
+    }
+    *layout = BoardLayout::new(size, zoom, view.pan, &config);
+}
+
+/// How far the player has zoomed in past fitting the board to the window, and the world
+/// point the view is centred on.
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+struct BoardView {
+    zoom: f32,
+    pan: Vec2,
+}
+
+impl Default for BoardView {
+    fn default() -> Self {
+        BoardView {
+            zoom: 1.0,
+            pan: Vec2::ZERO,
+        }
+    }
+}
+
+impl BoardView {
+    /// How much closer than fitting the window the board may be shown.
+    const ZOOM_RANGE: std::ops::RangeInclusive<f32> = 1.0..=4.0;
+    /// Zoom factor per line of mouse wheel.
+    const ZOOM_STEP: f32 = 1.1;
+
+    /// World units per pixel in a window of `window_size`.
+    fn scale(&self, window_size: Vec2) -> f32 {
+        VIEW_EXTENT / window_size.min_element().max(1.0) / self.zoom
+    }
+}
+
+/// Zooms the board view with the mouse wheel, toward the point under the cursor, pans it by
+/// dragging with the middle button, and puts it back with Home. The wheel is left to the
+/// move list while the cursor is over it.
+fn board_view_system(
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    keys: keys::Keys,
+    windows: Query<&Window>,
+    mouse_state: Res<MouseState>,
+    lists: Query<&Parent, With<MoveList>>,
+    nodes: Query<(&Node, &GlobalTransform)>,
+    mut view: ResMut<BoardView>,
+    mut dragged_from: Local<Option<Vec2>>,
+) {
+    if keys.just_pressed(Action::ResetView) && *view != BoardView::default() {
+        *view = BoardView::default();
+    }
+    let window = windows.single();
+    let window_size = Vec2::new(window.width(), window.height());
+    let Some(cursor) = mouse_state.cursor_pos else {
+        mouse_wheel_events.clear();
+        return;
+    };
+
+    let over_list = lists.iter().any(|parent| {
+        nodes
+            .get(parent.get())
+            .is_ok_and(|(node, transform)| cursor_over_node(cursor, window, node, transform))
+    });
+    let lines: f32 = mouse_wheel_events
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 20.0,
+        })
+        .sum();
+    if lines != 0.0 && !over_list {
+        // Keep the world point under the cursor where it is
+        let from_center = cursor - window_size / 2.0;
+        let under_cursor = view.pan + from_center * view.scale(window_size);
+        let zoom = (view.zoom * BoardView::ZOOM_STEP.powf(lines))
+            .clamp(*BoardView::ZOOM_RANGE.start(), *BoardView::ZOOM_RANGE.end());
+        if zoom != view.zoom {
+            view.zoom = zoom;
+            let pan = under_cursor - from_center * view.scale(window_size);
+            view.pan = clamp_pan(pan);
+        }
+    }
+
+    if mouse_buttons.pressed(MouseButton::Middle) {
+        if let Some(from) = dragged_from.replace(cursor) {
+            if from != cursor {
+                let pan = view.pan - (cursor - from) * view.scale(window_size);
+                view.pan = clamp_pan(pan);
+            }
+        }
+    } else {
+        *dragged_from = None;
+    }
+}
+
+/// Boards from this size up get a minimap, since zooming in is needed to play on them.
+const MINIMAP_MIN_SIZE: usize = 20;
+/// Width and height of the minimap, in pixels.
+const MINIMAP_EXTENT: f32 = 150.0;
+const MINIMAP_VIEWPORT_COLOR: Color = Color::rgba(1.0, 0.3, 0.2, 0.9);
+
+/// A small copy of the whole board in the bottom-left corner, with every stone on it and a
+/// frame around the part of the board that is in view. Clicking or dragging on it moves the
+/// view there. A button, so clicks on it don't reach the board underneath.
+#[derive(Component)]
+struct Minimap;
+
+/// One stone's dot on the minimap, redrawn whenever the board changes.
+#[derive(Component)]
+struct MinimapStone;
+
+/// The frame on the minimap around what the main view shows.
+#[derive(Component)]
+struct MinimapViewport;
+
+fn spawn_minimap(commands: &mut Commands, board_color: Color) {
+    let edge = |position: UiRect, size: Size| NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position,
+            size,
+            ..default()
+        },
+        background_color: MINIMAP_VIEWPORT_COLOR.into(),
+        ..default()
+    };
+    let (full, thin) = (Val::Percent(100.0), Val::Px(2.0));
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        bottom: Val::Px(70.0),
+                        left: Val::Px(10.0),
+                        ..default()
+                    },
+                    size: Size::all(Val::Px(MINIMAP_EXTENT)),
+                    overflow: Overflow::Hidden,
+                    ..default()
+                },
+                background_color: board_color.into(),
+                ..default()
+            },
+            Minimap,
+        ))
+        .with_children(|map| {
+            map.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        ..default()
+                    },
+                    ..default()
+                },
+                MinimapViewport,
+            ))
+            .with_children(|frame| {
+                let at = |top, left| UiRect {
+                    top,
+                    left,
+                    ..default()
+                };
+                let zero = Val::Px(0.0);
+                frame.spawn(edge(at(zero, zero), Size::new(full, thin)));
+                frame.spawn(edge(at(zero, zero), Size::new(thin, full)));
+                frame.spawn(edge(
+                    UiRect {
+                        bottom: zero,
+                        left: zero,
+                        ..default()
+                    },
+                    Size::new(full, thin),
+                ));
+                frame.spawn(edge(
+                    UiRect {
+                        top: zero,
+                        right: zero,
+                        ..default()
+                    },
+                    Size::new(thin, full),
+                ));
+            });
+        });
+}
+
+/// Where the world point `world` falls on the minimap, in pixels from its top-left corner.
+fn world_to_minimap(world: Vec2, config: &BoardConfig) -> Vec2 {
+    let cell = MINIMAP_EXTENT / config.size as f32;
+    let grid = (world - grid_to_world(0, 0, config)) / config.cell_size();
+    Vec2::new(grid.x + 0.5, config.size as f32 - 0.5 - grid.y) * cell
+}
+
+fn minimap_to_world(point: Vec2, config: &BoardConfig) -> Vec2 {
+    let cell = MINIMAP_EXTENT / config.size as f32;
+    let grid = Vec2::new(
+        point.x / cell - 0.5,
+        config.size as f32 - 0.5 - point.y / cell,
+    );
+    grid_to_world(0, 0, config) + grid * config.cell_size()
+}
+
+/// Redraws the minimap's stones when the board or the theme changes, moves its frame with
+/// the view, and pans the view to wherever the minimap is pressed.
+fn minimap_system(
+    mut commands: Commands,
+    windows: Query<&Window>,
+    mouse_state: Res<MouseState>,
+    config: Res<BoardConfig>,
+    board: Res<BoardState>,
+    theme: Res<theme::Theme>,
+    mut view: ResMut<BoardView>,
+    mut maps: MinimapQuery,
+    dots: Query<Entity, With<MinimapStone>>,
+    mut frames: Query<&mut Style, (With<MinimapViewport>, Without<Minimap>)>,
+) {
+    let Ok((map, interaction, node, transform, mut background)) = maps.get_single_mut() else {
+        return;
+    };
+    let window = windows.single();
+
+    if *interaction == Interaction::Clicked {
+        if let Some(cursor) = mouse_state.cursor_pos {
+            let from_top = Vec2::new(cursor.x, window.height() - cursor.y);
+            let corner = transform.translation().truncate() - node.size() / 2.0;
+            let pan = clamp_pan(minimap_to_world(from_top - corner, &config));
+            if pan != view.pan {
+                view.pan = pan;
+            }
+        }
+    }
+
+    if board.is_changed() || theme.is_changed() {
+        background.0 = theme.board;
+        for dot in &dots {
+            commands.entity(dot).despawn();
+        }
+        let cell = MINIMAP_EXTENT / config.size as f32;
+        let mut spawned = Vec::new();
+        for row in 0..config.size {
+            for col in 0..config.size {
+                let Some(color) = board.get(col, row) else {
+                    continue;
+                };
+                let top_left = Vec2::new(col as f32, (config.size - 1 - row) as f32) * cell;
+                let dot = NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: Val::Px(top_left.x + cell * 0.1),
+                            top: Val::Px(top_left.y + cell * 0.1),
+                            ..default()
+                        },
+                        size: Size::all(Val::Px(cell * 0.8)),
+                        ..default()
+                    },
+                    background_color: theme.stone(color).into(),
+                    ..default()
+                };
+                spawned.push(commands.spawn((dot, MinimapStone)).id());
+            }
+        }
+        // Under the frame, which is the map's first child
+        commands.entity(map).insert_children(0, &spawned);
+    }
+
+    // The frame is kept within the map even when more than the board is in view
+    let window_size = Vec2::new(window.width(), window.height());
+    let half_view = window_size / 2.0 * view.scale(window_size);
+    let top_left = world_to_minimap(view.pan + Vec2::new(-half_view.x, half_view.y), &config)
+        .clamp(Vec2::ZERO, Vec2::splat(MINIMAP_EXTENT));
+    let bottom_right = world_to_minimap(view.pan + Vec2::new(half_view.x, -half_view.y), &config)
+        .clamp(Vec2::ZERO, Vec2::splat(MINIMAP_EXTENT));
+    let position = UiRect {
+        left: Val::Px(top_left.x),
+        top: Val::Px(top_left.y),
+        ..default()
+    };
+    let size = Size::new(
+        Val::Px(bottom_right.x - top_left.x),
+        Val::Px(bottom_right.y - top_left.y),
+    );
+    for mut style in &mut frames {
+        if style.position != position || style.size != size {
+            style.position = position;
+            style.size = size;
+        }
+    }
+}
+
+type MinimapQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Interaction,
+        &'static Node,
+        &'static GlobalTransform,
+        &'static mut BackgroundColor,
+    ),
+    With<Minimap>,
+>;
+
+/// Keeps the middle of the view over the board.
+fn clamp_pan(pan: Vec2) -> Vec2 {
+    pan.clamp(
+        Vec2::splat(-BOARD_EXTENT / 2.0),
+        Vec2::splat(BOARD_EXTENT / 2.0),
+    )
+}
+
+/// Whether `cursor`, in window coordinates from the bottom-left, is over the UI node laid out
+/// as `node` at `transform`, whose coordinates run down from the top-left.
+fn cursor_over_node(
+    cursor: Vec2,
+    window: &Window,
+    node: &Node,
+    transform: &GlobalTransform,
+) -> bool {
+    let from_top = Vec2::new(cursor.x, window.height() - cursor.y);
+    Rect::from_center_size(transform.translation().truncate(), node.size()).contains(from_top)
+}
+
+fn grid_to_world(col: usize, row: usize, config: &BoardConfig) -> Vec2 {
+    let half_span = (BOARD_EXTENT - GRID_MARGIN) / 2.0;
+    Vec2::new(col as f32, row as f32) * config.cell_size() - Vec2::splat(half_span)
+}
+
+// 추가: 마우스 이동 시스템
+fn mouse_movement_system(
+    mut cursor_moved_events: EventReader<CursorMoved>,
+    mut mouse_state: ResMut<MouseState>,
+) {
+    for event in cursor_moved_events.iter() {
+        mouse_state.cursor_pos = Some(event.position);
+    }
+}
+
+/// Everything needed to put a stone on the board, shared by every source of moves so they
+/// all go through the same validation.
+#[derive(SystemParam)]
+struct StonePlacer<'w, 's> {
+    commands: Commands<'w, 's>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<ColorMaterial>>,
+    config: Res<'w, BoardConfig>,
+    board: ResMut<'w, BoardState>,
+    current_player: ResMut<'w, CurrentPlayer>,
+    history: ResMut<'w, MoveHistory>,
+    redo: ResMut<'w, RedoStack>,
+    status: ResMut<'w, GameStatus>,
+    rules: Res<'w, rules::RuleSet>,
+    variant: Res<'w, rules::Variant>,
+    look: StoneLook<'w>,
+    log: ResMut<'w, game_log::GameLog>,
+    positions: ResMut<'w, game_core::PositionCounts>,
+    game_over_events: EventWriter<'w, GameOver>,
+}
+
+/// The settings a new stone is drawn with.
+#[derive(SystemParam)]
+struct StoneLook<'w> {
+    font: Res<'w, UiFont>,
+    move_numbers: Res<'w, MoveNumbers>,
+    accessibility: Res<'w, Accessibility>,
+    theme: Res<'w, theme::Theme>,
+    anim_style: Res<'w, config::AnimStyle>,
+    appearance: Res<'w, config::Appearance>,
+}
+
+impl StonePlacer<'_, '_> {
+    /// Places the current player's stone at `(col, row)`, then checks for a win or draw and
+    /// passes the turn. Changes nothing if the move isn't allowed.
+    fn place(&mut self, col: usize, row: usize) -> Result<(), MoveError> {
+        if *self.status != GameStatus::InProgress {
+            return Err(MoveError::GameOver);
+        }
+        let stone = self.current_player.0;
+        let mut placement = game_core::apply_move(
+            &mut self.board,
+            col,
+            row,
+            stone,
+            &self.rules,
+            *self.variant,
+            capture_count(&self.history, stone),
+        )?;
+        placement.count_position(&mut self.positions, &self.board, stone, &self.rules);
+        self.current_player.0 = stone.opponent();
+        self.log.record(game_log::GameEvent::MovePlaced {
+            col,
+            row,
+            color: stone,
+            captured: placement.captured.len(),
+        });
+
+        let mut captures = Vec::new();
+        for (x, y) in placement.captured {
+            // The latest move at a point is the stone standing there
+            let Some(index) = self.history.latest_at(x, y) else {
+                continue;
+            };
+            if let Some(record) = self.history.0[index].stone() {
+                self.commands.entity(record.entity).despawn_recursive();
+            }
+            captures.push(index);
+        }
+
+        match placement.outcome {
+            game_core::Outcome::Continues => {
+                self.log.record(game_log::GameEvent::TurnChanged {
+                    to: stone.opponent(),
+                });
+            }
+            game_core::Outcome::Won { winner, line } => {
+                *self.status = GameStatus::Won(winner);
+                self.log.record(game_log::GameEvent::Won { winner });
+                self.game_over_events.send(GameOver {
+                    winner: Some(winner),
+                    line,
+                });
+            }
+            game_core::Outcome::Draw => {
+                *self.status = GameStatus::Draw;
+                self.log.record(game_log::GameEvent::Draw);
+                self.game_over_events.send(GameOver {
+                    winner: None,
+                    line: Vec::new(),
+                });
+            }
+        }
+
+        let entity = self.spawn_stone(col, row, stone, self.history.0.len() + 1);
+        self.history.0.push(HistoryEntry::Stone(MoveRecord {
+            col,
+            row,
+            color: stone,
+            entity,
+            captures,
+        }));
+        self.redo.0.clear();
+        Ok(())
+    }
+
+    /// Gives the game up for the current player, handing the win to their opponent.
+    fn resign(&mut self) -> Result<(), MoveError> {
+        if *self.status != GameStatus::InProgress {
+            return Err(MoveError::GameOver);
+        }
+        let color = self.current_player.0;
+        *self.status = GameStatus::Won(color.opponent());
+        self.log.record(game_log::GameEvent::Resigned { color });
+        self.log.record(game_log::GameEvent::Won {
+            winner: color.opponent(),
+        });
+        self.history.0.push(HistoryEntry::Resign(color));
+        self.redo.0.clear();
+        self.game_over_events.send(GameOver {
+            winner: Some(color.opponent()),
+            line: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Takes back the last move, returning the stones it captured to the board, and hands the
+    /// turn back to whoever played it. A resignation is withdrawn the same way. Returns what was
+    /// taken back, if there was anything.
+    fn take_back(&mut self) -> Option<HistoryEntry> {
+        let record = match self.history.0.pop()? {
+            HistoryEntry::Stone(record) => record,
+            HistoryEntry::Resign(color) => {
+                self.current_player.0 = color;
+                *self.status = GameStatus::InProgress;
+                self.log
+                    .record(game_log::GameEvent::Undo { color, point: None });
+                return Some(HistoryEntry::Resign(color));
+            }
+        };
+        self.log.record(game_log::GameEvent::Undo {
+            color: record.color,
+            point: Some((record.col, record.row)),
+        });
+        self.positions.forget(&self.board, record.color.opponent());
+        self.commands.entity(record.entity).despawn_recursive();
+        self.board.remove(record.col, record.row);
+        for &index in &record.captures {
+            let Some(captured) = self.history.0[index].stone() else {
+                continue;
+            };
+            let (col, row, color) = (captured.col, captured.row, captured.color);
+            self.board.set(col, row, color);
+            let entity = self.spawn_stone(col, row, color, index + 1);
+            if let Some(captured) = self.history.0[index].stone_mut() {
+                captured.entity = entity;
+            }
+        }
+        self.current_player.0 = record.color;
+        // Taking back the winning move reopens the game
+        *self.status = GameStatus::InProgress;
+        Some(HistoryEntry::Stone(record))
+    }
+
+    /// Turns every stone the other color, in the history and the redo stack as well as on the
+    /// board, and hands the turn to the other player, so the position is the same with the
+    /// sides swapped. The stones on the board are drawn again in their new colors.
+    fn swap_colors(&mut self) {
+        let on_board: Vec<usize> = (0..self.history.0.len())
+            .filter(|&index| {
+                self.history.0[index].stone().is_some_and(|record| {
+                    self.board.get(record.col, record.row) == Some(record.color)
+                        && self.history.latest_at(record.col, record.row) == Some(index)
+                })
+            })
+            .collect();
+        self.board.swap_colors();
+        self.current_player.0 = self.current_player.0.opponent();
+        // The positions so far were counted in their old colors; counting starts over
+        self.positions.start(&self.board, self.current_player.0);
+        for entry in self.history.0.iter_mut().chain(&mut self.redo.0) {
+            entry.swap_color();
+        }
+        for index in on_board {
+            let Some(record) = self.history.0[index].stone() else {
+                continue;
+            };
+            let (col, row, color, old) = (record.col, record.row, record.color, record.entity);
+            self.commands.entity(old).despawn_recursive();
+            let entity = self.spawn_stone(col, row, color, index + 1);
+            if let Some(record) = self.history.0[index].stone_mut() {
+                record.entity = entity;
+            }
+        }
+    }
+
+    /// Plays the latest taken-back move again for whoever made it. Returns false if there was
+    /// nothing to redo.
+    fn redo(&mut self) -> bool {
+        let Some(entry) = self.redo.0.pop() else {
+            return false;
+        };
+        // Placing clears the stack, but the moves under this one are still ahead
+        let rest = std::mem::take(&mut self.redo.0);
+        let result = match entry {
+            HistoryEntry::Stone(record) => {
+                self.current_player.0 = record.color;
+                self.place(record.col, record.row)
+            }
+            HistoryEntry::Resign(color) => {
+                self.current_player.0 = color;
+                self.resign()
+            }
+        };
+        self.redo.0 = rest;
+        result.is_ok()
+    }
+
+    /// Spawns the entity drawing a `color` stone at `(col, row)`, labelled with its move number.
+    fn spawn_stone(&mut self, col: usize, row: usize, color: StoneColor, number: usize) -> Entity {
+        let world_pos = grid_to_world(col, row, &self.config);
+        let radius = self.config.cell_size() * self.look.appearance.stone_radius_ratio;
+        let mut disc = |radius| {
+            self.meshes.add(Mesh::from(shape::Circle {
+                radius,
+                ..Default::default()
+            }))
+        };
+        let rim = disc(radius);
+        let face = disc(radius * 0.9);
+        let highlights: Vec<_> = STONE_HIGHLIGHTS
+            .iter()
+            .map(|&(size, offset, alpha)| {
+                // Brighter than white, so the bloom catches the shine on white stones
+                let material = ColorMaterial::from(Color::rgba(1.3, 1.3, 1.3, alpha));
+                (
+                    disc(radius * size),
+                    radius * offset,
+                    self.materials.add(material),
+                )
+            })
+            .collect();
+        let rim_material = self
+            .materials
+            .add(ColorMaterial::from(self.look.theme.stone_edge(color)));
+        let face_material = self
+            .materials
+            .add(ColorMaterial::from(self.look.theme.stone(color)));
+        let symbol = self.meshes.add(match color {
+            StoneColor::Black => Mesh::from(shape::Circle::new(radius * 0.25)),
+            StoneColor::White => Mesh::from(shape::RegularPolygon::new(radius * 0.35, 4)),
+        });
+        let symbol_material = self
+            .materials
+            .add(ColorMaterial::from(self.look.theme.label(color)));
+        let anim = PlaceAnim::new(
+            *self.look.anim_style,
+            Vec3::new(world_pos.x, world_pos.y, 2.0),
+            self.config.cell_size(),
+        );
+
+        self.commands
+            .spawn((
+                MaterialMesh2dBundle {
+                    mesh: rim.into(),
+                    transform: anim.start_transform(),
+                    material: rim_material,
+                    ..Default::default()
+                },
+                Stone,
+                anim,
+            ))
+            .with_children(|stone_entity| {
+                stone_entity.spawn((
+                    MaterialMesh2dBundle {
+                        mesh: face.into(),
+                        transform: Transform::from_xyz(0.0, 0.0, 0.01),
+                        material: face_material,
+                        ..Default::default()
+                    },
+                    StoneFace,
+                ));
+                stone_entity.spawn((
+                    MaterialMesh2dBundle {
+                        mesh: symbol.into(),
+                        transform: Transform::from_xyz(0.0, 0.0, 0.06),
+                        material: symbol_material,
+                        visibility: if self.look.accessibility.symbol_stones {
+                            Visibility::Inherited
+                        } else {
+                            Visibility::Hidden
+                        },
+                        ..Default::default()
+                    },
+                    StoneSymbol,
+                ));
+                for (layer, (mesh, offset, material)) in highlights.into_iter().enumerate() {
+                    stone_entity.spawn(MaterialMesh2dBundle {
+                        mesh: mesh.into(),
+                        transform: Transform::from_xyz(-offset, offset, 0.02 + 0.01 * layer as f32),
+                        material,
+                        ..Default::default()
+                    });
+                }
+                stone_entity.spawn((
+                    Text2dBundle {
+                        text: Text::from_section(
+                            number.to_string(),
+                            TextStyle {
+                                font: self.look.font.0.clone(),
+                                font_size: self.config.cell_size() * 0.45,
+                                color: self.look.theme.label(color),
+                            },
+                        ),
+                        transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                        visibility: if self.look.move_numbers.visible {
+                            Visibility::Inherited
+                        } else {
+                            Visibility::Hidden
+                        },
+                        ..default()
+                    },
+                    MoveNumberLabel,
+                ));
+            })
+            .id()
+    }
+}
+
+/// Pairs of stones `color` has captured so far in a Pente game.
+fn capture_count(history: &MoveHistory, color: StoneColor) -> usize {
+    history
+        .stones()
+        .filter(|record| record.color == color)
+        .map(|record| record.captures.len() / 2)
+        .sum()
+}
+
+fn mouse_click_system(
+    time: Res<Time>,
+    layout: Res<BoardLayout>,
+    mouse_button_inputs: Res<Input<MouseButton>>,
+    mut mouse_state: ResMut<MouseState>,
+    ai: Res<AiOpponent>,
+    role: Res<net::Role>,
+    replay: Res<ReplayState>,
+    opening: Res<opening::Opening>,
+    drag: Res<DragPlacement>,
+    mut cooldown: ResMut<PlacementCooldown>,
+    mut confirm: ResMut<ConfirmPlacement>,
+    mut notice: ResMut<Notice>,
+    buttons: Query<&Interaction, With<Button>>,
+    game_over_panels: Query<(), With<GameOverPanel>>,
+    mut placer: StonePlacer,
+) {
+    cooldown.tick(time.delta());
+    // A release ends the drag whether or not it places anything
+    let drag_released =
+        mouse_button_inputs.just_released(MouseButton::Left) && mouse_state.dragging;
+    if drag_released {
+        mouse_state.dragging = false;
+    }
+    // A click on a button is the button's, not the board's
+    if !cooldown.ready()
+        || buttons
+            .iter()
+            .any(|&interaction| interaction != Interaction::None)
+        || !game_over_panels.is_empty()
+        || ai.color == Some(placer.current_player.0)
+        || !role.may_play(placer.current_player.0)
+        || replay.reviewing()
+        || !opening.accepts_moves()
+    {
+        return;
+    }
+
+    let clicked = if drag.0 {
+        // Only a press on the board starts a drag, so one that began on a button can't
+        // place a stone when it is let go over the board
+        if mouse_button_inputs.just_pressed(MouseButton::Left)
+            && mouse_state
+                .cursor_pos
+                .and_then(|pos| layout.cursor_to_grid(pos, placer.config.size))
+                .is_some()
+        {
+            mouse_state.dragging = true;
+        }
+        drag_released
+    } else {
+        mouse_button_inputs.just_pressed(MouseButton::Left)
+    };
+
+    if clicked {
+        if let Some(pos) = mouse_state.cursor_pos {
+            let Some((col, row)) = layout.cursor_to_grid(pos, placer.config.size) else {
+                return;
+            };
+            if confirm.enabled && confirm.pending != Some((col, row)) {
+                if placer.board.get(col, row).is_none() {
+                    confirm.pending = Some((col, row));
+                }
+                return;
+            }
+            confirm.pending = None;
+            match placer.place(col, row) {
+                Ok(()) => cooldown.start(),
+                Err(MoveError::Forbidden(kind)) => {
+                    notice.show(format!("Forbidden move: {}", kind.name()));
+                }
+                Err(MoveError::OffCenter) => notice.show(OFF_CENTER_NOTICE),
+                Err(MoveError::NearCenter) => notice.show(NEAR_CENTER_NOTICE),
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+/// Shown when the first stone is tried off the center point under `--center-opening`.
+const OFF_CENTER_NOTICE: &str = "The first stone goes on the center point";
+/// Shown when Black's second stone in Pente is tried too close to the center.
+const NEAR_CENTER_NOTICE: &str = "Black's second stone goes at least three points from the center";
+
+/// Ignores clicks for a moment after one places a stone, so a single click can't land two
+/// and rapid clicking doesn't run ahead of the board. Only mouse placement waits on it.
+#[derive(Resource)]
+struct PlacementCooldown(Timer);
+
+impl PlacementCooldown {
+    fn new(duration: Duration) -> Self {
+        let mut timer = Timer::new(duration, TimerMode::Once);
+        // Nothing has been placed yet
+        timer.tick(duration);
+        PlacementCooldown(timer)
+    }
+
+    /// Lets `delta` of the wait go by.
+    fn tick(&mut self, delta: Duration) {
+        self.0.tick(delta);
+    }
+
+    /// Whether a click may place a stone now.
+    fn ready(&self) -> bool {
+        self.0.finished()
+    }
+
+    /// Starts the wait, after a click placed a stone.
+    fn start(&mut self) {
+        self.0.reset();
+    }
+}
+
+/// Moves the board cursor with the arrow keys, clamped to the board, and plays at it with
+/// Enter or Space through the same checks as a click; Space is bloom's while the visual
+/// settings are open. The highlight hides again when the mouse moves.
+fn keyboard_cursor_system(
+    keys: keys::Keys,
+    mouse_state: Res<MouseState>,
+    ai: Res<AiOpponent>,
+    role: Res<net::Role>,
+    replay: Res<ReplayState>,
+    opening: Res<opening::Opening>,
+    visual: Res<VisualSettings>,
+    mut cursor: ResMut<BoardCursor>,
+    mut notice: ResMut<Notice>,
+    mut placer: StonePlacer,
+    mut highlight: Query<(&mut Transform, &mut Visibility), With<BoardCursorHighlight>>,
+) {
+    let (mut transform, mut visibility) = highlight.single_mut();
+    if mouse_state.is_changed() {
+        *visibility = Visibility::Hidden;
+    }
+    // The arrows step through a review instead, and a stone can't go down during one anyway
+    if keys.ctrl() || replay.reviewing() {
+        return;
+    }
+
+    let last = placer.config.size - 1;
+    let mut moved = true;
+    if keys.input.just_pressed(KeyCode::Left) {
+        cursor.col = cursor.col.saturating_sub(1);
+    } else if keys.input.just_pressed(KeyCode::Right) {
+        cursor.col = (cursor.col + 1).min(last);
+    } else if keys.input.just_pressed(KeyCode::Down) {
+        cursor.row = cursor.row.saturating_sub(1);
+    } else if keys.input.just_pressed(KeyCode::Up) {
+        cursor.row = (cursor.row + 1).min(last);
+    } else {
+        moved = false;
+    }
+    if moved {
+        *visibility = Visibility::Visible;
+    }
+    let world_pos = grid_to_world(cursor.col, cursor.row, &placer.config);
+    transform.translation = world_pos.extend(2.5);
+
+    let place =
+        keys.just_pressed(Action::Place) || keys.just_pressed(Action::PlaceAlternate) && !visual.0;
+    if place
+        && ai.color != Some(placer.current_player.0)
+        && role.may_play(placer.current_player.0)
+        && opening.accepts_moves()
+    {
+        match placer.place(cursor.col, cursor.row) {
+            Err(MoveError::Forbidden(kind)) => {
+                notice.show(format!("Forbidden move: {}", kind.name()));
+            }
+            Err(MoveError::OffCenter) => notice.show(OFF_CENTER_NOTICE),
+            Err(MoveError::NearCenter) => notice.show(NEAR_CENTER_NOTICE),
+            _ => {}
+        }
+    }
+}
+
+/// Plays the computer's reply as soon as it is its turn, straight from the opening book
+/// when the position is in it.
+fn ai_move_system(
+    ai: Res<AiOpponent>,
+    difficulty: Res<ai::AiDifficulty>,
+    personality: Res<ai::AiPersonality>,
+    replay: Res<ReplayState>,
+    mut rng: ResMut<GameRng>,
+    mut plan: ResMut<AiPlan>,
+    mut ai_stats: ResMut<AiStats>,
+    mut placer: StonePlacer,
+) {
+    if *placer.status != GameStatus::InProgress
+        || ai.color != Some(placer.current_player.0)
+        || replay.reviewing()
+    {
+        return;
+    }
+
+    let book = ai::book_move(
+        &placer.board,
+        placer.current_player.0,
+        *difficulty,
+        &placer.rules,
+        *placer.variant,
+    );
+    let started = Instant::now();
+    let (line, stats) = match book {
+        Some(cell) => (vec![cell], ai::SearchStats::default()),
+        None => ai::choose_move(
+            &placer.board,
+            placer.current_player.0,
+            *difficulty,
+            *personality,
+            &placer.rules,
+            *placer.variant,
+            ai.think,
+            &mut rng.0,
+        ),
+    };
+    ai_stats.last = Some((stats, started.elapsed()));
+    if book.is_some() {
+        debug!("AI played from the opening book");
+    } else if stats.nodes > 0 {
+        debug!(
+            "AI searched {} positions to depth {}, {} of them answered from the transposition table",
+            stats.nodes, stats.depth, stats.table_hits
+        );
+    }
+    let (col, row) = line[0];
+    match placer.place(col, row) {
+        Ok(()) => {
+            plan.moves = line[1..].to_vec();
+            plan.hash = placer.board.hash();
+        }
+        Err(err) => warn!("AI move {:?} rejected: {:?}", (col, row), err),
+    }
+}
+
+/// How the computer's last move was found, for the performance HUD. The search figures are
+/// all 0 when the move came from the opening book or a forced win.
+#[derive(Resource, Default, Debug)]
+struct AiStats {
+    /// The search's statistics and the time it took; `None` until the computer has moved.
+    last: Option<(ai::SearchStats, Duration)>,
+}
+
+/// The replies the computer expected when it last moved, shown as numbered ghost stones
+/// while `shown` is on. Toggled with G.
+#[derive(Resource, Default)]
+struct AiPlan {
+    shown: bool,
+    moves: Vec<(usize, usize)>,
+    /// The position the plan follows on from; once the board changes it is out of date.
+    hash: u64,
+}
+
+impl AiPlan {
+    /// Predicted moves drawn at most, since the far end of the line is the least reliable.
+    const SHOWN_MOVES: usize = 6;
+}
+
+/// One ghost stone of the computer's plan, numbered in the order it expects the moves.
+#[derive(Component)]
+struct PlanGhost;
+
+/// Draws the computer's plan over the board while it's toggled on and still follows from
+/// the position on the board; the next real move clears it.
+fn plan_preview_system(
+    mut commands: Commands,
+    keys: keys::Keys,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<BoardConfig>,
+    board: Res<BoardState>,
+    current_player: Res<CurrentPlayer>,
+    theme: Res<theme::Theme>,
+    appearance: Res<config::Appearance>,
+    font: Res<UiFont>,
+    mut plan: ResMut<AiPlan>,
+    mut notice: ResMut<Notice>,
+    ghosts: Query<Entity, With<PlanGhost>>,
+    mut drawn: Local<Option<(bool, u64, u64)>>,
+) {
+    if keys.just_pressed(Action::PlanPreview) {
+        plan.shown = !plan.shown;
+        notice.show(if plan.shown {
+            "AI plan preview on"
+        } else {
+            "AI plan preview off"
+        });
+    }
+    let state = (plan.shown, plan.hash, board.hash());
+    if *drawn == Some(state) {
+        return;
+    }
+    *drawn = Some(state);
+
+    for entity in &ghosts {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !plan.shown || plan.hash != board.hash() {
+        return;
+    }
+
+    let mesh = meshes.add(Mesh::from(shape::Circle {
+        radius: config.cell_size() * appearance.stone_radius_ratio,
+        ..Default::default()
+    }));
+    let mut color = current_player.0;
+    for (number, &(col, row)) in plan.moves.iter().take(AiPlan::SHOWN_MOVES).enumerate() {
+        commands
+            .spawn((
+                MaterialMesh2dBundle {
+                    mesh: mesh.clone().into(),
+                    material: materials.add(ColorMaterial::from(theme.stone(color).with_a(0.35))),
+                    transform: Transform::from_translation(
+                        grid_to_world(col, row, &config).extend(2.4),
+                    ),
+                    ..Default::default()
+                },
+                PlanGhost,
+            ))
+            .with_children(|ghost| {
+                ghost.spawn(Text2dBundle {
+                    text: Text::from_section(
+                        (number + 1).to_string(),
+                        TextStyle {
+                            font: font.0.clone(),
+                            font_size: config.cell_size() * 0.45,
+                            color: theme.label(color).with_a(0.8),
+                        },
+                    ),
+                    transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                    ..Default::default()
+                });
+            });
+        color = color.opponent();
+    }
+}
 
-fn main() {
-    App::new()
-        .insert_resource(ClearColor(Color::DARK_GRAY))
-        .insert_resource(MouseState::default()) // 추가: MouseState 리소스 초기화
-        .add_plugins(DefaultPlugins)
-        .add_startup_system(setup)
-        .add_system(update_bloom_settings)
-        .add_system(mouse_movement_system) // 추가: 마우스 이동 시스템
-        .add_system(mouse_click_system)
-        .run();
+/// The source of every random choice the computer makes, seeded from `--seed` so the same
+/// seed and the same moves from the human replay a game exactly.
+#[derive(Resource)]
+struct GameRng(StdRng);
+
+fn hover_preview_system(
+    layout: Res<BoardLayout>,
+    mouse_state: Res<MouseState>,
+    config: Res<BoardConfig>,
+    board: Res<BoardState>,
+    current_player: Res<CurrentPlayer>,
+    status: Res<GameStatus>,
+    replay: Res<ReplayState>,
+    confirm: Res<ConfirmPlacement>,
+    theme: Res<theme::Theme>,
+    preview: Res<HoverPreview>,
+    mut previews: Query<(&mut Transform, &mut Visibility)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let accepting = *status == GameStatus::InProgress && !replay.reviewing();
+    let hovered = mouse_state
+        .cursor_pos
+        .map(|pos| (pos, layout.cursor_to_grid(pos, config.size)));
+
+    // `None` from the mapping means a click here would miss; say so at the closest edge point
+    let edge_point = match hovered {
+        Some((pos, None)) if accepting && confirm.pending.is_none() => {
+            layout.nearest_edge_point(pos, config.size)
+        }
+        _ => None,
+    };
+    if let Ok((mut transform, mut visibility)) = previews.get_mut(preview.off_board) {
+        match edge_point {
+            Some((col, row)) => {
+                transform.translation = grid_to_world(col, row, &config).extend(2.6);
+                *visibility = Visibility::Visible;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+
+    let Ok((mut transform, mut visibility)) = previews.get_mut(preview.entity) else {
+        return;
+    };
+
+    // A tap waiting for confirmation keeps the ghost in place instead of following the cursor
+    let cell = confirm
+        .pending
+        .or_else(|| hovered.and_then(|(_, cell)| cell))
+        .filter(|_| accepting)
+        .filter(|&(col, row)| board.get(col, row).is_none());
+
+    let Some((col, row)) = cell else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let world_pos = grid_to_world(col, row, &config);
+    transform.translation = Vec3::new(world_pos.x, world_pos.y, 2.0);
+    *visibility = Visibility::Visible;
+    // A held drag shows the stone about to be placed more firmly than a plain hover
+    let alpha = if mouse_state.dragging { 0.7 } else { 0.4 };
+    let color = theme.stone(current_player.0).with_a(alpha);
+    if materials.get(&preview.material).map(|m| m.color) != Some(color) {
+        if let Some(material) = materials.get_mut(&preview.material) {
+            material.color = color;
+        }
+    }
 }
 
-fn setup(
+fn restart_system(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    asset_server: Res<AssetServer>,
+    keys: keys::Keys,
+    stones: Query<Entity, With<Stone>>,
+    mut board: ResMut<BoardState>,
+    mut current_player: ResMut<CurrentPlayer>,
+    mut history: ResMut<MoveHistory>,
+    (mut redo, mut log, mut positions): (
+        ResMut<RedoStack>,
+        ResMut<game_log::GameLog>,
+        ResMut<game_core::PositionCounts>,
+    ),
+    mut status: ResMut<GameStatus>,
+    mut replay: ResMut<ReplayState>,
+    mut clocks: ResMut<GameClocks>,
+    rules: Res<rules::RuleSet>,
+    game_config: Res<config::GameConfig>,
+    online: Res<Online>,
+    mut opening: ResMut<opening::Opening>,
+    mut handicap: ResMut<Handicap>,
+    mut clicks: EventReader<ButtonAction>,
 ) {
-    commands.spawn((
-        Camera2dBundle {
-            camera: Camera {
-                hdr: true, // 1. HDR is required for bloom
-                ..default()
-            },
-            tonemapping: Tonemapping::TonyMcMapface, // 2. Using a tonemapper that desaturates to white is recommended
-            ..default()
-        },
-        BloomSettings {
-            intensity: 0.35,
-            ..BloomSettings::default()
-        }, // 3. Enable bloom for the camera
-    ));
+    let clicked = clicks.iter().any(|&button| button == ButtonAction::Restart);
+    // Both boards of an online game have to stay the same, so it can't be reset on one side
+    if !(keys.just_pressed(Action::Restart) || clicked) || online.0.is_some() {
+        return;
+    }
 
-    commands.spawn(MaterialMesh2dBundle {
-        mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-        transform: Transform::default().with_scale(Vec3::splat(600.)),
-        material: materials.add(ColorMaterial::from(Color::rgb(0.92, 0.75, 0.45))),
-        ..default()
-    });
+    for entity in &stones {
+        commands.entity(entity).despawn_recursive();
+    }
+    board.clear();
+    current_player.0 = game_config.first_player;
+    history.0.clear();
+    redo.0.clear();
+    positions.start(&board, current_player.0);
+    *status = GameStatus::InProgress;
+    *replay = ReplayState::default();
+    if let Some(clocks) = &mut clocks.0 {
+        *clocks = PlayerClocks::new(clocks.main_time);
+    }
+    *opening = opening::Opening::start(&rules);
+    handicap.pending = true;
+    log.record(game_log::GameEvent::Restart);
+}
 
-    let line_width = 2.0;
-    let line_length = 600.0 - line_width;
-    let line_color = Color::rgb(0.0, 0.0, 0.0);
+/// A right answer, as a translucent stone, shown once a drill is answered wrongly.
+#[derive(Component)]
+struct DrillAnswer;
 
-    for i in 0..19 {
-        let position = -300.0 + line_width / 2.0 + i as f32 * (line_length / 18.0);
-        commands.spawn(MaterialMesh2dBundle {
-            mesh: meshes
-                .add(Mesh::from(shape::Quad {
-                    size: Vec2::new(line_length, line_width),
-                    ..Default::default()
-                }))
-                .into(),
-            transform: Transform::from_translation(Vec3::new(0.0, position, 1.0)),
-            material: materials.add(ColorMaterial::from(line_color)),
-            ..Default::default()
+/// Sets up a random drill whenever the board is cleared, and judges the move played on it.
+/// A wrong answer marks the right ones; undo takes it back for another try, which is judged
+/// but not scored, and a restart moves on to the next drill.
+fn drill_system(
+    mut drill: ResMut<drill::DrillState>,
+    mut rng: ResMut<GameRng>,
+    mut notice: ResMut<Notice>,
+    answers: Query<Entity, With<DrillAnswer>>,
+    mut text: Query<&mut Text, With<DrillText>>,
+    mut placer: StonePlacer,
+) {
+    if !drill.enabled {
+        return;
+    }
+
+    let moves = placer.history.0.len();
+    if drill.current.is_none() || moves < drill.setup {
+        for entity in &answers {
+            placer.commands.entity(entity).despawn();
+        }
+        // A different drill from the last, where there is one
+        let index = match drill.current {
+            Some(last) => (last + rng.0.gen_range(1..drill::DRILLS.len())) % drill::DRILLS.len(),
+            None => rng.0.gen_range(0..drill::DRILLS.len()),
+        };
+        // Black's answers could be forbidden under Renju
+        let solver = if placer.rules.renju || rng.0.gen() {
+            StoneColor::White
+        } else {
+            StoneColor::Black
+        };
+        let layout = drill::DRILLS[index].lay_out(placer.config.size, rng.0.gen_range(0..8));
+        let stones = (layout
+            .theirs
+            .iter()
+            .map(|&point| (point, solver.opponent())))
+        .chain(layout.mine.iter().map(|&point| (point, solver)));
+        for ((col, row), color) in stones {
+            placer.current_player.0 = color;
+            if let Err(err) = placer.place(col, row) {
+                warn!(
+                    "Could not set up a drill stone at {}{}: {err:?}",
+                    column_label(col),
+                    row + 1
+                );
+            }
+        }
+        placer.current_player.0 = solver;
+        drill.start(index, solver, layout.answers, placer.history.0.len());
+        text.single_mut().sections[0].value = drill.status();
+        return;
+    }
+
+    if moves == drill.setup {
+        drill.judged = false;
+    }
+    if moves != drill.setup + 1 || drill.judged {
+        return;
+    }
+    let Some(&MoveRecord { col, row, .. }) = placer.history.0[drill.setup].stone() else {
+        return;
+    };
+    // Any five is right, whether or not the drill asked for one
+    let is_right =
+        drill.answers.contains(&(col, row)) || *placer.status == GameStatus::Won(drill.solver);
+    let first = !drill.answered;
+    drill.judge(is_right);
+    text.single_mut().sections[0].value = drill.status();
+    if is_right {
+        notice.show(if first {
+            "Right! Press R for the next drill"
+        } else {
+            "That's the one. Press R for the next drill"
         });
-        commands.spawn(MaterialMesh2dBundle {
-            mesh: meshes
-                .add(Mesh::from(shape::Quad {
-                    size: Vec2::new(line_width, line_length),
+        return;
+    }
+
+    let points: Vec<String> = drill
+        .answers
+        .iter()
+        .map(|&(col, row)| format!("{}{}", column_label(col), row + 1))
+        .collect();
+    notice.show(format!(
+        "Not quite: {} is the move. U to try again, R for the next drill",
+        points.join(" or ")
+    ));
+    if answers.is_empty() {
+        let stone = placer.look.theme.stone(drill.solver).with_a(0.5);
+        let radius = placer.config.cell_size() * placer.look.appearance.stone_radius_ratio;
+        for &(col, row) in &drill.answers {
+            let mesh = placer.meshes.add(Mesh::from(shape::Circle {
+                radius,
+                ..Default::default()
+            }));
+            let material = placer.materials.add(ColorMaterial::from(stone));
+            placer.commands.spawn((
+                MaterialMesh2dBundle {
+                    mesh: mesh.into(),
+                    material,
+                    transform: Transform::from_translation(
+                        grid_to_world(col, row, &placer.config).extend(2.4),
+                    ),
                     ..Default::default()
-                }))
-                .into(),
-            transform: Transform::from_translation(Vec3::new(position, 0.0, 1.0)),
-            material: materials.add(ColorMaterial::from(line_color)),
-            ..Default::default()
-        });
+                },
+                DrillAnswer,
+            ));
+        }
     }
+}
 
-    // UI
-    commands.spawn(
-        TextBundle::from_section(
-            "",
-            TextStyle {
-                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                font_size: 18.0,
-                color: Color::WHITE,
-            },
-        )
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            position: UiRect {
-                bottom: Val::Px(10.0),
-                left: Val::Px(10.0),
-                ..default()
-            },
-            ..default()
-        }),
-    );
+/// Takes back the last move with U or Ctrl+Z, and against the computer its reply as well.
+/// Online it asks the opponent instead, and only for one's own last move while they are to
+/// play.
+fn undo_system(
+    keys: keys::Keys,
+    ai: Res<AiOpponent>,
+    replay: Res<ReplayState>,
+    role: Res<net::Role>,
+    mut online: ResMut<Online>,
+    mut takeback: ResMut<Takeback>,
+    drill: Res<drill::DrillState>,
+    mut notice: ResMut<Notice>,
+    mut clicks: EventReader<ButtonAction>,
+    mut placer: StonePlacer,
+) {
+    let clicked = clicks.iter().any(|&button| button == ButtonAction::Undo);
+    let ctrl_z = keys.ctrl() && keys.input.just_pressed(KeyCode::Z);
+    if !(keys.just_pressed(Action::Undo) || ctrl_z || clicked) || replay.reviewing() {
+        return;
+    }
+
+    if let Some(connection) = &mut online.0 {
+        let net::Role::Player(color) = *role else {
+            return;
+        };
+        let own_last = placer
+            .history
+            .0
+            .last()
+            .and_then(HistoryEntry::stone)
+            .is_some_and(|record| record.color == color);
+        if *takeback == Takeback::Asked {
+            notice.show("Waiting for the opponent to answer");
+        } else if !own_last || *placer.status != GameStatus::InProgress {
+            notice.show("Only your last move can be taken back, before the opponent replies");
+        } else {
+            match connection.request_takeback() {
+                Ok(()) => {
+                    *takeback = Takeback::Asked;
+                    notice.show("Takeback requested");
+                }
+                Err(err) => notice.show(format!("Could not ask for a takeback: {err}")),
+            }
+        }
+        return;
+    }
+
+    if drill.enabled && placer.history.0.len() <= drill.setup {
+        notice.show("The drill's stones stay put; press R for the next drill");
+        return;
+    }
+    for _ in 0..placer.history.undo_plies(ai.color) {
+        if let Some(entry) = placer.take_back() {
+            placer.redo.0.push(entry);
+        }
+    }
 }
 
-// ------------------------------------------------------------------------------------------------
-#[derive(Default, Clone, Debug)]
-struct MouseState {
-    cursor_pos: Option<Vec2>,
+/// Plays the moves taken back with undo again, latest first, with Ctrl+Y. The computer's
+/// reply comes back with the move it answered, as undo took them back together.
+fn redo_system(
+    keys: keys::Keys,
+    replay: Res<ReplayState>,
+    online: Res<Online>,
+    ai: Res<AiOpponent>,
+    mut placer: StonePlacer,
+) {
+    if !keys.just_pressed(Action::Redo) || replay.reviewing() || online.0.is_some() {
+        return;
+    }
+
+    if placer.redo() && ai.color == Some(placer.current_player.0) {
+        let reply = placer.redo.0.last().and_then(HistoryEntry::stone);
+        if reply.is_some_and(|record| Some(record.color) == ai.color) {
+            placer.redo();
+        }
+    }
 }
 
-impl Resource for MouseState {}
+/// Resigns for the player to move with Esc pressed twice in a row; any other key in between
+/// calls it off.
+fn resign_system(
+    keys: keys::Keys,
+    replay: Res<ReplayState>,
+    role: Res<net::Role>,
+    mut armed: Local<bool>,
+    mut notice: ResMut<Notice>,
+    mut placer: StonePlacer,
+) {
+    if !keys.just_pressed(Action::Resign) {
+        if keys.input.get_just_pressed().next().is_some() {
+            *armed = false;
+        }
+        return;
+    }
+    if replay.reviewing()
+        || *placer.status != GameStatus::InProgress
+        || !role.may_play(placer.current_player.0)
+    {
+        return;
+    }
 
-// 추가: 마우스 이동 시스템
-fn mouse_movement_system(
-    mut cursor_moved_events: EventReader<CursorMoved>,
-    mut mouse_state: ResMut<MouseState>,
+    if *armed {
+        *armed = false;
+        let _ = placer.resign();
+    } else {
+        *armed = true;
+        notice.show(format!(
+            "Press Esc again to resign for {}",
+            placer.current_player.0.name()
+        ));
+    }
+}
+
+/// Press, drag and release placement: the ghost stone follows the cursor while the button is
+/// held and the stone goes down where it is let go. Set with `--drag`.
+#[derive(Resource)]
+struct DragPlacement(bool);
+
+/// Two-tap placement for touchscreens: the first tap puts a ghost stone down, a second tap on
+/// the same spot plays it and a tap elsewhere moves the ghost. Toggled with C.
+#[derive(Resource)]
+struct ConfirmPlacement {
+    enabled: bool,
+    pending: Option<(usize, usize)>,
+}
+
+fn confirm_placement_system(
+    keys: keys::Keys,
+    mut confirm: ResMut<ConfirmPlacement>,
+    mut notice: ResMut<Notice>,
 ) {
-    for event in cursor_moved_events.iter() {
-        mouse_state.cursor_pos = Some(event.position);
+    if keys.just_pressed(Action::ConfirmPlacement) {
+        confirm.enabled = !confirm.enabled;
+        confirm.pending = None;
+        notice.show(if confirm.enabled {
+            "Tap twice to place a stone"
+        } else {
+            "Click to place a stone"
+        });
     }
 }
 
-fn mouse_click_system(
-    windows: Query<&Window>,
+/// Brings a freshly placed stone in the way `AnimStyle` says. Only the stone's transform
+/// changes, so the board and hit-testing never see it, and it ends exactly on its point. A
+/// stone taken back before it settles is despawned along with its animation.
+#[derive(Component)]
+struct PlaceAnim {
+    style: config::AnimStyle,
+    /// Where the stone comes to rest.
+    target: Vec3,
+    /// How far above `target` a dropped stone starts.
+    height: f32,
+    timer: Timer,
+}
+
+impl PlaceAnim {
+    const START_SCALE: f32 = 0.1;
+    const POP_DURATION: f32 = 0.15;
+    const DROP_DURATION: f32 = 0.22;
+    /// How high a dropped stone starts, in cells.
+    const DROP_HEIGHT: f32 = 1.5;
+    /// The share of a drop spent falling, the rest being the bounce.
+    const LANDING: f32 = 0.7;
+    /// How high the bounce goes, as a share of the drop.
+    const BOUNCE: f32 = 0.12;
+
+    fn new(style: config::AnimStyle, target: Vec3, cell_size: f32) -> Self {
+        let duration = match style {
+            config::AnimStyle::Instant => 0.0,
+            config::AnimStyle::Pop => Self::POP_DURATION,
+            config::AnimStyle::DropIn => Self::DROP_DURATION,
+        };
+        PlaceAnim {
+            style,
+            target,
+            height: cell_size * Self::DROP_HEIGHT,
+            timer: Timer::from_seconds(duration, TimerMode::Once),
+        }
+    }
+
+    fn start_transform(&self) -> Transform {
+        self.transform_at(0.0)
+    }
+
+    /// The stone's transform `progress` of the way through the animation, from 0 to 1.
+    fn transform_at(&self, progress: f32) -> Transform {
+        let resting = Transform::from_translation(self.target);
+        match self.style {
+            config::AnimStyle::Instant => resting,
+            config::AnimStyle::Pop => {
+                // Ease out: quick to appear, settling gently at full size
+                let t = 1.0 - (1.0 - progress).powi(3);
+                let scale = Self::START_SCALE + (1.0 - Self::START_SCALE) * t;
+                resting.with_scale(Vec3::splat(scale))
+            }
+            config::AnimStyle::DropIn => {
+                // Falls faster and faster, then hops once before settling
+                let rise = if progress < Self::LANDING {
+                    let fall = progress / Self::LANDING;
+                    1.0 - fall * fall
+                } else {
+                    let hop = (progress - Self::LANDING) / (1.0 - Self::LANDING);
+                    Self::BOUNCE * (std::f32::consts::PI * hop).sin()
+                };
+                resting.with_translation(self.target + Vec3::Y * self.height * rise)
+            }
+        }
+    }
+}
+
+fn stone_anim_system(
     mut commands: Commands,
-    mouse_button_inputs: Res<Input<MouseButton>>,
-    mouse_state: Res<MouseState>,
+    time: Res<Time>,
+    mut stones: Query<(Entity, &mut Transform, &mut PlaceAnim)>,
+) {
+    for (entity, mut transform, mut anim) in &mut stones {
+        anim.timer.tick(time.delta());
+        if anim.timer.finished() {
+            *transform = Transform::from_translation(anim.target);
+            commands.entity(entity).remove::<PlaceAnim>();
+        } else {
+            *transform = anim.transform_at(anim.timer.percent());
+        }
+    }
+}
+
+/// Cycles the way new stones appear with K; stones already down are left alone.
+fn stone_animation_system(
+    keys: keys::Keys,
+    mut style: ResMut<config::AnimStyle>,
+    mut notice: ResMut<Notice>,
+) {
+    if keys.just_pressed(Action::StoneAnimation) {
+        *style = style.next();
+        notice.show(format!("Stones appear: {}", style.name()));
+    }
+}
+
+/// Whether the latest stones are ringed, fading with age; toggled with L.
+#[derive(Resource)]
+struct RecencyTrail(bool);
+
+/// A ring drawn behind the stone placed `age` moves ago, the newest being 0.
+#[derive(Component)]
+struct RecencyMarker {
+    age: usize,
+}
+
+impl RecencyMarker {
+    /// Ring opacity by age; its length is how many stones the trail covers.
+    const ALPHAS: [f32; 5] = [0.9, 0.65, 0.45, 0.3, 0.15];
+}
+
+/// Moves the rings onto the most recent stones after every move, undo or restart.
+fn recency_system(
+    keys: keys::Keys,
+    config: Res<BoardConfig>,
+    board: Res<BoardState>,
+    history: Res<MoveHistory>,
+    mut trail: ResMut<RecencyTrail>,
+    mut markers: Query<(&RecencyMarker, &mut Transform, &mut Visibility)>,
+) {
+    if keys.just_pressed(Action::Recency) {
+        trail.0 = !trail.0;
+    }
+    if !trail.is_changed() && !history.is_changed() {
+        return;
+    }
+
+    for (marker, mut transform, mut visibility) in &mut markers {
+        // Stones captured since are off the board and get no ring
+        let record =
+            history.stones().rev().nth(marker.age).filter(|record| {
+                trail.0 && board.get(record.col, record.row) == Some(record.color)
+            });
+        let Some(record) = record else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let world_pos = grid_to_world(record.col, record.row, &config);
+        // Just under the stones, so only the rim shows
+        transform.translation = world_pos.extend(1.9);
+        *visibility = Visibility::Visible;
+    }
+}
+
+/// Cycles through the color themes with P, recoloring the window, board, grid and every stone
+/// on it along with its move number.
+fn theme_system(
+    keys: keys::Keys,
+    history: Res<MoveHistory>,
+    mut theme: ResMut<theme::Theme>,
+    mut clear_color: ResMut<ClearColor>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
+    mut notice: ResMut<Notice>,
+    surfaces: Query<&Handle<ColorMaterial>, With<BoardSurface>>,
+    lines: Query<&Handle<ColorMaterial>, With<GridLine>>,
+    stones: Query<&Handle<ColorMaterial>, With<Stone>>,
+    faces: Query<(&Parent, &Handle<ColorMaterial>), With<StoneFace>>,
+    mut labels: Query<(&Parent, &mut Text), With<MoveNumberLabel>>,
+    symbols: Query<(&Parent, &Handle<ColorMaterial>), With<StoneSymbol>>,
 ) {
-    if mouse_button_inputs.just_pressed(MouseButton::Left) {
-        if let Some(pos) = mouse_state.cursor_pos {
-            let window = windows.single();
-            let size = Vec2::new(window.width() as f32, window.height() as f32);
+    if !keys.just_pressed(Action::Theme) {
+        return;
+    }
+    *theme = theme.next();
+    notice.show(format!("Theme: {}", theme.name));
+
+    clear_color.0 = theme.background;
+    for handle in &surfaces {
+        // A board texture is drawn as it is, not tinted
+        if let Some(material) = materials
+            .get_mut(handle)
+            .filter(|material| material.texture.is_none())
+        {
+            material.color = theme.board;
+        }
+    }
+    let mut recolor = |handle: &Handle<ColorMaterial>, color| {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = color;
+        }
+    };
+    for handle in &lines {
+        recolor(handle, theme.lines);
+    }
+    for record in history.stones() {
+        if let Ok(handle) = stones.get(record.entity) {
+            recolor(handle, theme.stone_edge(record.color));
+        }
+    }
+    for (parent, handle) in &faces {
+        if let Some(record) = history
+            .stones()
+            .find(|record| record.entity == parent.get())
+        {
+            recolor(handle, theme.stone(record.color));
+        }
+    }
+    for (parent, mut text) in &mut labels {
+        if let Some(record) = history
+            .stones()
+            .find(|record| record.entity == parent.get())
+        {
+            text.sections[0].style.color = theme.label(record.color);
+        }
+    }
+    for (parent, handle) in &symbols {
+        if let Some(record) = history
+            .stones()
+            .find(|record| record.entity == parent.get())
+        {
+            recolor(handle, theme.label(record.color));
+        }
+    }
+}
+
+/// Sound effects, loaded at startup from `assets/sounds`.
+#[derive(Resource)]
+struct Sounds {
+    place: Handle<AudioSource>,
+    victory: Handle<AudioSource>,
+}
+
+/// Whether sound effects play; toggled with M.
+#[derive(Resource)]
+struct SoundEnabled(bool);
+
+fn mute_system(keys: keys::Keys, mut enabled: ResMut<SoundEnabled>, mut notice: ResMut<Notice>) {
+    if keys.just_pressed(Action::Mute) {
+        enabled.0 = !enabled.0;
+        notice.show(if enabled.0 { "Sound on" } else { "Sound off" });
+    }
+}
+
+/// Clicks when a stone lands and plays a fanfare when the game ends. A new sound cuts off the
+/// previous one, so rapid moves don't pile up.
+fn sound_system(
+    audio: Res<Audio>,
+    sinks: Res<Assets<AudioSink>>,
+    sounds: Res<Sounds>,
+    enabled: Res<SoundEnabled>,
+    history: Res<MoveHistory>,
+    mut game_over_events: EventReader<GameOver>,
+    mut shown_moves: Local<usize>,
+    mut playing: Local<Option<Handle<AudioSink>>>,
+) {
+    let placed = history.0.len() > *shown_moves;
+    *shown_moves = history.0.len();
+    let game_over = game_over_events.iter().count() > 0;
+
+    let sound = match (game_over, placed) {
+        (true, _) => &sounds.victory,
+        (false, true) => &sounds.place,
+        (false, false) => return,
+    };
+    if !enabled.0 {
+        return;
+    }
+
+    if let Some(sink) = playing.as_ref().and_then(|handle| sinks.get(handle)) {
+        sink.stop();
+    }
+    *playing = Some(sinks.get_handle(audio.play(sound.clone())));
+}
 
-            let mut world_pos =
-                ((pos / size) - Vec2::new(0.5, 0.5)) * 2.0 * Vec2::new(300.0, 300.0);
-            world_pos.y *= 1.0;
+/// Shows or hides the symbols on the stones with O.
+fn symbol_stones_system(
+    keys: keys::Keys,
+    mut accessibility: ResMut<Accessibility>,
+    mut notice: ResMut<Notice>,
+    mut symbols: Query<&mut Visibility, With<StoneSymbol>>,
+) {
+    if !keys.just_pressed(Action::SymbolStones) {
+        return;
+    }
+
+    accessibility.symbol_stones = !accessibility.symbol_stones;
+    notice.show(if accessibility.symbol_stones {
+        "Stone symbols on"
+    } else {
+        "Stone symbols off"
+    });
+    for mut visibility in &mut symbols {
+        *visibility = if accessibility.symbol_stones {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
 
-            // Round the world_pos to the nearest grid intersection
-            let grid_size = 600.0 / 19.0; // Grid size of the Gomoku board
-            world_pos /= grid_size;
-            world_pos = (world_pos + Vec2::splat(0.5)).floor() * grid_size;
+fn move_numbers_system(
+    keys: keys::Keys,
+    opening: Res<opening::Opening>,
+    mut move_numbers: ResMut<MoveNumbers>,
+    mut labels: Query<&mut Visibility, With<MoveNumberLabel>>,
+) {
+    // N answers the pie rule's question instead
+    if !keys.just_pressed(Action::MoveNumbers) || *opening == opening::Opening::PieChoice {
+        return;
+    }
 
+    move_numbers.visible = !move_numbers.visible;
+    for mut visibility in &mut labels {
+        *visibility = if move_numbers.visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
 
-            println!("Mouse pos: {:?}", pos);
-            println!("World pos: {:?}", world_pos);
+/// A bar through the winning line, drawn over the stones.
+#[derive(Component)]
+struct WinHighlight;
 
-            let stone_color = Color::rgb(0.0, 0.0, 0.0);
+/// Draws a glowing bar through the winning five when the game ends, and removes it once the
+/// game is back in progress after a restart, undo or replay step.
+fn highlight_win_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<BoardConfig>,
+    status: Res<GameStatus>,
+    mut game_over_events: EventReader<GameOver>,
+    highlights: Query<Entity, With<WinHighlight>>,
+) {
+    if status.is_changed() && *status == GameStatus::InProgress {
+        for entity in &highlights {
+            commands.entity(entity).despawn();
+        }
+    }
 
-            commands.spawn(MaterialMesh2dBundle {
+    for event in game_over_events.iter() {
+        let (Some(&(first_col, first_row)), Some(&(last_col, last_row))) =
+            (event.line.first(), event.line.last())
+        else {
+            continue;
+        };
+        let start = grid_to_world(first_col, first_row, &config);
+        let end = grid_to_world(last_col, last_row, &config);
+        let span = end - start;
+        let thickness = config.cell_size() * 0.15;
+        commands.spawn((
+            MaterialMesh2dBundle {
                 mesh: meshes
-                    .add(Mesh::from(shape::Circle {
-                        radius: grid_size * 0.4,
+                    .add(Mesh::from(shape::Quad {
+                        size: Vec2::new(span.length() + thickness, thickness),
                         ..Default::default()
                     }))
                     .into(),
-                transform: Transform::from_xyz(world_pos.x, world_pos.y, 2.0),
-                material: materials.add(ColorMaterial::from(stone_color)),
+                transform: Transform::from_translation(((start + end) / 2.0).extend(3.0))
+                    .with_rotation(Quat::from_rotation_z(span.y.atan2(span.x))),
+                // Brighter than white so the bloom makes it glow
+                material: materials.add(ColorMaterial::from(Color::rgb(2.5, 1.8, 0.3))),
                 ..Default::default()
-            });
-        }
+            },
+            WinHighlight,
+        ));
     }
 }
 
+/// The letter keys the bloom and board controls use while the visual settings are open. The
+/// game doesn't see them being pressed for as long as that lasts.
+const VISUAL_KEYS: [KeyCode; 18] = [
+    KeyCode::Q,
+    KeyCode::A,
+    KeyCode::W,
+    KeyCode::S,
+    KeyCode::E,
+    KeyCode::D,
+    KeyCode::R,
+    KeyCode::F,
+    KeyCode::T,
+    KeyCode::G,
+    KeyCode::Y,
+    KeyCode::H,
+    KeyCode::U,
+    KeyCode::J,
+    KeyCode::I,
+    KeyCode::K,
+    KeyCode::O,
+    KeyCode::L,
+];
+
+/// Whether the bloom and board controls and their readout are open; toggled with F1.
+#[derive(Resource, Default)]
+struct VisualSettings(bool);
+
+/// Toggles bloom with the toolbar button. While the F1 visual settings are open, Space
+/// (outside a review) toggles it too, and the letter keys listed in the readout tune the
+/// bloom, the line width and the stone size. Runs before the game's systems so it can keep
+/// those keys from them.
 fn update_bloom_settings(
     mut camera: Query<(Entity, Option<&mut BloomSettings>), With<Camera>>,
-    mut text: Query<&mut Text>,
+    mut text: Query<&mut Text, With<hud::BloomText>>,
     mut commands: Commands,
-    keycode: Res<Input<KeyCode>>,
+    mut keycode: ResMut<Input<KeyCode>>,
     time: Res<Time>,
+    mut clicks: EventReader<ButtonAction>,
+    mut visual: ResMut<VisualSettings>,
+    replay: Res<ReplayState>,
+    bindings: Res<keys::KeyBindings>,
+    mut appearance: ResMut<config::Appearance>,
 ) {
+    if keycode.just_pressed(bindings.key(Action::VisualSettings)) {
+        visual.0 = !visual.0;
+    }
+    // Sharing a key, the autoplay has it while reviewing, and with Ctrl starts it; outside the
+    // visual settings it places stones
+    let bloom_key = bindings.key(Action::Bloom);
+    let ctrl = keycode.any_pressed([KeyCode::LControl, KeyCode::RControl]);
+    let autoplay_has_key =
+        bloom_key == bindings.key(Action::Autoplay) && (ctrl || replay.reviewing());
+    let toggle = keycode.just_pressed(bloom_key) && visual.0 && !autoplay_has_key
+        || clicks.iter().any(|&button| button == ButtonAction::Bloom);
     let bloom_settings = camera.single_mut();
     let mut text = text.single_mut();
     let text = &mut text.sections[0].value;
+    if visual.0 {
+        for key in VISUAL_KEYS {
+            keycode.clear_just_pressed(key);
+        }
+    } else {
+        text.clear();
+    }
 
     match bloom_settings {
+        (entity, Some(_)) if !visual.0 => {
+            if toggle {
+                commands.entity(entity).remove::<BloomSettings>();
+            }
+        }
         (entity, Some(mut bloom_settings)) => {
-            *text = "BloomSettings (Toggle: Space)\n".to_string();
+            *text = "BloomSettings (Toggle: Space, close: F1)\n".to_string();
             text.push_str(&format!("(Q/A) Intensity: {}\n", bloom_settings.intensity));
             text.push_str(&format!(
                 "(W/S) Low-frequency boost: {}\n",
@@ -205,7 +3148,7 @@ fn update_bloom_settings(
                 bloom_settings.prefilter_settings.threshold_softness
             ));
 
-            if keycode.just_pressed(KeyCode::Space) {
+            if toggle {
                 commands.entity(entity).remove::<BloomSettings>();
             }
 
@@ -273,11 +3216,227 @@ fn update_bloom_settings(
         }
 
         (entity, None) => {
-            *text = "Bloom: Off (Toggle: Space)".to_string();
+            if visual.0 {
+                *text = "Bloom: Off (Toggle: Space, close: F1)".to_string();
+            }
 
-            if keycode.just_pressed(KeyCode::Space) {
+            if toggle {
                 commands.entity(entity).insert(BloomSettings::default());
             }
         }
     }
+
+    if visual.0 {
+        let dt = time.delta_seconds();
+        let mut tuned = *appearance;
+        if keycode.pressed(KeyCode::K) {
+            tuned.line_width -= dt * 2.0;
+        }
+        if keycode.pressed(KeyCode::I) {
+            tuned.line_width += dt * 2.0;
+        }
+        if keycode.pressed(KeyCode::L) {
+            tuned.stone_radius_ratio -= dt / 10.0;
+        }
+        if keycode.pressed(KeyCode::O) {
+            tuned.stone_radius_ratio += dt / 10.0;
+        }
+        // Only a real change redraws the board
+        let tuned = tuned.clamped();
+        if tuned != *appearance {
+            *appearance = tuned;
+        }
+
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&format!("(I/K) Line width: {:.1}\n", appearance.line_width));
+        text.push_str(&format!(
+            "(O/L) Stone size: {:.2} of the line spacing, for new stones\n",
+            appearance.stone_radius_ratio
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The layout of a 15x15 board in the default window, unzoomed.
+    fn layout() -> BoardLayout {
+        let config = BoardConfig { size: 15 };
+        BoardLayout::new(Vec2::new(1280.0, 720.0), 1.0, Vec2::ZERO, &config)
+    }
+
+    /// The window position of the point `(col, row)` cells from column 0, row 0.
+    fn at(layout: &BoardLayout, col: f32, row: f32) -> Vec2 {
+        layout.origin + Vec2::new(col, row) * layout.cell_size
+    }
+
+    #[test]
+    fn cursor_snaps_to_the_nearest_intersection() {
+        let layout = layout();
+        assert_eq!(
+            layout.cursor_to_grid(at(&layout, 7.0, 7.0), 15),
+            Some((7, 7))
+        );
+        assert_eq!(
+            layout.cursor_to_grid(Vec2::new(640.0, 360.0), 15),
+            Some((7, 7))
+        );
+        assert_eq!(
+            layout.cursor_to_grid(at(&layout, 3.4, 9.6), 15),
+            Some((3, 10))
+        );
+        // Each corner, and points just inside the board from them
+        assert_eq!(
+            layout.cursor_to_grid(at(&layout, 0.0, 0.0), 15),
+            Some((0, 0))
+        );
+        assert_eq!(
+            layout.cursor_to_grid(at(&layout, -0.4, 14.4), 15),
+            Some((0, 14))
+        );
+        assert_eq!(
+            layout.cursor_to_grid(at(&layout, 14.4, -0.4), 15),
+            Some((14, 0))
+        );
+        assert_eq!(
+            layout.cursor_to_grid(at(&layout, 14.0, 14.0), 15),
+            Some((14, 14))
+        );
+    }
+
+    #[test]
+    fn cursor_past_the_edge_misses() {
+        let layout = layout();
+        assert_eq!(layout.cursor_to_grid(at(&layout, -0.6, 7.0), 15), None);
+        assert_eq!(layout.cursor_to_grid(at(&layout, 7.0, 14.6), 15), None);
+        // Exactly half a cell out rounds away from the board
+        assert_eq!(layout.cursor_to_grid(at(&layout, -0.5, 0.0), 15), None);
+        assert_eq!(layout.cursor_to_grid(at(&layout, 14.5, 14.0), 15), None);
+        assert_eq!(layout.cursor_to_grid(Vec2::ZERO, 15), None);
+        // Before the first layout there's no board to hit
+        assert_eq!(
+            BoardLayout::default().cursor_to_grid(Vec2::new(640.0, 360.0), 15),
+            None
+        );
+    }
+
+    #[test]
+    fn zoom_and_pan_move_the_board() {
+        let config = BoardConfig { size: 15 };
+        // Zoomed in twice over, around the intersection at column 11, row 3
+        let pan = grid_to_world(11, 3, &config);
+        let layout = BoardLayout::new(Vec2::new(1280.0, 720.0), 0.5, pan, &config);
+        assert_eq!(
+            layout.cursor_to_grid(Vec2::new(640.0, 360.0), 15),
+            Some((11, 3))
+        );
+        assert_eq!(layout.cell_size, config.cell_size() * 2.0);
+    }
+
+    #[test]
+    fn cursor_near_the_board_finds_the_edge() {
+        let layout = layout();
+        assert_eq!(
+            layout.nearest_edge_point(at(&layout, -1.0, 6.8), 15),
+            Some((0, 7))
+        );
+        assert_eq!(
+            layout.nearest_edge_point(at(&layout, 15.0, 15.0), 15),
+            Some((14, 14))
+        );
+        assert_eq!(layout.nearest_edge_point(at(&layout, -2.0, 7.0), 15), None);
+    }
+
+    #[test]
+    fn cooldown_blocks_clicks_for_its_duration() {
+        let mut cooldown = PlacementCooldown::new(Duration::from_millis(100));
+        assert!(cooldown.ready());
+        cooldown.start();
+        assert!(!cooldown.ready());
+        cooldown.tick(Duration::from_millis(60));
+        assert!(!cooldown.ready());
+        cooldown.tick(Duration::from_millis(40));
+        assert!(cooldown.ready());
+        // Time going by while nothing is placed doesn't build up
+        cooldown.tick(Duration::from_secs(5));
+        cooldown.start();
+        cooldown.tick(Duration::from_millis(99));
+        assert!(!cooldown.ready());
+    }
+
+    #[test]
+    fn no_cooldown_waits_for_nothing() {
+        let mut cooldown = PlacementCooldown::new(Duration::ZERO);
+        assert!(cooldown.ready());
+        cooldown.start();
+        cooldown.tick(Duration::ZERO);
+        assert!(cooldown.ready());
+    }
+
+    #[test]
+    fn swapping_a_step_changes_only_its_color() {
+        let mut stone = HistoryEntry::Stone(MoveRecord {
+            col: 3,
+            row: 4,
+            color: StoneColor::Black,
+            entity: Entity::from_raw(1),
+            captures: vec![0, 2],
+        });
+        stone.swap_color();
+        let record = stone.stone().unwrap();
+        assert_eq!((record.col, record.row), (3, 4));
+        assert_eq!(record.color, StoneColor::White);
+        assert_eq!(record.captures, vec![0, 2]);
+        stone.swap_color();
+        assert_eq!(stone.stone().unwrap().color, StoneColor::Black);
+
+        let mut resign = HistoryEntry::Resign(StoneColor::White);
+        resign.swap_color();
+        assert!(matches!(resign, HistoryEntry::Resign(StoneColor::Black)));
+    }
+
+    /// A history of stones at `(col, 0)`, one for each color in `colors`.
+    fn history_of(colors: &[StoneColor]) -> MoveHistory {
+        let entries = colors.iter().enumerate().map(|(col, &color)| {
+            HistoryEntry::Stone(MoveRecord {
+                col,
+                row: 0,
+                color,
+                entity: Entity::from_raw(col as u32),
+                captures: Vec::new(),
+            })
+        });
+        MoveHistory(entries.collect())
+    }
+
+    #[test]
+    fn undo_against_the_computer_hands_the_turn_back_to_the_human() {
+        use StoneColor::{Black, White};
+        let ai = Some(White);
+        let history = history_of(&[Black, White, Black, White]);
+        let plies = history.undo_plies(ai);
+        assert_eq!(plies, 2);
+        // Undo hands the turn to whoever played the earliest entry taken back
+        let first_undone = history.0[history.0.len() - plies].stone().unwrap();
+        assert_eq!(first_undone.color, Black);
+
+        // The human's own move, unanswered or winning, comes back alone
+        assert_eq!(history_of(&[Black, White, Black]).undo_plies(ai), 1);
+        let mut resigned = history_of(&[Black, White]);
+        resigned.0.push(HistoryEntry::Resign(Black));
+        assert_eq!(resigned.undo_plies(ai), 1);
+    }
+
+    #[test]
+    fn undo_takes_back_one_move_without_the_computer() {
+        use StoneColor::{Black, White};
+        let history = history_of(&[Black, White, Black, White]);
+        assert_eq!(history.undo_plies(None), 1);
+        // A computer playing Black that opened the game has no move of the human's to go with
+        assert_eq!(history_of(&[Black]).undo_plies(Some(Black)), 1);
+        assert_eq!(history_of(&[Black, White]).undo_plies(Some(Black)), 1);
+    }
 }
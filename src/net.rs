@@ -0,0 +1,749 @@
+//! Two-player games over TCP, with any number of spectators.
+//!
+//! The host listens on a port; the first client to connect plays, and everyone after that
+//! watches. Both sides exchange one JSON message per line. A client opens with `join`, and the
+//! host answers with `welcome`, carrying the settings the client adopts and the seat it gets;
+//! after that each player sends their own moves as they are played. The host passes every
+//! move on to the spectators, and catches up late joiners with the moves so far.
+//!
+//! Every move and resignation carries its sequence number, its place in the game. A player
+//! whose connection drops keeps dialling the host for a while and opens with `rejoin`
+//! instead, giving the token from its welcome and how many moves it already has; the host
+//! gives the seat back and sends only the moves it missed, and the player sends any of its
+//! own the host never got.
+//!
+//! A player may ask for its last move back with `takeback_request`, giving how many entries
+//! its record has so a request that crossed the other player's move is recognised as stale.
+//! Accepting sends `takeback`, which takes its own place in the record like a move, so
+//! spectators and rejoining players take the move back too; declining sends
+//! `takeback_declined`. Neither the request nor the refusal is recorded.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::{info, warn, Resource};
+use serde::{Deserialize, Serialize};
+
+use crate::StoneColor;
+
+/// How long a newcomer has to say who it is, and the host to answer.
+const GREETING_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a peer may go without reading before the connection to it is given up.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a player that lost the host keeps trying to get back in, and how often.
+const RECONNECT_WINDOW: Duration = Duration::from_secs(60);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+/// The longest line either side accepts; every message fits in a fraction of it, so anything
+/// longer means the peer isn't speaking the protocol.
+const MAX_LINE_LENGTH: u64 = 4096;
+
+/// What this side may do in the game.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Both colors are played at this screen.
+    #[default]
+    Local,
+    /// Only this color is played here; the other comes over the network.
+    Player(StoneColor),
+    /// Moves only arrive over the network.
+    Spectator,
+}
+
+impl Role {
+    /// Whether `color`'s stones may be placed from this side.
+    pub fn may_play(self, color: StoneColor) -> bool {
+        match self {
+            Role::Local => true,
+            Role::Player(local) => local == color,
+            Role::Spectator => false,
+        }
+    }
+}
+
+/// The match settings both sides must agree on; the host's win.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Settings {
+    pub size: usize,
+    pub win_length: usize,
+    /// Who moves first; the host plays this color.
+    pub first_player: StoneColor,
+    pub overline_forbidden: [bool; 2],
+    pub renju: bool,
+    pub first_move_center: bool,
+    pub early_draw: bool,
+    pub repetition_draw: bool,
+    pub pente: bool,
+    pub toroidal: bool,
+}
+
+/// A line of the protocol.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    /// A newcomer's first line.
+    Join,
+    /// A player's first line when coming back after its connection dropped: the token from
+    /// its welcome, and how many moves it has, so only the rest is sent.
+    Rejoin {
+        token: u64,
+        have: usize,
+    },
+    Welcome {
+        settings: Settings,
+        role: Role,
+        /// Only given to the player, to claim the seat back with.
+        token: Option<u64>,
+        /// How many moves the host has; a rejoining player sends what it has beyond that.
+        moves: usize,
+    },
+    Move {
+        seq: usize,
+        col: usize,
+        row: usize,
+        color: StoneColor,
+    },
+    Resign {
+        seq: usize,
+        color: StoneColor,
+    },
+    /// Asks the other player to let the sender's last move be taken back; `seq` is the
+    /// sequence number the next entry would have.
+    TakebackRequest {
+        seq: usize,
+    },
+    TakebackDeclined,
+    /// The last move comes off the board.
+    Takeback {
+        seq: usize,
+    },
+}
+
+/// Something that happened on the network, as seen by the game.
+#[derive(Debug)]
+pub enum PeerEvent {
+    Move {
+        col: usize,
+        row: usize,
+        color: StoneColor,
+    },
+    Resign(StoneColor),
+    /// The other player asks for their last move back.
+    TakebackRequested,
+    /// The other player turned down our request.
+    TakebackDeclined,
+    /// The last move was taken back, by agreement of both players.
+    Takeback,
+    /// Someone started watching this host's game.
+    SpectatorJoined,
+    /// The connection to the other player dropped. The host waits for them to come back; the
+    /// client keeps trying to reach the host.
+    Reconnecting,
+    /// The other player is back and caught up.
+    Reconnected,
+    /// The connection to the host closed or broke and couldn't be made again; nothing more
+    /// will arrive.
+    Disconnected,
+}
+
+/// A newly connected socket and the first line it sent.
+struct Greeted {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+    message: Message,
+}
+
+/// The sending end of a connection. Lines are written on a background thread, so a peer
+/// that stops reading can't hold up a frame; after `WRITE_TIMEOUT` the thread shuts the
+/// connection, which the reading end then reports as closed.
+struct Outgoing {
+    stream: TcpStream,
+    queue: Sender<Message>,
+}
+
+impl Outgoing {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        let mut writer = stream.try_clone()?;
+        writer.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let (queue, queued) = mpsc::channel::<Message>();
+        thread::spawn(move || {
+            for message in queued {
+                if send(&mut writer, &message).is_err() {
+                    let _ = writer.shutdown(Shutdown::Both);
+                    return;
+                }
+            }
+        });
+        Ok(Outgoing { stream, queue })
+    }
+
+    /// Queues `message`, failing only once an earlier write has failed.
+    fn send(&self, message: &Message) -> io::Result<()> {
+        self.queue
+            .send(message.clone())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the connection is closed"))
+    }
+
+    fn shutdown(&self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// Where a reconnection thread hands over the new connection to the host and how many moves
+/// the host has, or why it gave up.
+type Reconnection = Mutex<Receiver<io::Result<(Greeted, usize)>>>;
+
+/// The parts of a connection only one end has.
+enum Side {
+    Host {
+        settings: Settings,
+        /// Connections that have introduced themselves, to be seated or turned away.
+        joining: Mutex<Receiver<Greeted>>,
+        watching: Vec<Outgoing>,
+    },
+    Client {
+        addr: String,
+        /// The way back in after a dropped connection, while it's being tried.
+        reconnecting: Option<Reconnection>,
+    },
+}
+
+/// An open connection to the other player, or to the host when watching. Incoming lines are
+/// read on a background thread and queued, and outgoing ones written on another, so neither
+/// polling nor sending blocks a frame.
+pub struct Connection {
+    outgoing: Outgoing,
+    /// Lines from `stream`; `None` once it closes.
+    incoming: Mutex<Receiver<Option<Message>>>,
+    /// Every move, resignation and takeback so far, from both sides, in order.
+    log: Vec<Message>,
+    /// The sequence number of the next move; anything from the other side numbered lower
+    /// has been seen already.
+    next_seq: usize,
+    /// The player's claim on its seat, shared by the host and the player it welcomed.
+    token: Option<u64>,
+    /// Set while the other player's connection is down; their moves wait in `log` until
+    /// they're back.
+    away: bool,
+    side: Side,
+}
+
+impl Connection {
+    /// Waits for a player to connect on `port` and sends them `settings`, then keeps taking
+    /// spectators, and the player again if they drop, in the background. The host plays
+    /// `settings.first_player`.
+    pub fn host(port: u16, settings: Settings) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        info!("Waiting for a player on port {port}...");
+        let (
+            Greeted {
+                mut stream, reader, ..
+            },
+            peer,
+        ) = loop {
+            let (stream, peer) = listener.accept()?;
+            match greeting(stream) {
+                Ok(greeted) if matches!(greeted.message, Message::Join) => break (greeted, peer),
+                // Most likely a player still trying to get back into an earlier game
+                _ => info!("Turned {peer} away"),
+            }
+        };
+        info!("{peer} joined");
+        let token = rand::random();
+        let welcome = Message::Welcome {
+            settings,
+            role: Role::Player(settings.first_player.opponent()),
+            token: Some(token),
+            moves: 0,
+        };
+        send(&mut stream, &welcome)?;
+
+        // Each newcomer is greeted on its own thread, so a slow one holds up nobody else
+        let (sender, joining) = mpsc::channel();
+        thread::spawn(move || {
+            for newcomer in listener.incoming().flatten() {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    if let Ok(greeted) = greeting(newcomer) {
+                        let _ = sender.send(greeted);
+                    }
+                });
+            }
+        });
+        let mut connection = Connection::start(
+            stream,
+            reader,
+            Side::Host {
+                settings,
+                joining: Mutex::new(joining),
+                watching: Vec::new(),
+            },
+        )?;
+        connection.token = Some(token);
+        Ok(connection)
+    }
+
+    /// Connects to a host at `addr`, returning the connection, the host's settings and the
+    /// seat it gave us.
+    pub fn join(addr: &str) -> io::Result<(Self, Settings, Role)> {
+        let greeted = handshake(addr, &Message::Join)?;
+        let Message::Welcome {
+            settings,
+            role,
+            token,
+            ..
+        } = greeted.message
+        else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the host didn't send its settings",
+            ));
+        };
+        let side = Side::Client {
+            addr: addr.to_string(),
+            reconnecting: None,
+        };
+        let mut connection = Connection::start(greeted.stream, greeted.reader, side)?;
+        connection.token = token;
+        Ok((connection, settings, role))
+    }
+
+    fn start(stream: TcpStream, reader: BufReader<TcpStream>, side: Side) -> io::Result<Self> {
+        Ok(Connection {
+            outgoing: Outgoing::new(stream)?,
+            incoming: read_lines(reader),
+            log: Vec::new(),
+            next_seq: 0,
+            token: None,
+            away: false,
+            side,
+        })
+    }
+
+    pub fn is_host(&self) -> bool {
+        matches!(self.side, Side::Host { .. })
+    }
+
+    /// Everything that happened since the last call. Moves seen before, which a catch-up
+    /// after a reconnection can repeat, are left out. New spectators are caught up with the
+    /// game so far before they're reported.
+    pub fn poll(&mut self) -> Vec<PeerEvent> {
+        let received: Vec<_> = match self.incoming.lock() {
+            Ok(incoming) => incoming.try_iter().collect(),
+            Err(_) => vec![None],
+        };
+        let mut events = Vec::new();
+        for message in received {
+            match message {
+                Some(Message::Move {
+                    seq,
+                    col,
+                    row,
+                    color,
+                }) if self.is_new(seq) => events.push(PeerEvent::Move { col, row, color }),
+                Some(Message::Resign { seq, color }) if self.is_new(seq) => {
+                    events.push(PeerEvent::Resign(color))
+                }
+                // Anything recorded since the request was made answers it already
+                Some(Message::TakebackRequest { seq }) if seq == self.next_seq => {
+                    events.push(PeerEvent::TakebackRequested)
+                }
+                Some(Message::TakebackDeclined) => events.push(PeerEvent::TakebackDeclined),
+                Some(Message::Takeback { seq }) if self.is_new(seq) => {
+                    events.push(PeerEvent::Takeback)
+                }
+                Some(_) => {}
+                None => events.push(self.lost()),
+            }
+        }
+
+        match self.side {
+            Side::Host { .. } => self.seat_newcomers(&mut events),
+            Side::Client { .. } => self.finish_reconnecting(&mut events),
+        }
+        events
+    }
+
+    fn is_new(&mut self, seq: usize) -> bool {
+        if seq < self.next_seq {
+            return false;
+        }
+        self.next_seq = seq + 1;
+        true
+    }
+
+    /// Reacts to the other end closing: a player's seat is kept open for them, and a
+    /// player that lost the host starts trying to reach it again.
+    fn lost(&mut self) -> PeerEvent {
+        match (&mut self.side, self.token) {
+            (Side::Host { .. }, _) => {
+                info!("The other player dropped, waiting for them to come back");
+                self.away = true;
+                PeerEvent::Reconnecting
+            }
+            (Side::Client { addr, reconnecting }, Some(token)) => {
+                self.away = true;
+                *reconnecting = Some(reconnect(addr.clone(), token, self.next_seq));
+                PeerEvent::Reconnecting
+            }
+            (Side::Client { .. }, None) => PeerEvent::Disconnected,
+        }
+    }
+
+    /// Welcomes the spectators that connected since the last poll, and gives the player
+    /// their seat back if they were among them.
+    fn seat_newcomers(&mut self, events: &mut Vec<PeerEvent>) {
+        let Side::Host {
+            settings, joining, ..
+        } = &self.side
+        else {
+            return;
+        };
+        let settings = *settings;
+        let joined: Vec<_> = match joining.lock() {
+            Ok(joining) => joining.try_iter().collect(),
+            Err(_) => Vec::new(),
+        };
+
+        for Greeted {
+            stream,
+            reader,
+            message,
+        } in joined
+        {
+            let (role, token, have) = match message {
+                Message::Join => (Role::Spectator, None, 0),
+                Message::Rejoin { token, have } if Some(token) == self.token => (
+                    Role::Player(settings.first_player.opponent()),
+                    self.token,
+                    have,
+                ),
+                _ => continue,
+            };
+            let welcome = Message::Welcome {
+                settings,
+                role,
+                token,
+                moves: self.log.len(),
+            };
+            let Ok(outgoing) = Outgoing::new(stream) else {
+                continue;
+            };
+            let caught_up = std::iter::once(&welcome)
+                .chain(self.log.iter().skip(have))
+                .try_for_each(|message| outgoing.send(message));
+            if caught_up.is_err() {
+                continue;
+            }
+
+            if role == Role::Spectator {
+                if let Side::Host { watching, .. } = &mut self.side {
+                    watching.push(outgoing);
+                }
+                events.push(PeerEvent::SpectatorJoined);
+            } else {
+                // The old connection may not have noticed it's dead yet
+                self.outgoing.shutdown();
+                self.outgoing = outgoing;
+                self.incoming = read_lines(reader);
+                self.away = false;
+                events.push(PeerEvent::Reconnected);
+            }
+        }
+    }
+
+    /// Takes over the new connection to the host once the reconnection thread has one, and
+    /// sends the host the moves played here that it never got.
+    fn finish_reconnecting(&mut self, events: &mut Vec<PeerEvent>) {
+        let Side::Client { reconnecting, .. } = &mut self.side else {
+            return;
+        };
+        let Some(pending) = reconnecting else {
+            return;
+        };
+        let result = match pending.get_mut().map(|pending| pending.try_recv()) {
+            Ok(Err(TryRecvError::Empty)) => return,
+            Ok(Ok(result)) => result,
+            _ => Err(io::Error::other("the reconnection was abandoned")),
+        };
+        *reconnecting = None;
+
+        let result = result.and_then(|(greeted, host_moves)| {
+            Ok((Outgoing::new(greeted.stream)?, greeted.reader, host_moves))
+        });
+        match result {
+            Ok((outgoing, reader, host_moves)) => {
+                self.outgoing = outgoing;
+                self.incoming = read_lines(reader);
+                self.away = false;
+                let resent = self
+                    .log
+                    .iter()
+                    .skip(host_moves)
+                    .try_for_each(|message| self.outgoing.send(message));
+                if let Err(err) = resent {
+                    warn!("Could not send the host the moves it missed: {err}");
+                }
+                events.push(PeerEvent::Reconnected);
+            }
+            Err(err) => {
+                warn!("Could not get back to the host: {err}");
+                events.push(PeerEvent::Disconnected);
+            }
+        }
+    }
+
+    /// Adds a move to the game's record, sending it to the other player if it was played on
+    /// this side and passing it on to the spectators if this side is hosting.
+    pub fn record_move(
+        &mut self,
+        col: usize,
+        row: usize,
+        color: StoneColor,
+        played_here: bool,
+    ) -> io::Result<()> {
+        let seq = self.log.len();
+        self.record(
+            Message::Move {
+                seq,
+                col,
+                row,
+                color,
+            },
+            played_here,
+        )
+    }
+
+    /// Adds a resignation to the game's record, the same way as `record_move`.
+    pub fn record_resign(&mut self, color: StoneColor, played_here: bool) -> io::Result<()> {
+        let seq = self.log.len();
+        self.record(Message::Resign { seq, color }, played_here)
+    }
+
+    /// Adds the taking back of the last move to the game's record, the same way as
+    /// `record_move`; `agreed_here` is whether this side accepted it.
+    pub fn record_takeback(&mut self, agreed_here: bool) -> io::Result<()> {
+        let seq = self.log.len();
+        self.record(Message::Takeback { seq }, agreed_here)
+    }
+
+    /// Asks the other player to let our last move be taken back.
+    pub fn request_takeback(&mut self) -> io::Result<()> {
+        if self.away {
+            return Err(io::Error::other("the other player is away"));
+        }
+        let seq = self.log.len();
+        self.outgoing.send(&Message::TakebackRequest { seq })
+    }
+
+    pub fn decline_takeback(&mut self) -> io::Result<()> {
+        self.outgoing.send(&Message::TakebackDeclined)
+    }
+
+    /// How many moves and resignations the record holds after its takebacks, which is how
+    /// far into the move history it has got.
+    pub fn recorded(&self) -> usize {
+        self.log.iter().fold(0, |count, message| match message {
+            Message::Takeback { .. } => count.saturating_sub(1),
+            _ => count + 1,
+        })
+    }
+
+    fn record(&mut self, message: Message, played_here: bool) -> io::Result<()> {
+        if let Side::Host { watching, .. } = &mut self.side {
+            // A spectator with a broken connection has left
+            watching.retain(|spectator| spectator.send(&message).is_ok());
+        }
+        // While the other player is away it waits in the log for their return
+        let sent = if played_here && !self.away {
+            self.outgoing.send(&message)
+        } else {
+            Ok(())
+        };
+        self.log.push(message);
+        self.next_seq = self.next_seq.max(self.log.len());
+        sent
+    }
+}
+
+impl Drop for Connection {
+    /// The reader thread holds its own handle on the socket, so close it for both.
+    fn drop(&mut self) {
+        self.outgoing.shutdown();
+    }
+}
+
+fn send(stream: &mut TcpStream, message: &Message) -> io::Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())
+}
+
+/// Reads the first line from a new connection, giving up after `GREETING_TIMEOUT`.
+fn greeting(stream: TcpStream) -> io::Result<Greeted> {
+    stream.set_read_timeout(Some(GREETING_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let line = read_line(&mut reader)?.unwrap_or_default();
+    stream.set_read_timeout(None)?;
+    Ok(Greeted {
+        stream,
+        reader,
+        message: serde_json::from_str(&line)?,
+    })
+}
+
+/// Connects to `addr`, opens with `hello` and returns the host's answer.
+fn handshake(addr: &str, hello: &Message) -> io::Result<Greeted> {
+    let mut stream = TcpStream::connect(addr)?;
+    send(&mut stream, hello)?;
+    greeting(stream)
+}
+
+/// Reads one line from `reader`, without its newline, or `None` once the connection has
+/// closed. A line longer than `MAX_LINE_LENGTH` is an error, with the rest left unread.
+fn read_line(reader: &mut BufReader<TcpStream>) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if reader.take(MAX_LINE_LENGTH + 1).read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    } else if line.len() as u64 > MAX_LINE_LENGTH {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"));
+    }
+    Ok(Some(line))
+}
+
+/// Queues the lines `reader` receives on a background thread, then `None` when it closes.
+/// A line that's too long closes the connection.
+fn read_lines(mut reader: BufReader<TcpStream>) -> Mutex<Receiver<Option<Message>>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        loop {
+            let line = match read_line(&mut reader) {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(err) => {
+                    if err.kind() == io::ErrorKind::InvalidData {
+                        warn!("Dropping the connection: {err}");
+                        let _ = reader.get_ref().shutdown(Shutdown::Both);
+                    }
+                    break;
+                }
+            };
+            match serde_json::from_str(&line) {
+                Ok(message) => {
+                    if sender.send(Some(message)).is_err() {
+                        return;
+                    }
+                }
+                Err(err) => warn!("Ignoring unreadable message {line:?}: {err}"),
+            }
+        }
+        let _ = sender.send(None);
+    });
+    Mutex::new(receiver)
+}
+
+/// Keeps trying to rejoin the host at `addr` on a background thread, for up to
+/// `RECONNECT_WINDOW`, and hands over the new connection with the number of moves the host
+/// has.
+fn reconnect(addr: String, token: u64, have: usize) -> Reconnection {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let started = Instant::now();
+        let result = loop {
+            let error = match handshake(&addr, &Message::Rejoin { token, have }) {
+                Ok(greeted) => match greeted.message {
+                    Message::Welcome { moves, .. } => break Ok((greeted, moves)),
+                    _ => io::Error::new(io::ErrorKind::InvalidData, "the host didn't welcome us"),
+                },
+                Err(err) => err,
+            };
+            if started.elapsed() >= RECONNECT_WINDOW {
+                break Err(error);
+            }
+            thread::sleep(RECONNECT_INTERVAL);
+        };
+        let _ = sender.send(result);
+    });
+    Mutex::new(receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Both ends of a connection over the loopback interface.
+    fn pair() -> (TcpStream, BufReader<TcpStream>) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let near = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (far, _) = listener.accept().unwrap();
+        (near, BufReader::new(far))
+    }
+
+    #[test]
+    fn reads_lines_up_to_the_limit() {
+        let (mut near, mut far) = pair();
+        let longest = "x".repeat(MAX_LINE_LENGTH as usize);
+        write!(near, "join\r\n{longest}\n").unwrap();
+        drop(near);
+        assert_eq!(read_line(&mut far).unwrap().as_deref(), Some("join"));
+        assert_eq!(read_line(&mut far).unwrap(), Some(longest));
+        assert_eq!(read_line(&mut far).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_a_line_too_long() {
+        let (mut near, mut far) = pair();
+        let writer = thread::spawn(move || {
+            // Never ends the line; the reader must give up without waiting for the rest
+            let chunk = [b'x'; 1024];
+            while near.write_all(&chunk).is_ok() {}
+        });
+        let err = read_line(&mut far).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        far.get_ref().shutdown(Shutdown::Both).unwrap();
+        drop(far);
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn sending_to_a_peer_that_stops_reading_doesnt_block() {
+        let (near, _far) = pair();
+        let outgoing = Outgoing::new(near).unwrap();
+        let started = Instant::now();
+        // Far more than the socket buffers hold
+        for seq in 0..100_000 {
+            let message = Message::Move {
+                seq,
+                col: 7,
+                row: 7,
+                color: StoneColor::Black,
+            };
+            outgoing.send(&message).unwrap();
+        }
+        assert!(started.elapsed() < WRITE_TIMEOUT);
+    }
+
+    #[test]
+    fn a_line_too_long_closes_the_connection() {
+        let (mut near, far) = pair();
+        let incoming = read_lines(far);
+        let too_long = "x".repeat(MAX_LINE_LENGTH as usize + 1);
+        near.write_all(too_long.as_bytes()).unwrap();
+        let received = incoming.lock().unwrap().recv().unwrap();
+        assert!(received.is_none());
+    }
+}
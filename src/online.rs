@@ -0,0 +1,310 @@
+//! The game's side of network play: opening the connection, sending the moves played here
+//! and placing the ones that arrive, and agreeing on takebacks.
+
+use bevy::prelude::*;
+
+use crate::{
+    cli, config, game_core,
+    hud::{NetText, Notice},
+    net,
+    replay::ReplayState,
+    GameStatus, HistoryEntry, MoveError, MoveHistory, StonePlacer,
+};
+
+/// Opens the connection asked for with `--host` or `--connect`, along with the seat this side
+/// takes. A client takes on the host's settings, and neither side plays the computer or the
+/// swap2 opening online. Falls back to a local game if connecting fails.
+pub fn connect(
+    args: &mut cli::Args,
+    game_config: &mut config::GameConfig,
+) -> (Option<net::Connection>, net::Role) {
+    let connection = if let Some(port) = args.host {
+        let settings = net::Settings {
+            size: args.size,
+            win_length: args.win_length,
+            first_player: game_config.first_player,
+            overline_forbidden: args.overline_forbidden,
+            renju: args.renju,
+            first_move_center: args.first_move_center,
+            early_draw: args.early_draw,
+            repetition_draw: args.repetition_draw,
+            pente: args.pente,
+            toroidal: args.toroidal,
+        };
+        net::Connection::host(port, settings)
+            .map(|connection| (connection, net::Role::Player(settings.first_player)))
+    } else if let Some(addr) = &args.connect {
+        net::Connection::join(addr).map(|(connection, settings, role)| {
+            args.size = settings.size;
+            game_config.first_player = settings.first_player;
+            args.win_length = settings.win_length;
+            args.overline_forbidden = settings.overline_forbidden;
+            args.renju = settings.renju;
+            args.first_move_center = settings.first_move_center;
+            args.early_draw = settings.early_draw;
+            args.repetition_draw = settings.repetition_draw;
+            args.pente = settings.pente;
+            args.toroidal = settings.toroidal;
+            (connection, role)
+        })
+    } else {
+        return (None, net::Role::Local);
+    };
+
+    match connection {
+        Ok((connection, role)) => {
+            if args.ai || args.swap2 || args.pie || args.handicap > 0 {
+                warn!(
+                    "--ai, --swap2, --pie and --handicap don't apply to online games, ignoring \
+                     them"
+                );
+            }
+            args.ai = false;
+            args.swap2 = false;
+            args.pie = false;
+            args.handicap = 0;
+            (Some(connection), role)
+        }
+        Err(err) => {
+            warn!("Could not connect ({err}), playing locally");
+            (None, net::Role::Local)
+        }
+    }
+}
+
+/// The connection to the other player, or to the host when watching, in an online game;
+/// `None` when playing locally.
+#[derive(Resource)]
+pub struct Online(pub Option<net::Connection>);
+
+/// Where a request to take back a move in an online game stands.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Takeback {
+    #[default]
+    None,
+    /// We asked for our last move back and are waiting for the answer.
+    Asked,
+    /// The opponent asked for theirs back when the history was `moves` long; we are asked
+    /// to accept with Y or turn it down with N.
+    Offered { moves: usize },
+}
+
+/// The connection line of the HUD for `role`.
+pub fn net_status(role: net::Role) -> String {
+    match role {
+        net::Role::Player(color) => format!("Online: playing {}", color.name()),
+        _ => "Online: watching".to_string(),
+    }
+}
+
+/// Answers the opponent's takeback request with Y or N, taking their last move back on this
+/// board before telling them. The keys are used up here so they don't also do what they are
+/// bound to. Moving or resigning instead turns the request down without a word.
+pub fn takeback_answer_system(
+    mut input: ResMut<Input<KeyCode>>,
+    role: Res<net::Role>,
+    mut online: ResMut<Online>,
+    mut takeback: ResMut<Takeback>,
+    mut notice: ResMut<Notice>,
+    mut placer: StonePlacer,
+    mut text: Query<&mut Text, With<NetText>>,
+) {
+    let Takeback::Offered { moves } = *takeback else {
+        return;
+    };
+    let Some(connection) = &mut online.0 else {
+        return;
+    };
+    let answer = if placer.history.0.len() != moves || *placer.status != GameStatus::InProgress {
+        None
+    } else if input.clear_just_pressed(KeyCode::Y) {
+        Some(true)
+    } else if input.clear_just_pressed(KeyCode::N) {
+        Some(false)
+    } else {
+        return;
+    };
+
+    *takeback = Takeback::None;
+    text.single_mut().sections[0].value = net_status(*role);
+    let sent = match answer {
+        Some(true) => {
+            placer.take_back();
+            notice.show("Took back the opponent's last move");
+            connection.record_takeback(true)
+        }
+        Some(false) => {
+            notice.show("Takeback declined");
+            connection.decline_takeback()
+        }
+        None => Ok(()),
+    };
+    if let Err(err) = sent {
+        warn!("Could not answer the takeback request: {err}");
+    }
+}
+
+/// Adds every move to the connection's record, which sends those made on this side to the
+/// peer and, when hosting, passes them all on to the spectators. Entries already seen aren't
+/// recorded again when a replay puts them back.
+pub fn net_send_system(
+    history: Res<MoveHistory>,
+    role: Res<net::Role>,
+    mut online: ResMut<Online>,
+) {
+    let Some(connection) = &mut online.0 else {
+        return;
+    };
+    if !history.is_changed() {
+        return;
+    }
+
+    for entry in history.0.iter().skip(connection.recorded()) {
+        let result = match *entry {
+            HistoryEntry::Stone(ref record) => connection.record_move(
+                record.col,
+                record.row,
+                record.color,
+                role.may_play(record.color),
+            ),
+            HistoryEntry::Resign(color) => connection.record_resign(color, role.may_play(color)),
+        };
+        if let Err(err) = result {
+            warn!("Could not send to the other player: {err}");
+        }
+    }
+}
+
+/// Plays the peer's moves through the same checks as local ones, handles takeback requests,
+/// and reports a dropped connection, and the wait for it to come back, on the HUD. Nothing
+/// is taken in while a replay is being reviewed.
+pub fn net_receive_system(
+    mut online: ResMut<Online>,
+    role: Res<net::Role>,
+    replay: Res<ReplayState>,
+    mut takeback: ResMut<Takeback>,
+    mut notice: ResMut<Notice>,
+    mut placer: StonePlacer,
+    mut text: Query<&mut Text, With<NetText>>,
+) {
+    let status = || net_status(*role);
+    if online.is_added() && online.0.is_some() {
+        text.single_mut().sections[0].value = status();
+    }
+    let Some(connection) = &mut online.0 else {
+        return;
+    };
+    if replay.reviewing() {
+        return;
+    }
+
+    let hosting = connection.is_host();
+    for event in connection.poll() {
+        // Whatever the opponent does next is their answer
+        if *takeback == Takeback::Asked
+            && matches!(
+                event,
+                net::PeerEvent::Move { .. } | net::PeerEvent::Resign(_)
+            )
+        {
+            *takeback = Takeback::None;
+        }
+        match event {
+            net::PeerEvent::Move { col, row, color } => {
+                // Moves for a color played here only ever come from here
+                let checked = if role.may_play(color) {
+                    Err(MoveError::OutOfTurn)
+                } else {
+                    game_core::validate_remote_move(
+                        &placer.board,
+                        color,
+                        col,
+                        row,
+                        placer.current_player.0,
+                    )
+                };
+                // A refused move leaves this board alone; the host's board is what
+                // spectators are sent, so the host has the last word
+                if let Err(err) = checked.and_then(|()| placer.place(col, row)) {
+                    warn!(
+                        "Ignoring a {} move at ({col}, {row}) from the network: {err:?}",
+                        color.name()
+                    );
+                }
+            }
+            net::PeerEvent::Resign(color) => {
+                if !role.may_play(color) && color == placer.current_player.0 {
+                    let _ = placer.resign();
+                }
+            }
+            net::PeerEvent::TakebackRequested => {
+                let theirs = placer
+                    .history
+                    .0
+                    .last()
+                    .and_then(HistoryEntry::stone)
+                    .is_some_and(|record| !role.may_play(record.color));
+                if theirs && *placer.status == GameStatus::InProgress && *takeback == Takeback::None
+                {
+                    *takeback = Takeback::Offered {
+                        moves: placer.history.0.len(),
+                    };
+                    notice.show("The opponent asks for their last move back");
+                    text.single_mut().sections[0].value =
+                        "Takeback requested \u{2014} accept? (Y/N)".to_string();
+                } else if let Err(err) = connection.decline_takeback() {
+                    warn!("Could not answer the takeback request: {err}");
+                }
+            }
+            net::PeerEvent::TakebackDeclined => {
+                if *takeback == Takeback::Asked {
+                    *takeback = Takeback::None;
+                    notice.show("The opponent declined the takeback");
+                }
+            }
+            net::PeerEvent::Takeback => {
+                // A resignation is never taken back online
+                let last_is_stone = placer
+                    .history
+                    .0
+                    .last()
+                    .is_some_and(|entry| entry.stone().is_some());
+                if !last_is_stone {
+                    warn!("Ignoring a takeback from the network with no move to take back");
+                    continue;
+                }
+                placer.take_back();
+                // Passes it on to the spectators when hosting
+                if let Err(err) = connection.record_takeback(false) {
+                    warn!("Could not send to the other player: {err}");
+                }
+                if *takeback == Takeback::Asked {
+                    *takeback = Takeback::None;
+                    notice.show("The opponent accepted the takeback");
+                } else {
+                    notice.show("The last move was taken back");
+                }
+            }
+            net::PeerEvent::SpectatorJoined => notice.show("A spectator joined"),
+            net::PeerEvent::Reconnecting => {
+                text.single_mut().sections[0].value = if hosting {
+                    "Online: opponent reconnecting\u{2026}".to_string()
+                } else {
+                    "Online: reconnecting\u{2026}".to_string()
+                };
+            }
+            net::PeerEvent::Reconnected => {
+                notice.show(if hosting {
+                    "The opponent is back"
+                } else {
+                    "Reconnected to the host"
+                });
+                text.single_mut().sections[0].value = status();
+            }
+            net::PeerEvent::Disconnected => {
+                notice.show("The connection was lost");
+                text.single_mut().sections[0].value = "Online: disconnected".to_string();
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! The swap2 and pie rule openings, which offset Black's first-move advantage.
+//!
+//! In swap2, Player 1 places three stones (black, white, black). Player 2 then takes White,
+//! takes Black, or places two more stones (white, black) and lets Player 1 choose instead.
+//! Under the pie rule Player 1 places the first stone and Player 2 may swap, taking that
+//! stone's color as theirs and leaving Player 1 to play the other one next. Stones alternate
+//! colors as usual throughout, so only the choices need handling here.
+//!
+//! A handicap game instead opens with Black's stones set on the star points.
+
+use bevy::prelude::*;
+
+use crate::{
+    column_label,
+    hud::{Notice, OpeningText},
+    replay::ReplayState,
+    rules::{self, RuleSet},
+    MoveHistory, StoneColor, StonePlacer,
+};
+
+/// Where the game is in the opening.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Opening {
+    /// Normal alternating play; any opening is over.
+    Done,
+    /// Player 1 places the first three stones.
+    PlaceThree,
+    /// Player 2 picks a color or offers to place two more stones.
+    FirstChoice,
+    /// Player 2 places the fourth and fifth stones.
+    PlaceTwo,
+    /// Player 1 picks a color.
+    SecondChoice,
+    /// Pie rule: Player 1 places the first stone.
+    PieMove,
+    /// Pie rule: Player 2 swaps colors or keeps their own.
+    PieChoice,
+}
+
+impl Opening {
+    /// The state a new game starts in.
+    pub fn start(rules: &RuleSet) -> Self {
+        if rules.swap2 {
+            Opening::PlaceThree
+        } else if rules.pie {
+            Opening::PieMove
+        } else {
+            Opening::Done
+        }
+    }
+
+    /// Whether stones may be placed, rather than waiting on a choice.
+    pub fn accepts_moves(self) -> bool {
+        !matches!(
+            self,
+            Opening::FirstChoice | Opening::SecondChoice | Opening::PieChoice
+        )
+    }
+
+    /// Moves on once enough stones are down, or back again when stones are taken back.
+    pub fn after_moves(self, count: usize) -> Self {
+        match self {
+            Opening::PlaceThree if count >= 3 => Opening::FirstChoice,
+            Opening::FirstChoice | Opening::PlaceTwo if count < 3 => Opening::PlaceThree,
+            Opening::PlaceTwo if count >= 5 => Opening::SecondChoice,
+            Opening::SecondChoice if count < 5 => Opening::PlaceTwo,
+            Opening::PieMove if count >= 1 => Opening::PieChoice,
+            Opening::PieChoice if count < 1 => Opening::PieMove,
+            other => other,
+        }
+    }
+
+    /// Applies choice `option` (numbered from 1) of the current prompt, returning the new state
+    /// and what was decided, or `None` if there is no such choice.
+    pub fn choose(self, option: usize) -> Option<(Self, &'static str)> {
+        match (self, option) {
+            (Opening::FirstChoice, 1) => Some((Opening::Done, "Player 2 plays White")),
+            (Opening::FirstChoice, 2) => Some((Opening::Done, "Player 2 plays Black")),
+            (Opening::FirstChoice, 3) => Some((Opening::PlaceTwo, "Player 2 places two more")),
+            (Opening::SecondChoice, 1) => Some((Opening::Done, "Player 1 plays White")),
+            (Opening::SecondChoice, 2) => Some((Opening::Done, "Player 1 plays Black")),
+            (Opening::PieChoice, 1) => Some((
+                Opening::Done,
+                "Player 2 takes the first stone, Player 1 moves next",
+            )),
+            (Opening::PieChoice, 2) => Some((Opening::Done, "Player 2 keeps their color")),
+            _ => None,
+        }
+    }
+
+    /// HUD line telling the players what the opening needs next.
+    pub fn prompt(self) -> &'static str {
+        match self {
+            Opening::Done => "",
+            Opening::PlaceThree => "Swap2: Player 1 places two black stones and one white",
+            Opening::FirstChoice => {
+                "Swap2, Player 2: 1 = play White, 2 = play Black, 3 = place two more stones"
+            }
+            Opening::PlaceTwo => "Swap2: Player 2 places one white and one black stone",
+            Opening::SecondChoice => "Swap2, Player 1: 1 = play White, 2 = play Black",
+            Opening::PieMove => "Pie rule: Player 1 places the first stone",
+            Opening::PieChoice => "Pie rule, Player 2: Swap? (Y/N)",
+        }
+    }
+}
+
+/// Advances the opening as stones go down and reads the players' choices from the number
+/// keys, or Y and N for the pie rule's swap.
+pub fn opening_system(
+    keycode: Res<Input<KeyCode>>,
+    history: Res<MoveHistory>,
+    replay: Res<ReplayState>,
+    mut opening: ResMut<Opening>,
+    mut notice: ResMut<Notice>,
+    mut text: Query<&mut Text, With<OpeningText>>,
+) {
+    // Stepping through a replay shuffles the stone count without replaying the choices
+    if !replay.reviewing() {
+        let next = opening.after_moves(history.stones().count());
+        if next != *opening {
+            *opening = next;
+        }
+    }
+
+    let keys: &[_] = if *opening == Opening::PieChoice {
+        &[(KeyCode::Y, 1), (KeyCode::N, 2)]
+    } else {
+        &[(KeyCode::Key1, 1), (KeyCode::Key2, 2), (KeyCode::Key3, 3)]
+    };
+    for &(key, option) in keys {
+        if !keycode.just_pressed(key) {
+            continue;
+        }
+        if let Some((next, decision)) = opening.choose(option) {
+            *opening = next;
+            notice.show(decision);
+        }
+    }
+
+    if opening.is_changed() {
+        text.single_mut().sections[0].value = opening.prompt().to_string();
+    }
+}
+
+/// Black stones set on the star points at the start of every game, with `--handicap`.
+#[derive(Resource)]
+pub struct Handicap {
+    pub stones: usize,
+    /// Whether the board is waiting for them, at the start and after a restart.
+    pub pending: bool,
+}
+
+/// Puts the handicap stones down as Black's opening moves, so they are in the history like
+/// any other stone, and hands the first real move to White.
+pub fn handicap_system(mut handicap: ResMut<Handicap>, mut placer: StonePlacer) {
+    if !std::mem::take(&mut handicap.pending) || handicap.stones == 0 {
+        return;
+    }
+
+    for (col, row) in rules::handicap_points(placer.config.size, handicap.stones) {
+        placer.current_player.0 = StoneColor::Black;
+        if let Err(err) = placer.place(col, row) {
+            warn!(
+                "Could not place a handicap stone at {}{}: {err:?}",
+                column_label(col),
+                row + 1
+            );
+            return;
+        }
+    }
+    placer.current_player.0 = StoneColor::White;
+}
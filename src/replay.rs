@@ -0,0 +1,200 @@
+//! Stepping back through a game, and forward again, one move at a time or on autoplay.
+
+use bevy::prelude::*;
+
+use crate::{
+    column_label,
+    hud::{ButtonAction, Notice, ReplayText},
+    keys::{self, Action},
+    sgf, StoneColor, StonePlacer,
+};
+
+/// The game being stepped through; moves past `cursor` are off the board until replayed.
+#[derive(Resource, Default)]
+pub struct ReplayState {
+    pub moves: Vec<sgf::SgfMove>,
+    pub cursor: usize,
+    /// Who resigned after the last move; the resignation returns once it is replayed.
+    pub resigned: Option<StoneColor>,
+}
+
+impl ReplayState {
+    /// True while some moves are taken back for review; play resumes at the last move.
+    pub fn reviewing(&self) -> bool {
+        self.cursor < self.moves.len()
+    }
+
+    /// Takes the latest shown stone off the board, first setting the review up from the
+    /// history if it isn't under way.
+    pub fn step_back(&mut self, placer: &mut StonePlacer) {
+        if !self.reviewing() {
+            self.moves = placer
+                .history
+                .stones()
+                .map(|record| (record.col, record.row, record.color))
+                .collect();
+            self.cursor = self.moves.len();
+            self.resigned = placer.history.resigned();
+            // Step back over the stones only; the resignation has no place on the board
+            if self.resigned.is_some() && !self.moves.is_empty() {
+                placer.take_back();
+            }
+        }
+        if self.cursor > 0 && placer.take_back().is_some() {
+            self.cursor -= 1;
+        }
+    }
+
+    /// Puts the next stone of the review back on the board, and the resignation after the
+    /// last one. Returns false if the stone was refused and the review can't go on.
+    pub fn step_forward(&mut self, placer: &mut StonePlacer) -> bool {
+        let (col, row, color) = self.moves[self.cursor];
+        placer.current_player.0 = color;
+        if let Err(err) = placer.place(col, row) {
+            warn!(
+                "Replay stopped at {}{}: {err:?}",
+                column_label(col),
+                row + 1
+            );
+            return false;
+        }
+        self.cursor += 1;
+        if let Some(color) = self.resigned.filter(|_| !self.reviewing()) {
+            placer.current_player.0 = color;
+            let _ = placer.resign();
+        }
+        true
+    }
+}
+
+/// Plays the review forward on its own, one move every `timer` period, while `playing`.
+#[derive(Resource)]
+pub struct AutoplayTimer {
+    pub playing: bool,
+    pub timer: Timer,
+}
+
+impl AutoplayTimer {
+    /// The seconds between moves that + and - step through.
+    pub const INTERVALS: [f32; 6] = [0.25, 0.5, 1.0, 1.5, 2.0, 3.0];
+    pub const DEFAULT_INTERVAL: f32 = 1.0;
+
+    pub fn interval(&self) -> f32 {
+        self.timer.duration().as_secs_f32()
+    }
+
+    /// Moves to the next interval in `INTERVALS` that is shorter (`faster`) or longer,
+    /// staying put at either end.
+    pub fn change_speed(&mut self, faster: bool) {
+        let current = self.interval();
+        let next = if faster {
+            Self::INTERVALS
+                .iter()
+                .rev()
+                .find(|&&interval| interval < current)
+        } else {
+            Self::INTERVALS.iter().find(|&&interval| interval > current)
+        };
+        if let Some(&interval) = next {
+            self.timer = Timer::from_seconds(interval, TimerMode::Repeating);
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.playing = true;
+        self.timer.reset();
+    }
+}
+
+impl Default for AutoplayTimer {
+    fn default() -> Self {
+        AutoplayTimer {
+            playing: false,
+            timer: Timer::from_seconds(Self::DEFAULT_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Steps through the game with Ctrl and the arrow keys: Left takes the latest shown stone off
+/// the board, Right puts the next one back. Once a review is under way the arrows step without
+/// Ctrl as well; otherwise plain arrows move the keyboard cursor.
+///
+/// Ctrl+Space rewinds to the first move and plays the game through on its own; while
+/// reviewing, Space pauses and resumes and + and - change the speed. Stepping by hand pauses,
+/// and reaching the last move stops.
+pub fn replay_system(
+    keys: keys::Keys,
+    time: Res<Time>,
+    mut replay: ResMut<ReplayState>,
+    mut autoplay: ResMut<AutoplayTimer>,
+    mut clicks: EventReader<ButtonAction>,
+    mut notice: ResMut<Notice>,
+    mut placer: StonePlacer,
+    mut text: Query<&mut Text, With<ReplayText>>,
+) {
+    let review = clicks.iter().any(|&button| button == ButtonAction::Review);
+    // Ctrl or not, as the keyboard cursor leaves the arrows alone during a review
+    let reviewing_key =
+        |action| replay.reviewing() && keys.input.just_pressed(keys.bindings.key(action));
+    if keys.ctrl() && keys.just_pressed(Action::Autoplay) {
+        if !placer.history.0.is_empty() {
+            replay.step_back(&mut placer);
+            while replay.cursor > 0 {
+                replay.step_back(&mut placer);
+            }
+            autoplay.start();
+        }
+    } else if keys.just_pressed(Action::Autoplay) && replay.reviewing() {
+        if autoplay.playing {
+            autoplay.playing = false;
+        } else {
+            autoplay.start();
+        }
+    } else if keys.just_pressed(Action::ReviewBack) || review || reviewing_key(Action::ReviewBack) {
+        autoplay.playing = false;
+        replay.step_back(&mut placer);
+    } else if reviewing_key(Action::ReviewForward) {
+        autoplay.playing = false;
+        if !replay.step_forward(&mut placer) {
+            return;
+        }
+    }
+
+    if replay.reviewing() {
+        let faster = keys.just_pressed(Action::AutoplayFaster)
+            || keys
+                .input
+                .any_just_pressed([KeyCode::Plus, KeyCode::NumpadAdd]);
+        let slower = keys.just_pressed(Action::AutoplaySlower)
+            || keys.input.just_pressed(KeyCode::NumpadSubtract);
+        if faster || slower {
+            autoplay.change_speed(faster);
+            notice.show(format!("Autoplay: a move every {} s", autoplay.interval()));
+        }
+    }
+    if autoplay.playing && replay.reviewing() {
+        autoplay.timer.tick(time.delta());
+        if autoplay.timer.just_finished() && !replay.step_forward(&mut placer) {
+            autoplay.playing = false;
+        }
+    }
+    // The end of the game, or a restart or undo that ended the review, stops it
+    if autoplay.playing && !replay.reviewing() {
+        autoplay.playing = false;
+    }
+
+    if replay.is_changed() || autoplay.is_changed() {
+        text.single_mut().sections[0].value = if !replay.reviewing() {
+            String::new()
+        } else if autoplay.playing {
+            format!(
+                "Move {} / {} \u{25b6} {} s",
+                replay.cursor,
+                replay.moves.len(),
+                autoplay.interval()
+            )
+        } else {
+            format!("Move {} / {}", replay.cursor, replay.moves.len())
+        };
+    }
+}
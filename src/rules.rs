@@ -0,0 +1,964 @@
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+
+use crate::{BoardState, StoneColor};
+
+pub mod renju;
+
+/// Stones in a row that win a game of Gomoku.
+pub const DEFAULT_WIN_LENGTH: usize = 5;
+/// The winning line lengths a game may be set up with; anything shorter is trivial and
+/// anything longer hardly fits the smallest board.
+pub const WIN_LENGTHS: std::ops::RangeInclusive<usize> = 3..=9;
+/// How many handicap stones Black may start with, besides none; the star points of each
+/// board give room for nine.
+pub const HANDICAPS: std::ops::RangeInclusive<usize> = 2..=9;
+
+/// Which variant of the rules the game is played under.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RuleSet {
+    /// How many stones in a row win; five unless playing a custom variant.
+    pub win_length: usize,
+    /// For Black and White, indexed by `StoneColor as usize`, whether that color is barred
+    /// from lines longer than `win_length`: such a line doesn't win, and a move making one is
+    /// refused unless it makes exactly `win_length` as well. Otherwise overlines win too
+    /// (freestyle).
+    pub overline_forbidden: [bool; 2],
+    /// Whether Black is barred from the Renju forbidden moves; see `renju::is_forbidden`.
+    pub renju: bool,
+    /// Whether the game opens with swap2; see `crate::opening`.
+    pub swap2: bool,
+    /// Whether the second player may take over the first stone's color; see
+    /// `crate::opening`.
+    pub pie: bool,
+    /// Whether the first stone of the game must go on a center point; see `is_center`.
+    pub first_move_center: bool,
+    /// Whether the game is drawn once `detect_dead_draw` finds no line left to complete.
+    pub early_draw: bool,
+    /// Whether the game is drawn when a position comes up for the `REPETITIONS_TO_DRAW`th
+    /// time with the same player to move; see `game_core::PositionCounts`.
+    pub repetition_draw: bool,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet {
+            win_length: DEFAULT_WIN_LENGTH,
+            overline_forbidden: [false; 2],
+            renju: false,
+            swap2: false,
+            pie: false,
+            first_move_center: false,
+            early_draw: false,
+            repetition_draw: false,
+        }
+    }
+}
+
+/// Which family of rules the game follows, on top of the `RuleSet` flags.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Variant {
+    /// Plain five in a row.
+    #[default]
+    Standard,
+    /// Flanking exactly two opposing stones captures them; five captures also win. Only the
+    /// stone just played captures, so playing into a flanked pair is safe. The first player's
+    /// second stone has to keep away from the center; see `variant_move_legal`.
+    Pente,
+    /// Five in a row on a board whose edges join up, left to right and top to bottom, so a
+    /// line can run off one side and carry on from the other. The wrapping itself is
+    /// `BoardState::offset`'s.
+    Toroidal,
+}
+
+/// Captured pairs that win a Pente game.
+pub const CAPTURES_TO_WIN: usize = 5;
+/// How far from the center point, counted along a row or a column, Pente's first player has
+/// to put their second stone.
+pub const PENTE_OPENING_DISTANCE: usize = 3;
+/// How many times the same position makes a draw under `RuleSet::repetition_draw`.
+pub const REPETITIONS_TO_DRAW: u8 = 3;
+
+/// The four line directions through a stone; each is also scanned in reverse.
+pub const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+/// Whether `(col, row)` is a center point of a `size` board: the middle intersection, or on
+/// an even-sized board, which has no single middle, any of the four around it.
+pub fn is_center(size: usize, col: usize, row: usize) -> bool {
+    let middle = |line: usize| line == size / 2 || size.is_multiple_of(2) && line + 1 == size / 2;
+    middle(col) && middle(row)
+}
+
+/// If the stone just placed at `(col, row)` completes a winning line (`rules.win_length` in a
+/// row, or longer when `rules` allows overlines), returns the stones of that line from one end
+/// to the other.
+///
+/// Only the four lines through `(col, row)` are looked at. That is all a new stone needs as
+/// long as every stone is checked here as it goes down, since a stone can only complete lines
+/// through itself. Taking stones off is another matter when `rules` forbid overlines: removing
+/// the end of a six leaves an exact, winning five that no stone just placed runs through. So
+/// a Pente capture under such rules and a stone removed in edit mode are followed by
+/// `full_win_scan`. Undo needs neither, as it only goes back to positions that were checked.
+pub fn check_win(
+    board: &BoardState,
+    col: usize,
+    row: usize,
+    color: StoneColor,
+    rules: &RuleSet,
+) -> Option<Vec<(usize, usize)>> {
+    // A line that loops all the way round a wrapping board still has only `size` points
+    let longest = board.size() - 1;
+    DIRECTIONS.iter().find_map(|&(dx, dy)| {
+        let back = count_direction(board, col, row, -dx, -dy, color, longest);
+        let ahead = count_direction(board, col, row, dx, dy, color, longest - back);
+        let count = 1 + back + ahead;
+        let overline_wins = !rules.overline_forbidden[color as usize];
+        if !(count == rules.win_length || count > rules.win_length && overline_wins) {
+            return None;
+        }
+
+        let line = (-(back as i32)..=ahead as i32)
+            .filter_map(|step| board.offset(col, row, dx, dy, step))
+            .collect();
+        Some(line)
+    })
+}
+
+/// The first winning line found on `board` checking every stone the way `check_win` checks
+/// the one just placed, with the color it wins for. Far slower than `check_win`, so only for
+/// positions stones were taken off; see there.
+pub fn full_win_scan(
+    board: &BoardState,
+    rules: &RuleSet,
+) -> Option<(StoneColor, Vec<(usize, usize)>)> {
+    (0..board.size())
+        .flat_map(|row| (0..board.size()).map(move |col| (col, row)))
+        .find_map(|(col, row)| {
+            let color = board.get(col, row)?;
+            check_win(board, col, row, color, rules).map(|line| (color, line))
+        })
+}
+
+/// Removes the opposing pairs the `color` stone just placed at `(col, row)` flanks, as in
+/// X O O X along any line, and returns the captured points. The mover's own stones are never
+/// taken, even when the new one completes a flanked pair of its own color.
+pub fn resolve_captures(
+    board: &mut BoardState,
+    col: usize,
+    row: usize,
+    color: StoneColor,
+) -> Vec<(usize, usize)> {
+    let at = |step: i32, dx: i32, dy: i32| board.offset(col, row, dx, dy, step);
+
+    let mut captured = Vec::new();
+    for (dx, dy) in DIRECTIONS
+        .iter()
+        .flat_map(|&(dx, dy)| [(dx, dy), (-dx, -dy)])
+    {
+        let (Some(first), Some(second), Some(anchor)) =
+            (at(1, dx, dy), at(2, dx, dy), at(3, dx, dy))
+        else {
+            continue;
+        };
+        let opponent = Some(color.opponent());
+        if board.get(first.0, first.1) == opponent
+            && board.get(second.0, second.1) == opponent
+            && board.get(anchor.0, anchor.1) == Some(color)
+        {
+            captured.extend([first, second]);
+        }
+    }
+    for &(x, y) in &captured {
+        board.remove(x, y);
+    }
+    captured
+}
+
+/// Whether `variant` lets `color` play at the empty point `(col, row)`, on top of what
+/// `forbidden_move` rules out. Only Pente ever says no: under its tournament rule Black's
+/// second stone, played with one stone of each color on the board, has to go at least
+/// `PENTE_OPENING_DISTANCE` points from the center, so the first player can't build on the
+/// center straight away. A stone played into a flanked pair is never taken for it, so that is
+/// always allowed.
+pub fn variant_move_legal(
+    board: &BoardState,
+    col: usize,
+    row: usize,
+    color: StoneColor,
+    variant: Variant,
+) -> bool {
+    if variant != Variant::Pente || color != StoneColor::Black || board.stone_count() != 2 {
+        return true;
+    }
+    let black = (0..board.size())
+        .flat_map(|y| (0..board.size()).map(move |x| (x, y)))
+        .filter(|&(x, y)| board.get(x, y) == Some(StoneColor::Black))
+        .count();
+    let center = board.size() / 2;
+    black != 1 || col.abs_diff(center).max(row.abs_diff(center)) >= PENTE_OPENING_DISTANCE
+}
+
+/// Whether `rules` forbid `color` from playing at `(col, row)`.
+pub fn forbidden_move(
+    board: &BoardState,
+    col: usize,
+    row: usize,
+    color: StoneColor,
+    rules: &RuleSet,
+) -> Option<renju::ForbiddenKind> {
+    if rules.renju && color == StoneColor::Black {
+        if let Some(kind) = renju::is_forbidden(board, col, row) {
+            return Some(kind);
+        }
+    }
+    let overline = rules.overline_forbidden[color as usize]
+        && makes_overline(board, col, row, color, rules.win_length);
+    overline.then_some(renju::ForbiddenKind::Overline)
+}
+
+/// Whether a `color` stone at the empty point `(col, row)` would make a line longer than
+/// `win_length` and none of exactly `win_length`, which wins all the same.
+fn makes_overline(
+    board: &BoardState,
+    col: usize,
+    row: usize,
+    color: StoneColor,
+    win_length: usize,
+) -> bool {
+    let longest = board.size() - 1;
+    let runs: Vec<usize> = DIRECTIONS
+        .iter()
+        .map(|&(dx, dy)| {
+            let back = count_direction(board, col, row, -dx, -dy, color, longest);
+            1 + back + count_direction(board, col, row, dx, dy, color, longest - back)
+        })
+        .collect();
+    !runs.contains(&win_length) && runs.iter().any(|&run| run > win_length)
+}
+
+/// Returns true once every intersection is occupied; call it only after ruling out a win.
+pub fn is_draw(board: &BoardState) -> bool {
+    board.stone_count() == board.size() * board.size()
+}
+
+/// How many stones go down between `detect_dead_draw` checks, which read every line of the
+/// board.
+pub const DEAD_DRAW_INTERVAL: usize = 4;
+
+/// Whether neither color can complete a line of `win_length` anywhere any more: every stretch
+/// of `win_length` points holds stones of both colors. Overlines and forbidden points aren't
+/// ruled out, so a line only they could complete still counts as open and the draw may come
+/// later than it could. Captures can reopen lines, so this means nothing in Pente.
+pub fn detect_dead_draw(board: &BoardState, win_length: usize) -> bool {
+    let size = board.size() as i32;
+    let length = win_length as i32;
+    for &(dx, dy) in &DIRECTIONS {
+        for row in 0..size {
+            for col in 0..size {
+                let (col, row) = (col as usize, row as usize);
+                if board.offset(col, row, dx, dy, length - 1).is_none() {
+                    continue;
+                }
+                let mut colors = (0..length)
+                    .filter_map(|i| board.offset(col, row, dx, dy, i))
+                    .filter_map(|(x, y)| board.get(x, y));
+                let open = match colors.next() {
+                    None => true,
+                    Some(first) => colors.all(|color| color == first),
+                };
+                if open {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Counts consecutive `color` stones starting next to `(col, row)` and walking by `(dx, dy)`,
+/// stopping at `limit`.
+fn count_direction(
+    board: &BoardState,
+    col: usize,
+    row: usize,
+    dx: i32,
+    dy: i32,
+    color: StoneColor,
+    limit: usize,
+) -> usize {
+    let mut count = 0;
+    while count < limit
+        && board
+            .offset(col, row, dx, dy, count as i32 + 1)
+            .is_some_and(|(x, y)| board.get(x, y) == Some(color))
+    {
+        count += 1;
+    }
+    count
+}
+
+/// The star points `count` handicap stones go on, in the order Go places them: opposite
+/// corners first, then the other two, then the sides, with the center taking the odd stone
+/// from five up. `count` is within `HANDICAPS`.
+pub fn handicap_points(size: usize, count: usize) -> Vec<(usize, usize)> {
+    let near = if size >= 13 { 3 } else { 2 };
+    let (far, mid) = (size - 1 - near, size / 2);
+    let mut points = vec![(far, near), (near, far), (far, far), (near, near)];
+    points.truncate(count);
+    if count >= 6 {
+        points.extend([(near, mid), (far, mid)]);
+    }
+    if count >= 8 {
+        points.extend([(mid, near), (mid, far)]);
+    }
+    if count >= 5 && count % 2 == 1 {
+        points.push((mid, mid));
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_core::{self, MoveError};
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+    /// A 15x15 Pente board with Black on the center and White next to it.
+    fn pente_opening() -> BoardState {
+        let mut board = BoardState::for_variant(15, Variant::Pente);
+        board.set(7, 7, StoneColor::Black);
+        board.set(8, 7, StoneColor::White);
+        board
+    }
+
+    /// A 15x15 board with `color` stones at `points`.
+    fn board_with(color: StoneColor, points: &[(usize, usize)]) -> BoardState {
+        let mut board = BoardState::new(15);
+        for &(col, row) in points {
+            board.set(col, row, color);
+        }
+        board
+    }
+
+    #[test]
+    fn five_wins_in_every_direction() {
+        let rules = RuleSet::default();
+        for (dx, dy) in DIRECTIONS {
+            let points: Vec<_> = (0..5)
+                .map(|i| ((5 + i * dx) as usize, (7 + i * dy) as usize))
+                .collect();
+            let board = board_with(StoneColor::Black, &points);
+            for &(col, row) in &points {
+                let line = check_win(&board, col, row, StoneColor::Black, &rules);
+                assert_eq!(line.map(|line| line.len()), Some(5), "{dx}, {dy}");
+            }
+        }
+    }
+
+    #[test]
+    fn four_or_a_broken_five_does_not_win() {
+        let rules = RuleSet::default();
+        let four = board_with(StoneColor::Black, &[(3, 3), (4, 3), (5, 3), (6, 3)]);
+        assert_eq!(check_win(&four, 6, 3, StoneColor::Black, &rules), None);
+        let broken = board_with(StoneColor::Black, &[(3, 3), (4, 3), (6, 3), (7, 3), (8, 3)]);
+        assert_eq!(check_win(&broken, 8, 3, StoneColor::Black, &rules), None);
+        // Another color's stones don't count towards the line
+        let mut mixed = four.clone();
+        mixed.set(7, 3, StoneColor::White);
+        assert_eq!(check_win(&mixed, 7, 3, StoneColor::White, &rules), None);
+    }
+
+    #[test]
+    fn fives_along_the_edges_win() {
+        let rules = RuleSet::default();
+        let bottom: Vec<_> = (10..15).map(|col| (col, 0)).collect();
+        let board = board_with(StoneColor::White, &bottom);
+        assert!(check_win(&board, 14, 0, StoneColor::White, &rules).is_some());
+        let left: Vec<_> = (0..5).map(|row| (0, row)).collect();
+        let board = board_with(StoneColor::White, &left);
+        assert!(check_win(&board, 0, 0, StoneColor::White, &rules).is_some());
+        let corner: Vec<_> = (0..5).map(|i| (14 - i, i)).collect();
+        let board = board_with(StoneColor::White, &corner);
+        assert!(check_win(&board, 14, 0, StoneColor::White, &rules).is_some());
+        // A flat board doesn't wrap, so four at one edge and one at the other is no line
+        let split = board_with(
+            StoneColor::White,
+            &[(11, 2), (12, 2), (13, 2), (14, 2), (0, 2)],
+        );
+        assert_eq!(check_win(&split, 14, 2, StoneColor::White, &rules), None);
+    }
+
+    #[test]
+    fn only_the_fifth_stone_ends_the_game() {
+        let mut board = BoardState::new(15);
+        let rules = RuleSet::default();
+        let mut outcomes = Vec::new();
+        for (i, col) in [3, 4, 5, 6, 7].into_iter().enumerate() {
+            let black = game_core::apply_move(
+                &mut board,
+                col,
+                7,
+                StoneColor::Black,
+                &rules,
+                Variant::Standard,
+                0,
+            );
+            outcomes.push(black.unwrap().outcome);
+            if i < 4 {
+                let white = game_core::apply_move(
+                    &mut board,
+                    col,
+                    9,
+                    StoneColor::White,
+                    &rules,
+                    Variant::Standard,
+                    0,
+                );
+                assert_eq!(white.unwrap().outcome, game_core::Outcome::Continues);
+            }
+        }
+        let wins = outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome, game_core::Outcome::Won { .. }))
+            .count();
+        assert_eq!(wins, 1);
+        assert_eq!(
+            outcomes[4],
+            game_core::Outcome::Won {
+                winner: StoneColor::Black,
+                line: (3..=7).map(|col| (col, 7)).collect(),
+            }
+        );
+    }
+
+    #[test]
+    fn a_diagonal_win_returns_its_five_stones_in_order() {
+        let diagonal = [(3, 9), (4, 8), (5, 7), (6, 6), (7, 5)];
+        let mut board = board_with(StoneColor::White, &diagonal);
+        // Stones next to the line but off it stay out of it
+        board.set(8, 5, StoneColor::White);
+        board.set(2, 10, StoneColor::Black);
+        let line = check_win(&board, 5, 7, StoneColor::White, &RuleSet::default());
+        assert_eq!(line, Some(diagonal.to_vec()));
+    }
+
+    #[test]
+    fn six_in_a_row_wins_only_with_overlines_allowed() {
+        let six: Vec<_> = (4..10).map(|col| (col, 7)).collect();
+        let board = board_with(StoneColor::Black, &six);
+        let freestyle = RuleSet::default();
+        let line = check_win(&board, 9, 7, StoneColor::Black, &freestyle);
+        assert_eq!(line, Some(six.clone()));
+
+        let strict = RuleSet {
+            overline_forbidden: [true, true],
+            ..RuleSet::default()
+        };
+        for &(col, row) in &six {
+            assert_eq!(
+                check_win(&board, col, row, StoneColor::Black, &strict),
+                None
+            );
+        }
+        // Exactly five still wins
+        let five = board_with(StoneColor::Black, &six[..5]);
+        assert!(check_win(&five, 8, 7, StoneColor::Black, &strict).is_some());
+    }
+
+    #[test]
+    fn a_full_board_without_five_is_a_draw() {
+        let mut board = BoardState::new(9);
+        let rules = RuleSet::default();
+        // Pairs of columns alternating along each row, shifted by one from row to row, leave
+        // no run longer than two in any direction
+        let cells: Vec<_> = (0..9)
+            .flat_map(|row| (0..9).map(move |col| (col, row)))
+            .collect();
+        for (i, &(col, row)) in cells.iter().enumerate() {
+            let color = if (col / 2 + row) % 2 == 0 {
+                StoneColor::Black
+            } else {
+                StoneColor::White
+            };
+            assert!(!is_draw(&board));
+            let placement =
+                game_core::apply_move(&mut board, col, row, color, &rules, Variant::Standard, 0)
+                    .unwrap();
+            let expected = if i + 1 == cells.len() {
+                game_core::Outcome::Draw
+            } else {
+                game_core::Outcome::Continues
+            };
+            assert_eq!(placement.outcome, expected);
+        }
+        assert!(is_draw(&board));
+    }
+
+    #[test]
+    fn flanking_a_pair_captures_it() {
+        let mut board = BoardState::for_variant(15, Variant::Pente);
+        // Along the row
+        board.set(3, 7, StoneColor::Black);
+        board.set(4, 7, StoneColor::White);
+        board.set(5, 7, StoneColor::White);
+        // And up the diagonal from the same point
+        board.set(7, 9, StoneColor::White);
+        board.set(8, 10, StoneColor::White);
+        board.set(9, 11, StoneColor::Black);
+        board.set(6, 8, StoneColor::Black);
+        // Down the other diagonal the pair isn't flanked by Black
+        board.set(7, 7, StoneColor::White);
+        board.set(8, 6, StoneColor::White);
+
+        board.set(6, 7, StoneColor::Black);
+        let mut captured = resolve_captures(&mut board, 6, 7, StoneColor::Black);
+        captured.sort();
+        assert_eq!(captured, vec![(4, 7), (5, 7)]);
+        assert_eq!(board.get(4, 7), None);
+        assert_eq!(board.get(5, 7), None);
+
+        let mut other = board.clone();
+        let captured = resolve_captures(&mut other, 6, 8, StoneColor::Black);
+        assert_eq!(captured, vec![(7, 9), (8, 10)]);
+        assert_eq!(other.get(7, 7), Some(StoneColor::White));
+        assert_eq!(other.get(8, 6), Some(StoneColor::White));
+    }
+
+    #[test]
+    fn no_capture_without_both_ends() {
+        let mut board = BoardState::for_variant(9, Variant::Pente);
+        // A pair against the edge has nothing beyond it
+        board.set(0, 4, StoneColor::White);
+        board.set(1, 4, StoneColor::White);
+        board.set(2, 4, StoneColor::Black);
+        assert!(resolve_captures(&mut board, 2, 4, StoneColor::Black).is_empty());
+        // Nor does a pair running into the corner diagonally
+        board.set(8, 8, StoneColor::White);
+        board.set(7, 7, StoneColor::White);
+        board.set(6, 6, StoneColor::Black);
+        assert!(resolve_captures(&mut board, 6, 6, StoneColor::Black).is_empty());
+        // Three in a row, or a single stone, aren't a pair
+        for col in 3..6 {
+            board.set(col, 0, StoneColor::White);
+        }
+        board.set(2, 0, StoneColor::Black);
+        board.set(6, 0, StoneColor::Black);
+        assert!(resolve_captures(&mut board, 6, 0, StoneColor::Black).is_empty());
+        board.set(4, 2, StoneColor::White);
+        board.set(3, 2, StoneColor::Black);
+        board.set(5, 2, StoneColor::Black);
+        assert!(resolve_captures(&mut board, 5, 2, StoneColor::Black).is_empty());
+        assert_eq!(board.stone_count(), 14);
+    }
+
+    #[test]
+    fn the_center_of_odd_boards_is_one_point() {
+        for size in [9, 13, 15, 19, 25] {
+            let middle = size / 2;
+            let centers: Vec<_> = (0..size)
+                .flat_map(|col| (0..size).map(move |row| (col, row)))
+                .filter(|&(col, row)| is_center(size, col, row))
+                .collect();
+            assert_eq!(centers, vec![(middle, middle)], "on {size}x{size}");
+        }
+    }
+
+    #[test]
+    fn the_center_of_even_boards_is_four_points() {
+        let centers: Vec<_> = (0..10)
+            .flat_map(|col| (0..10).map(move |row| (col, row)))
+            .filter(|&(col, row)| is_center(10, col, row))
+            .collect();
+        assert_eq!(centers, vec![(4, 4), (4, 5), (5, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn standard_allows_every_point() {
+        let mut board = BoardState::new(15);
+        board.set(7, 7, StoneColor::Black);
+        board.set(8, 7, StoneColor::White);
+        for variant in [Variant::Standard, Variant::Toroidal] {
+            assert!(variant_move_legal(&board, 6, 6, StoneColor::Black, variant));
+            assert!(variant_move_legal(&board, 9, 9, StoneColor::Black, variant));
+        }
+    }
+
+    #[test]
+    fn pente_keeps_blacks_second_stone_off_the_center() {
+        let board = pente_opening();
+        for (col, row) in [(6, 6), (9, 7), (7, 5), (5, 9)] {
+            assert!(!variant_move_legal(
+                &board,
+                col,
+                row,
+                StoneColor::Black,
+                Variant::Pente
+            ));
+        }
+        for (col, row) in [(10, 7), (7, 4), (4, 4), (0, 14)] {
+            assert!(variant_move_legal(
+                &board,
+                col,
+                row,
+                StoneColor::Black,
+                Variant::Pente
+            ));
+        }
+    }
+
+    #[test]
+    fn pente_opening_rule_binds_only_blacks_second_stone() {
+        let mut board = BoardState::for_variant(15, Variant::Pente);
+        assert!(variant_move_legal(
+            &board,
+            7,
+            7,
+            StoneColor::Black,
+            Variant::Pente
+        ));
+        board.set(7, 7, StoneColor::Black);
+        assert!(variant_move_legal(
+            &board,
+            8,
+            8,
+            StoneColor::White,
+            Variant::Pente
+        ));
+        board.set(8, 8, StoneColor::White);
+        board.set(10, 7, StoneColor::Black);
+        assert!(variant_move_legal(
+            &board,
+            6,
+            6,
+            StoneColor::White,
+            Variant::Pente
+        ));
+        board.set(6, 6, StoneColor::White);
+        assert!(variant_move_legal(
+            &board,
+            7,
+            8,
+            StoneColor::Black,
+            Variant::Pente
+        ));
+
+        // Two handicap stones are both Black's, so the next Black stone is not its second
+        let mut handicap = BoardState::for_variant(15, Variant::Pente);
+        handicap.set(3, 3, StoneColor::Black);
+        handicap.set(11, 11, StoneColor::Black);
+        assert!(variant_move_legal(
+            &handicap,
+            7,
+            7,
+            StoneColor::Black,
+            Variant::Pente
+        ));
+    }
+
+    #[test]
+    fn playing_into_a_flanked_pair_is_legal_and_safe() {
+        // White O O _ and Black X at both ends: the Black stone filling the pair's gap is
+        // flanked but not captured, since only the mover captures
+        let mut board = BoardState::for_variant(15, Variant::Pente);
+        board.set(2, 2, StoneColor::White);
+        board.set(3, 2, StoneColor::Black);
+        board.set(5, 2, StoneColor::White);
+        board.set(9, 9, StoneColor::Black);
+        board.set(10, 10, StoneColor::White);
+        assert!(variant_move_legal(
+            &board,
+            4,
+            2,
+            StoneColor::Black,
+            Variant::Pente
+        ));
+        let placement = game_core::apply_move(
+            &mut board,
+            4,
+            2,
+            StoneColor::Black,
+            &RuleSet::default(),
+            Variant::Pente,
+            0,
+        )
+        .unwrap();
+        assert!(placement.captured.is_empty());
+        assert_eq!(board.get(3, 2), Some(StoneColor::Black));
+        assert_eq!(board.get(4, 2), Some(StoneColor::Black));
+    }
+
+    #[test]
+    fn local_check_agrees_with_a_full_scan() {
+        let mut rng = StdRng::seed_from_u64(95);
+        let rule_sets = [
+            RuleSet::default(),
+            RuleSet {
+                overline_forbidden: [true, true],
+                ..RuleSet::default()
+            },
+            RuleSet {
+                win_length: 4,
+                overline_forbidden: [true, false],
+                ..RuleSet::default()
+            },
+        ];
+        for rules in &rule_sets {
+            for _ in 0..200 {
+                let mut board = BoardState::new(9);
+                let mut color = StoneColor::Black;
+                let mut empty: Vec<_> = (0..9).flat_map(|r| (0..9).map(move |c| (c, r))).collect();
+                empty.shuffle(&mut rng);
+                for (col, row) in empty {
+                    board.set(col, row, color);
+                    let local = check_win(&board, col, row, color, rules);
+                    let full = full_win_scan(&board, rules);
+                    assert_eq!(local.is_some(), full.is_some(), "{board:?}");
+                    if local.is_some() {
+                        assert_eq!(full.map(|(winner, _)| winner), Some(color));
+                        break;
+                    }
+                    color = color.opponent();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn removing_an_overlines_end_can_leave_a_five() {
+        let rules = RuleSet {
+            overline_forbidden: [true, true],
+            ..RuleSet::default()
+        };
+        let mut board = BoardState::new(15);
+        for col in 2..=7 {
+            board.set(col, 5, StoneColor::Black);
+        }
+        assert_eq!(check_win(&board, 7, 5, StoneColor::Black, &rules), None);
+        assert_eq!(full_win_scan(&board, &rules), None);
+
+        board.remove(2, 5);
+        let (winner, line) = full_win_scan(&board, &rules).unwrap();
+        assert_eq!(winner, StoneColor::Black);
+        assert_eq!(line, (3..=7).map(|col| (col, 5)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn a_capture_that_uncovers_a_five_wins_it_for_its_owner() {
+        let rules = RuleSet {
+            overline_forbidden: [true, true],
+            ..RuleSet::default()
+        };
+        let mut board = BoardState::for_variant(15, Variant::Pente);
+        // White's six along row 5 doesn't win, but its end stone is half of a flanked pair
+        for col in 2..=7 {
+            board.set(col, 5, StoneColor::White);
+        }
+        board.set(2, 6, StoneColor::White);
+        board.set(2, 7, StoneColor::Black);
+        board.set(10, 10, StoneColor::Black);
+        let placement = game_core::apply_move(
+            &mut board,
+            2,
+            4,
+            StoneColor::Black,
+            &rules,
+            Variant::Pente,
+            0,
+        )
+        .unwrap();
+        assert_eq!(placement.captured.len(), 2);
+        assert_eq!(
+            placement.outcome,
+            game_core::Outcome::Won {
+                winner: StoneColor::White,
+                line: (3..=7).map(|col| (col, 5)).collect(),
+            }
+        );
+    }
+
+    #[test]
+    fn apply_move_refuses_what_the_variant_forbids() {
+        let mut board = pente_opening();
+        let rules = RuleSet::default();
+        let near = game_core::apply_move(
+            &mut board,
+            6,
+            6,
+            StoneColor::Black,
+            &rules,
+            Variant::Pente,
+            0,
+        );
+        assert_eq!(near, Err(MoveError::NearCenter));
+        assert_eq!(board.get(6, 6), None);
+        let far = game_core::apply_move(
+            &mut board,
+            4,
+            7,
+            StoneColor::Black,
+            &rules,
+            Variant::Pente,
+            0,
+        );
+        assert!(far.is_ok());
+    }
+
+    /// A `size` board filled so that no stretch of five in any direction is all one color:
+    /// the colors go in pairs along the rows and alternate down the columns.
+    fn blocked_board(size: usize) -> BoardState {
+        let mut board = BoardState::new(size);
+        for row in 0..size {
+            for col in 0..size {
+                let color = if (col / 2 + row).is_multiple_of(2) {
+                    StoneColor::Black
+                } else {
+                    StoneColor::White
+                };
+                board.set(col, row, color);
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn an_empty_board_is_no_dead_draw() {
+        assert!(!detect_dead_draw(&BoardState::new(15), DEFAULT_WIN_LENGTH));
+    }
+
+    #[test]
+    fn a_board_with_every_line_blocked_is_a_dead_draw() {
+        let mut board = blocked_board(15);
+        assert!(detect_dead_draw(&board, DEFAULT_WIN_LENGTH));
+        // Points far enough apart leave every stretch through them holding both colors
+        for (col, row) in [(0, 0), (7, 7), (14, 14), (0, 14), (14, 0)] {
+            board.remove(col, row);
+        }
+        assert!(!is_draw(&board));
+        assert!(detect_dead_draw(&board, DEFAULT_WIN_LENGTH));
+    }
+
+    #[test]
+    fn one_open_stretch_is_enough_to_play_on() {
+        let mut board = blocked_board(15);
+        // Clearing a pair of Black stones between two White pairs leaves six points of the
+        // row with only White on them
+        board.remove(2, 1);
+        board.remove(3, 1);
+        assert!(!detect_dead_draw(&board, DEFAULT_WIN_LENGTH));
+        // A longer target needs seven
+        assert!(detect_dead_draw(&board, 7));
+    }
+
+    #[test]
+    fn a_board_too_small_for_the_line_is_a_dead_draw_from_the_start() {
+        assert!(detect_dead_draw(&BoardState::new(4), DEFAULT_WIN_LENGTH));
+        assert!(!detect_dead_draw(&BoardState::new(5), DEFAULT_WIN_LENGTH));
+    }
+
+    /// A 15x15 board that wraps, with the given Black stones.
+    fn torus_with(points: &[(usize, usize)]) -> BoardState {
+        let mut board = BoardState::for_variant(15, Variant::Toroidal);
+        for &(col, row) in points {
+            board.set(col, row, StoneColor::Black);
+        }
+        board
+    }
+
+    #[test]
+    fn a_line_wraps_across_the_right_edge() {
+        let rules = RuleSet::default();
+        let points = [(12, 7), (13, 7), (14, 7), (0, 7), (1, 7)];
+        let torus = torus_with(&points);
+        for &(col, row) in &points {
+            let line = check_win(&torus, col, row, StoneColor::Black, &rules);
+            assert_eq!(line.as_deref(), Some(&points[..]), "from ({col}, {row})");
+        }
+        let flat = board_with(StoneColor::Black, &points);
+        assert_eq!(check_win(&flat, 0, 7, StoneColor::Black, &rules), None);
+        assert_eq!(check_win(&flat, 14, 7, StoneColor::Black, &rules), None);
+    }
+
+    #[test]
+    fn a_diagonal_wraps_across_the_corner() {
+        let rules = RuleSet::default();
+        let points = [(13, 13), (14, 14), (0, 0), (1, 1), (2, 2)];
+        let line = check_win(&torus_with(&points), 0, 0, StoneColor::Black, &rules);
+        assert_eq!(line.as_deref(), Some(&points[..]));
+        let flat = board_with(StoneColor::Black, &points);
+        assert_eq!(check_win(&flat, 0, 0, StoneColor::Black, &rules), None);
+    }
+
+    #[test]
+    fn a_row_all_the_way_round_is_counted_once() {
+        let points: Vec<_> = (0..15).map(|col| (col, 7)).collect();
+        let torus = torus_with(&points);
+        let line = check_win(&torus, 4, 7, StoneColor::Black, &RuleSet::default());
+        assert_eq!(line.map(|line| line.len()), Some(15));
+        let no_overline = RuleSet {
+            overline_forbidden: [true, true],
+            ..RuleSet::default()
+        };
+        assert_eq!(
+            check_win(&torus, 4, 7, StoneColor::Black, &no_overline),
+            None
+        );
+    }
+
+    #[test]
+    fn each_color_follows_its_own_overline_setting() {
+        let five = [(3, 7), (4, 7), (5, 7), (6, 7), (7, 7)];
+        for overline_forbidden in [[false, false], [true, false], [false, true], [true, true]] {
+            let rules = RuleSet {
+                overline_forbidden,
+                ..RuleSet::default()
+            };
+            for color in [StoneColor::Black, StoneColor::White] {
+                let barred = overline_forbidden[color as usize];
+                let setting = format!("{color:?} with {overline_forbidden:?}");
+
+                // Two stones and three either side of a gap, which a stone fills to make six
+                let mut board = board_with(color, &five);
+                board.remove(5, 7);
+                board.set(8, 7, color);
+                let refused = forbidden_move(&board, 5, 7, color, &rules);
+                assert_eq!(refused.is_some(), barred, "{setting}");
+
+                board.set(5, 7, color);
+                let six = check_win(&board, 5, 7, color, &rules);
+                assert_eq!(six.is_none(), barred, "{setting}");
+
+                // Exactly five wins whatever the setting
+                let board = board_with(color, &five);
+                assert!(
+                    check_win(&board, 7, 7, color, &rules).is_some(),
+                    "{setting}"
+                );
+                assert_eq!(
+                    forbidden_move(&board, 2, 8, color, &rules),
+                    None,
+                    "{setting}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_stone_making_a_five_and_a_six_at_once_is_allowed() {
+        let rules = RuleSet {
+            overline_forbidden: [true, true],
+            ..RuleSet::default()
+        };
+        for color in [StoneColor::Black, StoneColor::White] {
+            // (7, 7) joins five along the row and six down the column
+            let mut board = board_with(color, &[(3, 7), (4, 7), (5, 7), (6, 7)]);
+            for row in [2, 3, 4, 5, 6] {
+                board.set(7, row, color);
+            }
+            assert_eq!(forbidden_move(&board, 7, 7, color, &rules), None);
+            board.set(7, 7, color);
+            let line = check_win(&board, 7, 7, color, &rules);
+            assert_eq!(line.map(|line| line.len()), Some(5), "{color:?}");
+        }
+    }
+}
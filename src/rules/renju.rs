@@ -0,0 +1,220 @@
+//! Renju restrictions on Black: no overlines, no double fours and no double threes.
+//!
+//! Threes are judged one level deep: a three counts as open if one more stone turns it into a
+//! straight four, without checking whether that stone would itself be forbidden.
+
+use crate::{BoardState, StoneColor};
+
+use super::DIRECTIONS;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ForbiddenKind {
+    Overline,
+    DoubleFour,
+    DoubleThree,
+}
+
+impl ForbiddenKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ForbiddenKind::Overline => "overline",
+            ForbiddenKind::DoubleFour => "double four",
+            ForbiddenKind::DoubleThree => "double three",
+        }
+    }
+}
+
+/// How far along each direction a line is inspected; enough to see any five through the stone.
+const REACH: usize = 5;
+const CENTER: usize = REACH;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Cell {
+    Black,
+    Empty,
+    Blocked,
+}
+
+type Line = [Cell; 2 * REACH + 1];
+
+/// Whether a Black stone at the empty cell `(col, row)` would break the Renju rules, and how.
+/// Making exactly five always wins, so such a move is never forbidden.
+pub fn is_forbidden(board: &BoardState, col: usize, row: usize) -> Option<ForbiddenKind> {
+    let lines: Vec<Line> = DIRECTIONS
+        .iter()
+        .map(|&(dx, dy)| line_through(board, col, row, dx, dy))
+        .collect();
+
+    if lines.iter().any(|line| run_length(line, CENTER) == 5) {
+        return None;
+    }
+    if lines.iter().any(|line| run_length(line, CENTER) > 5) {
+        return Some(ForbiddenKind::Overline);
+    }
+
+    let fours: usize = lines.iter().map(count_fours).sum();
+    if fours >= 2 {
+        return Some(ForbiddenKind::DoubleFour);
+    }
+
+    let threes = lines
+        .iter()
+        .filter(|line| count_fours(line) == 0 && is_open_three(line))
+        .count();
+    if threes >= 2 {
+        return Some(ForbiddenKind::DoubleThree);
+    }
+    None
+}
+
+/// The cells within `REACH` of `(col, row)` along `(dx, dy)`, with a Black stone placed at the
+/// center and anything off the board or White treated as blocked.
+fn line_through(board: &BoardState, col: usize, row: usize, dx: i32, dy: i32) -> Line {
+    let mut line = [Cell::Blocked; 2 * REACH + 1];
+    for (i, cell) in line.iter_mut().enumerate() {
+        let Some((x, y)) = board.offset(col, row, dx, dy, i as i32 - CENTER as i32) else {
+            continue;
+        };
+        *cell = match board.get(x, y) {
+            None => Cell::Empty,
+            Some(StoneColor::Black) => Cell::Black,
+            Some(StoneColor::White) => Cell::Blocked,
+        };
+    }
+    line[CENTER] = Cell::Black;
+    line
+}
+
+/// Length of the run of Black stones covering `index`, and where it starts.
+fn run_bounds(line: &Line, index: usize) -> (usize, usize) {
+    let mut start = index;
+    while start > 0 && line[start - 1] == Cell::Black {
+        start -= 1;
+    }
+    let mut end = index;
+    while end + 1 < line.len() && line[end + 1] == Cell::Black {
+        end += 1;
+    }
+    (start, end)
+}
+
+fn run_length(line: &Line, index: usize) -> usize {
+    let (start, end) = run_bounds(line, index);
+    end - start + 1
+}
+
+/// Empty cells where one more Black stone makes exactly five together with the center stone.
+fn five_points(line: &Line) -> Vec<usize> {
+    (0..line.len())
+        .filter(|&i| line[i] == Cell::Empty)
+        .filter(|&i| {
+            let mut next = *line;
+            next[i] = Cell::Black;
+            let (start, end) = run_bounds(&next, i);
+            end - start + 1 == 5 && (start..=end).contains(&CENTER)
+        })
+        .collect()
+}
+
+/// Number of fours through the center. A straight four has two five points, five apart, but is
+/// still one four; five points any other distance apart belong to two separate fours.
+fn count_fours(line: &Line) -> usize {
+    match five_points(line).as_slice() {
+        [] => 0,
+        [_] => 1,
+        [a, b] if b - a == 5 => 1,
+        _ => 2,
+    }
+}
+
+/// Whether one more Black stone in this line turns it into a straight four through the center.
+fn is_open_three(line: &Line) -> bool {
+    (0..line.len())
+        .filter(|&i| line[i] == Cell::Empty)
+        .any(|i| {
+            let mut next = *line;
+            next[i] = Cell::Black;
+            matches!(five_points(&next).as_slice(), [a, b] if b - a == 5)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 15x15 board with the given Black and White stones.
+    fn position(black: &[(usize, usize)], white: &[(usize, usize)]) -> BoardState {
+        let mut board = BoardState::new(15);
+        for &(col, row) in black {
+            board.set(col, row, StoneColor::Black);
+        }
+        for &(col, row) in white {
+            board.set(col, row, StoneColor::White);
+        }
+        board
+    }
+
+    #[test]
+    fn two_open_threes_are_a_double_three() {
+        let board = position(&[(5, 7), (6, 7), (7, 5), (7, 6)], &[]);
+        assert_eq!(is_forbidden(&board, 7, 7), Some(ForbiddenKind::DoubleThree));
+        // Split threes count as well
+        let board = position(&[(4, 7), (6, 7), (7, 4), (7, 6)], &[]);
+        assert_eq!(is_forbidden(&board, 7, 7), Some(ForbiddenKind::DoubleThree));
+    }
+
+    #[test]
+    fn a_blocked_three_is_not_open() {
+        let board = position(&[(5, 7), (6, 7), (7, 5), (7, 6)], &[(4, 7)]);
+        assert_eq!(is_forbidden(&board, 7, 7), None);
+        let board = position(&[(5, 7), (6, 7)], &[]);
+        assert_eq!(is_forbidden(&board, 7, 7), None);
+    }
+
+    #[test]
+    fn two_fours_are_a_double_four() {
+        let board = position(&[(4, 7), (5, 7), (6, 7), (7, 4), (7, 5), (7, 6)], &[]);
+        assert_eq!(is_forbidden(&board, 7, 7), Some(ForbiddenKind::DoubleFour));
+        // Blocking one end of each still leaves two fours
+        let board = position(
+            &[(4, 7), (5, 7), (6, 7), (7, 4), (7, 5), (7, 6)],
+            &[(3, 7), (7, 3)],
+        );
+        assert_eq!(is_forbidden(&board, 7, 7), Some(ForbiddenKind::DoubleFour));
+    }
+
+    #[test]
+    fn two_fours_along_one_line_are_a_double_four() {
+        // X.X?X.X: the stone makes a four with either outer pair
+        let board = position(&[(4, 7), (6, 7), (8, 7), (10, 7)], &[]);
+        assert_eq!(is_forbidden(&board, 7, 7), Some(ForbiddenKind::DoubleFour));
+    }
+
+    #[test]
+    fn a_straight_four_is_one_four() {
+        let board = position(&[(4, 7), (5, 7), (6, 7)], &[]);
+        assert_eq!(is_forbidden(&board, 7, 7), None);
+    }
+
+    #[test]
+    fn six_is_an_overline_but_five_always_wins() {
+        let board = position(&[(4, 7), (5, 7), (6, 7), (8, 7), (9, 7)], &[]);
+        assert_eq!(is_forbidden(&board, 7, 7), Some(ForbiddenKind::Overline));
+        // The five along the row wins even though the column and the diagonal make a double
+        // three as well
+        let board = position(
+            &[
+                (3, 7),
+                (4, 7),
+                (5, 7),
+                (6, 7),
+                (7, 5),
+                (7, 6),
+                (5, 5),
+                (6, 6),
+            ],
+            &[],
+        );
+        assert_eq!(is_forbidden(&board, 7, 7), None);
+    }
+}
@@ -0,0 +1,493 @@
+//! The game in progress, written to `save.json` when the window closes and picked up again on
+//! the next start, and the named games kept under `saves/` with F5. Games also go out as SGF,
+//! to `game.sgf` with S and to the archive as they end, and come back in with `--load`.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{app::AppExit, prelude::*, window::WindowCloseRequested};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    archive, column_label, drill,
+    hud::{Notice, Scoreboard},
+    keys::{self, Action},
+    online::Online,
+    rules::{self, RuleSet, Variant},
+    sgf::{self, SgfMove},
+    BoardConfig, CurrentPlayer, GameOver, GameStatus, MoveHistory, StoneColor, StonePlacer,
+};
+
+/// Where the game is kept between runs, relative to the working directory.
+pub const SAVE_PATH: &str = "save.json";
+
+/// Where named saves go, one `<name>.json` each, relative to the working directory.
+pub const SAVES_DIR: &str = "saves";
+
+/// Everything needed to set the board up again.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedGame {
+    pub size: usize,
+    /// Who was to move, which differs from the alternation after a swap2 choice.
+    pub current_player: StoneColor,
+    pub moves: Vec<SavedMove>,
+    /// Who gave the game up, if anyone; older saves don't have it.
+    #[serde(default)]
+    pub resigned: Option<StoneColor>,
+    /// When it was saved, in seconds since 1970 UTC; older saves don't have it.
+    #[serde(default)]
+    pub saved_at: Option<u64>,
+    /// How the game stood, such as "Black won", for listing saves without replaying them.
+    #[serde(default)]
+    pub result: Option<String>,
+    /// The rules the game is played under, which it goes on with when loaded; older saves
+    /// don't have them and take the command line's.
+    #[serde(default)]
+    pub rules: Option<RuleSet>,
+    #[serde(default)]
+    pub variant: Option<Variant>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedMove {
+    pub col: usize,
+    pub row: usize,
+    pub color: StoneColor,
+}
+
+impl SavedGame {
+    pub fn new(
+        history: &MoveHistory,
+        config: &BoardConfig,
+        current_player: &CurrentPlayer,
+        status: GameStatus,
+        rules: &RuleSet,
+        variant: Variant,
+    ) -> Self {
+        let result = match status {
+            GameStatus::InProgress => "In progress".to_string(),
+            GameStatus::Won(color) => format!("{} won", color.name()),
+            GameStatus::Draw => "Draw".to_string(),
+        };
+        SavedGame {
+            size: config.size,
+            current_player: current_player.0,
+            moves: history
+                .stones()
+                .map(|record| SavedMove {
+                    col: record.col,
+                    row: record.row,
+                    color: record.color,
+                })
+                .collect(),
+            resigned: history.resigned(),
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|since| since.as_secs()),
+            result: Some(result),
+            rules: Some(*rules),
+            variant: Some(variant),
+        }
+    }
+
+    /// The moves in the order they were played.
+    pub fn moves(&self) -> Vec<SgfMove> {
+        self.moves
+            .iter()
+            .map(|saved| (saved.col, saved.row, saved.color))
+            .collect()
+    }
+
+    /// The rules the game was saved with, if it says.
+    pub fn rules(&self) -> Option<(RuleSet, Variant)> {
+        self.rules.zip(self.variant)
+    }
+
+    /// Writes the game to `path`, making its directory first if need be.
+    pub fn write(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+    }
+
+    /// Reads the game saved at `path`. A missing file is the usual first start and passes
+    /// silently; one that can't be used is reported and skipped.
+    pub fn read(path: impl AsRef<Path>) -> Option<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).ok()?;
+        let path = path.display();
+        match serde_json::from_str::<SavedGame>(&text) {
+            Ok(game) if crate::SUPPORTED_BOARD_SIZES.contains(&game.size) => Some(game),
+            Ok(game) => {
+                warn!(
+                    "Ignoring {path}: unsupported board size {}, starting a new game",
+                    game.size
+                );
+                None
+            }
+            Err(err) => {
+                warn!("Ignoring corrupt {path} ({err}), starting a new game");
+                None
+            }
+        }
+    }
+}
+
+/// Where the save called `name` is kept.
+pub fn slot_path(name: &str) -> PathBuf {
+    Path::new(SAVES_DIR).join(format!("{name}.json"))
+}
+
+/// Whether `name` can be used for a save: letters, digits, `-` and `_`, so it is a plain file
+/// name on every system.
+pub fn is_valid_slot_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// A name for a save nobody picked, from the time it was started, such as
+/// `game-20261014-153000`.
+pub fn default_slot_name() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (year, month, day, hour, minute, second) = civil_time(now);
+    format!("game-{year:04}{month:02}{day:02}-{hour:02}{minute:02}{second:02}")
+}
+
+/// Prints every save under `saves/`, by name, with when it was saved, the board size, how
+/// the game stood and how many moves it had.
+pub fn print_slots() {
+    let Ok(entries) = std::fs::read_dir(SAVES_DIR) else {
+        println!("No saved games in {SAVES_DIR}/");
+        return;
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            Some(path.file_stem()?.to_str()?.to_string())
+        })
+        .collect();
+    if names.is_empty() {
+        println!("No saved games in {SAVES_DIR}/");
+        return;
+    }
+    names.sort();
+
+    for name in names {
+        let Some(game) = SavedGame::read(slot_path(&name)) else {
+            continue;
+        };
+        let date = game.saved_at.map_or_else(
+            || "date unknown".to_string(),
+            |secs| {
+                let (year, month, day, hour, minute, _) = civil_time(secs);
+                format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02} UTC")
+            },
+        );
+        println!(
+            "{name:<24} {date:<21} {size}x{size}  {moves:>3} moves  {result}",
+            size = game.size,
+            moves = game.moves.len(),
+            result = game.result.as_deref().unwrap_or("")
+        );
+    }
+}
+
+/// Splits seconds since 1970 UTC into year, month, day, hour, minute and second, with the
+/// days-to-date conversion from Howard Hinnant's `civil_from_days`.
+pub fn civil_time(secs: u64) -> (u64, u64, u64, u64, u64, u64) {
+    let (days, time) = (secs / 86_400, secs % 86_400);
+    // Count from 1 March 0000, so the leap day falls at the end of each year
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+
+pub const SGF_PATH: &str = "game.sgf";
+
+/// A game read from `--load` or the last run's save, replayed onto the board once at startup.
+#[derive(Resource)]
+pub struct LoadedMoves {
+    pub size: usize,
+    pub moves: Vec<sgf::SgfMove>,
+    /// Who moves after the last stone, when the record says so.
+    pub next_player: Option<StoneColor>,
+    /// Who gave the game up after the last stone, if anyone.
+    pub resigned: Option<StoneColor>,
+    /// The rules the game was played under, when the record says.
+    pub rules: Option<(rules::RuleSet, rules::Variant)>,
+}
+
+/// Reads the board size, moves and rules of an SGF file, reporting why if it can't be used.
+pub fn read_sgf_file(path: &Path) -> Option<LoadedMoves> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => {
+            warn!("Could not read {}: {err}", path.display());
+            return None;
+        }
+    };
+    let game = sgf::board_size(&text).and_then(|size| {
+        Ok(LoadedMoves {
+            size,
+            moves: sgf::from_sgf(&text)?,
+            next_player: None,
+            resigned: None,
+            rules: sgf::recorded_rules(&text)?,
+        })
+    });
+    match game {
+        Ok(game) => Some(game),
+        Err(err) => {
+            warn!("Could not load {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+/// Places the loaded moves in order, so the game continues with the right player to move.
+pub fn replay_loaded_moves_system(
+    mut loaded: ResMut<LoadedMoves>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut auto_archive: ResMut<AutoArchive>,
+    mut placer: StonePlacer,
+) {
+    // The game starts from the board as it is, with whoever plays first to move
+    let first = loaded
+        .moves
+        .first()
+        .map_or(placer.current_player.0, |&(_, _, color)| color);
+    placer.positions.start(&placer.board, first);
+    for (col, row, color) in std::mem::take(&mut loaded.moves) {
+        placer.current_player.0 = color;
+        if let Err(err) = placer.place(col, row) {
+            warn!(
+                "Stopped loading at {}{}: {err:?}",
+                column_label(col),
+                row + 1
+            );
+            return;
+        }
+    }
+    if let Some(color) = loaded.next_player.take() {
+        placer.current_player.0 = color;
+    }
+    if let Some(color) = loaded.resigned.take() {
+        placer.current_player.0 = color;
+        // Fails only if the last stone already ended the game
+        let _ = placer.resign();
+    }
+    // A game that was over before this run isn't part of its tally, nor archived again
+    scoreboard.counted = *placer.status != GameStatus::InProgress;
+    auto_archive.archived = scoreboard.counted;
+}
+
+/// Keeps the game in `save.json` when the window is closed, to be picked up on the next start.
+pub fn save_on_exit_system(
+    mut close_requests: EventReader<WindowCloseRequested>,
+    mut exits: EventReader<AppExit>,
+    config: Res<BoardConfig>,
+    history: Res<MoveHistory>,
+    current_player: Res<CurrentPlayer>,
+    status: Res<GameStatus>,
+    rules: Res<rules::RuleSet>,
+    variant: Res<rules::Variant>,
+    online: Res<Online>,
+    drill: Res<drill::DrillState>,
+) {
+    // An online game can't be resumed alone, so it leaves the local save alone, and so does
+    // a drill
+    if close_requests.is_empty() && exits.is_empty() || online.0.is_some() || drill.enabled {
+        return;
+    }
+    close_requests.clear();
+    exits.clear();
+
+    let game = SavedGame::new(
+        &history,
+        &config,
+        &current_player,
+        *status,
+        &rules,
+        *variant,
+    );
+    if let Err(err) = game.write(SAVE_PATH) {
+        warn!("Could not save {}: {err}", SAVE_PATH);
+    }
+}
+
+/// The save under `saves/` that F5 writes to, named with `--save-as`.
+#[derive(Resource)]
+pub struct SaveSlot {
+    pub name: String,
+    /// Whether this run has written it already, so writing again needs no confirming.
+    pub written: bool,
+    /// When F5 was last refused because the save is someone else's, by `Time::elapsed`; a
+    /// second F5 while that notice is up overwrites it.
+    pub asked_at: Option<f64>,
+}
+
+/// Saves the game under its name in `saves/` with F5, asking for a second press before
+/// writing over a save this run didn't make.
+pub fn save_slot_system(
+    keys: keys::Keys,
+    time: Res<Time>,
+    config: Res<BoardConfig>,
+    history: Res<MoveHistory>,
+    current_player: Res<CurrentPlayer>,
+    status: Res<GameStatus>,
+    rules: Res<rules::RuleSet>,
+    variant: Res<rules::Variant>,
+    mut slot: ResMut<SaveSlot>,
+    mut notice: ResMut<Notice>,
+) {
+    if !keys.just_pressed(Action::SaveSlot) {
+        return;
+    }
+
+    let path = slot_path(&slot.name);
+    let now = time.elapsed_seconds_f64();
+    let confirmed = slot
+        .asked_at
+        .is_some_and(|asked_at| now - asked_at < f64::from(Notice::DURATION));
+    if path.exists() && !slot.written && !confirmed {
+        slot.asked_at = Some(now);
+        notice.show(format!(
+            "{} already exists, press F5 again to overwrite it",
+            path.display()
+        ));
+        return;
+    }
+
+    slot.asked_at = None;
+    let game = SavedGame::new(
+        &history,
+        &config,
+        &current_player,
+        *status,
+        &rules,
+        *variant,
+    );
+    match game.write(&path) {
+        Ok(()) => {
+            slot.written = true;
+            notice.show(format!("Saved as {}", slot.name));
+        }
+        Err(err) => notice.show(format!("Could not save {}: {err}", path.display())),
+    }
+}
+
+/// Writes the game so far to `game.sgf` with Ctrl+S.
+pub fn save_sgf_system(
+    keys: keys::Keys,
+    config: Res<BoardConfig>,
+    history: Res<MoveHistory>,
+    rules: Res<rules::RuleSet>,
+    variant: Res<rules::Variant>,
+    mut notice: ResMut<Notice>,
+) {
+    if !keys.just_pressed(Action::SaveSgf) {
+        return;
+    }
+
+    let record = sgf::to_sgf(&history, config.size, &rules, *variant);
+    match std::fs::write(SGF_PATH, record) {
+        Ok(()) => notice.show(format!("Saved {SGF_PATH}")),
+        Err(err) => notice.show(format!("Could not save {SGF_PATH}: {err}")),
+    }
+}
+
+/// Whether finished games go into the archive, and whether the game on the board is in it.
+#[derive(Resource)]
+pub struct AutoArchive {
+    pub enabled: bool,
+    /// Set once the game on the board is archived, so stepping back over its end and forward
+    /// again doesn't archive it twice; a game that was already over when loaded counts too.
+    pub archived: bool,
+}
+
+/// Writes each game to the archive as it ends.
+pub fn auto_archive_system(
+    config: Res<BoardConfig>,
+    history: Res<MoveHistory>,
+    rules: Res<rules::RuleSet>,
+    variant: Res<rules::Variant>,
+    mut auto_archive: ResMut<AutoArchive>,
+    mut notice: ResMut<Notice>,
+    mut game_over_events: EventReader<GameOver>,
+) {
+    if history.0.is_empty() {
+        auto_archive.archived = false;
+    }
+    for event in game_over_events.iter() {
+        if !auto_archive.enabled || auto_archive.archived {
+            continue;
+        }
+        auto_archive.archived = true;
+        match archive::save_game(&history, config.size, &rules, *variant, event.winner) {
+            Ok(path) => info!("Archived the game as {}", path.display()),
+            Err(err) => notice.show(format!("Could not archive the game: {err}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_rules_are_saved_with_the_game() {
+        let rules = RuleSet {
+            win_length: 6,
+            overline_forbidden: [false, true],
+            repetition_draw: true,
+            ..RuleSet::default()
+        };
+        let game = SavedGame::new(
+            &MoveHistory(Vec::new()),
+            &BoardConfig { size: 15 },
+            &CurrentPlayer(StoneColor::White),
+            GameStatus::InProgress,
+            &rules,
+            Variant::Pente,
+        );
+        let json = serde_json::to_string(&game).unwrap();
+        let read: SavedGame = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.rules(), Some((rules, Variant::Pente)));
+        assert_eq!(read.current_player, StoneColor::White);
+    }
+
+    #[test]
+    fn an_older_save_has_no_rules() {
+        let json = r#"{"size": 15, "current_player": "black", "moves": [
+            {"col": 7, "row": 7, "color": "black"}
+        ]}"#;
+        let game: SavedGame = serde_json::from_str(json).unwrap();
+        assert_eq!(game.rules(), None);
+        assert_eq!(game.moves(), vec![(7, 7, StoneColor::Black)]);
+    }
+}
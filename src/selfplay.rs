@@ -0,0 +1,171 @@
+//! `--selfplay <n>`: the computer plays itself `n` times without opening a window, for
+//! testing and tuning the AI.
+
+use std::time::{Duration, Instant};
+
+use rand::{rngs::StdRng, Rng};
+
+use crate::{
+    ai::{self, AiDifficulty},
+    game_core::{self, BoardState, Outcome, PositionCounts, StoneColor},
+    rules::{self, RuleSet, Variant},
+};
+
+/// Moves after Black's opening stone picked at random near it, so games against a search
+/// that always picks the same move still differ.
+const RANDOM_OPENING_MOVES: usize = 2;
+
+#[derive(Default)]
+struct Totals {
+    black_wins: usize,
+    white_wins: usize,
+    draws: usize,
+    moves: usize,
+    /// Moves the AI searched for, leaving out the scripted opening, and the time it took.
+    searched: u32,
+    thinking: Duration,
+    nodes: u64,
+    table_hits: u64,
+    /// Summed over the searched moves, for the average depth reached.
+    depth: u64,
+}
+
+/// Plays `games` games on a `size` board and prints the results as they come in, then the
+/// totals.
+pub fn run(
+    games: usize,
+    size: usize,
+    difficulty: AiDifficulty,
+    rules: &RuleSet,
+    variant: Variant,
+    think: Duration,
+    rng: &mut StdRng,
+) {
+    println!(
+        "Self-play: {games} games on {size}x{size} at {}",
+        difficulty.name()
+    );
+    let mut totals = Totals::default();
+    for game in 1..=games {
+        let (winner, moves) = play_game(size, difficulty, rules, variant, think, rng, &mut totals);
+        let result = match winner {
+            Some(StoneColor::Black) => {
+                totals.black_wins += 1;
+                "Black wins"
+            }
+            Some(StoneColor::White) => {
+                totals.white_wins += 1;
+                "White wins"
+            }
+            None => {
+                totals.draws += 1;
+                "Draw"
+            }
+        };
+        println!("Game {game}/{games}: {result} after {moves} moves");
+    }
+
+    let percent = |count: usize| 100.0 * count as f64 / games.max(1) as f64;
+    println!(
+        "Black {} ({:.1}%), White {} ({:.1}%), draws {} ({:.1}%)",
+        totals.black_wins,
+        percent(totals.black_wins),
+        totals.white_wins,
+        percent(totals.white_wins),
+        totals.draws,
+        percent(totals.draws)
+    );
+    println!(
+        "Average game length {:.1} moves, average move time {:.1?}",
+        totals.moves as f64 / games.max(1) as f64,
+        totals.thinking / totals.searched.max(1)
+    );
+    println!(
+        "Average depth {:.1} and {:.0} positions searched per move, {:.1}% answered from the \
+         transposition table",
+        totals.depth as f64 / totals.searched.max(1) as f64,
+        totals.nodes as f64 / totals.searched.max(1) as f64,
+        100.0 * totals.table_hits as f64 / totals.nodes.max(1) as f64
+    );
+}
+
+/// Plays one game to the end, returning the winner, `None` for a draw, and how many moves it
+/// took.
+fn play_game(
+    size: usize,
+    difficulty: AiDifficulty,
+    rules: &RuleSet,
+    variant: Variant,
+    think: Duration,
+    rng: &mut StdRng,
+    totals: &mut Totals,
+) -> (Option<StoneColor>, usize) {
+    let mut board = BoardState::for_variant(size, variant);
+    let mut color = StoneColor::Black;
+    // Pairs captured by Black and by White
+    let (mut black_pairs, mut white_pairs) = (0, 0);
+    let mut positions = PositionCounts::default();
+    positions.start(&board, color);
+    let mut number = 0;
+    loop {
+        let (col, row) = if number == 0 {
+            (size / 2, size / 2)
+        } else if number <= RANDOM_OPENING_MOVES {
+            let legal = |&(col, row): &(usize, usize)| {
+                rules::variant_move_legal(&board, col, row, color, variant)
+            };
+            let mut moves = ai::candidate_moves(&board, 1);
+            moves.retain(legal);
+            if moves.is_empty() {
+                // Pente's opening rule keeps Black's second stone further out
+                moves = ai::candidate_moves(&board, rules::PENTE_OPENING_DISTANCE);
+                moves.retain(legal);
+            }
+            moves[rng.gen_range(0..moves.len())]
+        } else {
+            let started = Instant::now();
+            let (line, stats) = ai::choose_move(
+                &board,
+                color,
+                difficulty,
+                ai::AiPersonality::Balanced,
+                rules,
+                variant,
+                think,
+                rng,
+            );
+            totals.thinking += started.elapsed();
+            totals.searched += 1;
+            totals.nodes += stats.nodes;
+            totals.table_hits += stats.table_hits;
+            totals.depth += u64::from(stats.depth);
+            line[0]
+        };
+        let pairs = match color {
+            StoneColor::Black => &mut black_pairs,
+            StoneColor::White => &mut white_pairs,
+        };
+        let Ok(mut placement) =
+            game_core::apply_move(&mut board, col, row, color, rules, variant, *pairs)
+        else {
+            // Only a forbidden point was left for Black; count it as a loss the way a
+            // Renju referee would
+            totals.moves += number;
+            return (Some(color.opponent()), number);
+        };
+
+        placement.count_position(&mut positions, &board, color, rules);
+        number += 1;
+        *pairs += placement.captured.len() / 2;
+        let winner = match placement.outcome {
+            Outcome::Continues => {
+                color = color.opponent();
+                continue;
+            }
+            Outcome::Won { winner, .. } => Some(winner),
+            Outcome::Draw => None,
+        };
+        totals.moves += number;
+        return (winner, number);
+    }
+}
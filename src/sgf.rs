@@ -0,0 +1,482 @@
+//! Reading and writing games in Smart Game Format, using game type `GM[4]` (Gomoku and Renju).
+//!
+//! SGF coordinates are two letters, column then row, starting from `a` at the top-left corner.
+//! Board rows here count up from the bottom, so row `r` is written as `size - 1 - r`.
+//!
+//! The rules a game is played under go in `RU`, in words `recorded_rules` can read back.
+
+use crate::{
+    rules::{self, RuleSet, Variant},
+    MoveHistory, StoneColor,
+};
+
+/// What the root node of a finished game's record says about it besides the rules.
+pub struct GameInfo {
+    /// As `result_value` writes it.
+    pub result: String,
+    /// `YYYY-MM-DD`.
+    pub date: String,
+}
+
+/// Writes the moves of `history` on a `size`x`size` board, played under `rules` and
+/// `variant`, as an SGF game record. A resignation becomes the result, such as `RE[B+R]`
+/// when White resigns.
+pub fn to_sgf(history: &MoveHistory, size: usize, rules: &RuleSet, variant: Variant) -> String {
+    let result = history
+        .resigned()
+        .map(|loser| format!("RE[{}]", result_value(Some(loser.opponent()), true)));
+    let root = format!(
+        "{}RU[{}]",
+        result.unwrap_or_default(),
+        rules_value(rules, variant)
+    );
+    write(history, size, root)
+}
+
+/// `to_sgf` for a finished game, with `info`'s result and date in the root node as `RE` and
+/// `DT`.
+pub fn to_sgf_with_info(
+    history: &MoveHistory,
+    size: usize,
+    rules: &RuleSet,
+    variant: Variant,
+    info: &GameInfo,
+) -> String {
+    let root = format!(
+        "RE[{}]RU[{}]DT[{}]",
+        info.result,
+        rules_value(rules, variant),
+        info.date
+    );
+    write(history, size, root)
+}
+
+/// The rules in words for a record's `RU`, such as "5 in a row, Renju, swap2".
+pub fn rules_value(rules: &RuleSet, variant: Variant) -> String {
+    let length = format!("{} in a row", rules.win_length);
+    let mut parts = vec![length.as_str()];
+    match variant {
+        Variant::Standard => {}
+        Variant::Pente => parts.push("Pente captures"),
+        Variant::Toroidal => parts.push("wrap-around board"),
+    }
+    match rules.overline_forbidden {
+        [false, false] => {}
+        [true, true] => parts.push("no overlines"),
+        [true, false] => parts.push("no overlines for Black"),
+        [false, true] => parts.push("no overlines for White"),
+    }
+    let flags = [
+        (rules.renju, "Renju"),
+        (rules.swap2, "swap2"),
+        (rules.pie, "pie rule"),
+        (rules.first_move_center, "center opening"),
+        (rules.early_draw, "early draw"),
+        (rules.repetition_draw, "draw by repetition"),
+    ];
+    parts.extend(
+        flags
+            .into_iter()
+            .filter(|&(set, _)| set)
+            .map(|(_, words)| words),
+    );
+    parts.join(", ")
+}
+
+/// The rules `value` names, if it names them the way `rules_value` writes them and they go
+/// together: the Renju restrictions need five in a row, and a board can't both capture and
+/// wrap.
+fn parse_rules(value: &str) -> Option<(RuleSet, Variant)> {
+    let mut parts = value.split(',').map(str::trim);
+    let win_length = parts
+        .next()?
+        .strip_suffix(" in a row")?
+        .parse()
+        .ok()
+        .filter(|length| rules::WIN_LENGTHS.contains(length))?;
+    let mut rules = RuleSet {
+        win_length,
+        ..RuleSet::default()
+    };
+    let mut variant = Variant::Standard;
+    for part in parts {
+        match part {
+            "Pente captures" if variant == Variant::Standard => variant = Variant::Pente,
+            "wrap-around board" if variant == Variant::Standard => variant = Variant::Toroidal,
+            "no overlines" => rules.overline_forbidden = [true; 2],
+            "no overlines for Black" => rules.overline_forbidden[0] = true,
+            "no overlines for White" => rules.overline_forbidden[1] = true,
+            "Renju" => rules.renju = true,
+            "swap2" => rules.swap2 = true,
+            "pie rule" => rules.pie = true,
+            "center opening" => rules.first_move_center = true,
+            "early draw" => rules.early_draw = true,
+            "draw by repetition" => rules.repetition_draw = true,
+            _ => return None,
+        }
+    }
+    (!rules.renju || win_length == rules::DEFAULT_WIN_LENGTH).then_some((rules, variant))
+}
+
+/// The `RE` value for a game `winner` won, or drawn if there is none: `B+R` or `W+R` when
+/// the loser resigned, `B+` or `W+` for any other win, and `0` for a draw.
+pub fn result_value(winner: Option<StoneColor>, by_resignation: bool) -> String {
+    match winner {
+        Some(winner) if by_resignation => format!("{}+R", color_letter(winner)),
+        Some(winner) => format!("{}+", color_letter(winner)),
+        None => "0".to_string(),
+    }
+}
+
+/// The record of `history` with `root` added to the root node's properties.
+fn write(history: &MoveHistory, size: usize, root: String) -> String {
+    let mut sgf = format!("(;FF[4]GM[4]SZ[{size}]{root}");
+    for record in history.stones() {
+        let property = color_letter(record.color);
+        sgf.push_str(&format!(
+            ";{property}[{}{}]",
+            coordinate_letter(record.col),
+            coordinate_letter(size - 1 - record.row)
+        ));
+    }
+    sgf.push(')');
+    sgf
+}
+
+fn color_letter(color: StoneColor) -> char {
+    match color {
+        StoneColor::Black => 'B',
+        StoneColor::White => 'W',
+    }
+}
+
+fn coordinate_letter(index: usize) -> char {
+    (b'a' + index as u8) as char
+}
+
+/// Why an SGF record could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SgfError {
+    /// The text isn't a well-formed game tree.
+    Malformed(String),
+    /// A move names a point that isn't on the board.
+    InvalidCoordinate(String),
+    /// `SZ` isn't a size this game can play on.
+    UnsupportedSize(String),
+}
+
+impl std::fmt::Display for SgfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SgfError::Malformed(reason) => write!(f, "malformed SGF: {reason}"),
+            SgfError::InvalidCoordinate(value) => write!(f, "invalid move coordinate [{value}]"),
+            SgfError::UnsupportedSize(value) => write!(f, "unsupported board size SZ[{value}]"),
+        }
+    }
+}
+
+impl std::error::Error for SgfError {}
+
+/// Board size used when a record has no `SZ`, as the SGF specification sets for `GM[4]`.
+const DEFAULT_SIZE: usize = 15;
+
+/// A move read from a record, as `(col, row, color)`.
+pub type SgfMove = (usize, usize, StoneColor);
+
+/// Reads the moves of an SGF game record, in order.
+pub fn from_sgf(text: &str) -> Result<Vec<SgfMove>, SgfError> {
+    parse(text).map(|game| game.moves)
+}
+
+/// Reads the board size of an SGF game record.
+pub fn board_size(text: &str) -> Result<usize, SgfError> {
+    parse(text).map(|game| game.size)
+}
+
+/// The result a record gives in `RE`, such as `B+R`, if it has one.
+pub fn recorded_result(text: &str) -> Result<Option<String>, SgfError> {
+    parse(text).map(|game| game.result)
+}
+
+/// The rules a record gives in `RU`, if it has them in the words `rules_value` uses. Records
+/// from elsewhere that name their rules some other way are read as giving none.
+pub fn recorded_rules(text: &str) -> Result<Option<(RuleSet, Variant)>, SgfError> {
+    parse(text).map(|game| game.rules.as_deref().and_then(parse_rules))
+}
+
+struct Game {
+    size: usize,
+    moves: Vec<SgfMove>,
+    result: Option<String>,
+    rules: Option<String>,
+}
+
+/// Parses the main line of a single game tree; variations are rejected.
+fn parse(text: &str) -> Result<Game, SgfError> {
+    let text = text.trim();
+    let body = text
+        .strip_prefix('(')
+        .and_then(|rest| rest.strip_suffix(')'))
+        .ok_or_else(|| SgfError::Malformed("expected a game tree in parentheses".into()))?;
+
+    let mut size = DEFAULT_SIZE;
+    let mut result = None;
+    let mut rules = None;
+    let mut raw_moves = Vec::new();
+    let mut chars = body.chars().peekable();
+    let mut seen_node = false;
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => seen_node = true,
+            c if c.is_whitespace() => {}
+            '(' | ')' => return Err(SgfError::Malformed("variations are not supported".into())),
+            c if c.is_ascii_uppercase() => {
+                if !seen_node {
+                    return Err(SgfError::Malformed("property outside of a node".into()));
+                }
+                let mut ident = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if !next.is_ascii_uppercase() {
+                        break;
+                    }
+                    ident.push(next);
+                    chars.next();
+                }
+
+                let mut values = Vec::new();
+                while chars.peek().is_some_and(|next| next.is_whitespace()) {
+                    chars.next();
+                }
+                while chars.peek() == Some(&'[') {
+                    chars.next();
+                    values.push(read_value(&mut chars)?);
+                    while chars.peek().is_some_and(|next| next.is_whitespace()) {
+                        chars.next();
+                    }
+                }
+                if values.is_empty() {
+                    return Err(SgfError::Malformed(format!(
+                        "property {ident} has no value"
+                    )));
+                }
+
+                match ident.as_str() {
+                    "SZ" => {
+                        size = values[0]
+                            .trim()
+                            .parse()
+                            .ok()
+                            .filter(|size| crate::SUPPORTED_BOARD_SIZES.contains(size))
+                            .ok_or_else(|| SgfError::UnsupportedSize(values[0].clone()))?;
+                    }
+                    "RE" => result = Some(values[0].trim().to_string()),
+                    "RU" => rules = Some(values[0].clone()),
+                    "B" => raw_moves.push((StoneColor::Black, values[0].clone())),
+                    "W" => raw_moves.push((StoneColor::White, values[0].clone())),
+                    _ => {}
+                }
+            }
+            other => {
+                return Err(SgfError::Malformed(format!(
+                    "unexpected character {other:?}"
+                )));
+            }
+        }
+    }
+
+    // A point can come up again once a Pente capture has emptied it, so whether it is free is
+    // left to replaying the moves
+    let moves = raw_moves
+        .into_iter()
+        .map(|(color, value)| parse_point(&value, size).map(|(col, row)| (col, row, color)))
+        .collect::<Result<_, _>>()?;
+    Ok(Game {
+        size,
+        moves,
+        result,
+        rules,
+    })
+}
+
+/// Reads a property value up to its closing bracket, honouring `\` escapes.
+fn read_value(chars: &mut impl Iterator<Item = char>) -> Result<String, SgfError> {
+    let mut value = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            ']' => return Ok(value),
+            '\\' => value.extend(chars.next()),
+            c => value.push(c),
+        }
+    }
+    Err(SgfError::Malformed("unterminated property value".into()))
+}
+
+fn parse_point(value: &str, size: usize) -> Result<(usize, usize), SgfError> {
+    let invalid = || SgfError::InvalidCoordinate(value.to_string());
+    let bytes = value.as_bytes();
+    if bytes.len() != 2 {
+        return Err(invalid());
+    }
+    let index = |b: u8| b.checked_sub(b'a').map(usize::from).filter(|&i| i < size);
+    let col = index(bytes[0]).ok_or_else(invalid)?;
+    let sgf_row = index(bytes[1]).ok_or_else(invalid)?;
+    Ok((col, size - 1 - sgf_row))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Entity;
+
+    use super::*;
+    use crate::{HistoryEntry, MoveRecord};
+
+    /// A history of `moves` with no captures.
+    fn history(moves: &[SgfMove]) -> MoveHistory {
+        let entries = moves.iter().enumerate().map(|(i, &(col, row, color))| {
+            HistoryEntry::Stone(MoveRecord {
+                col,
+                row,
+                color,
+                entity: Entity::from_raw(i as u32),
+                captures: Vec::new(),
+            })
+        });
+        MoveHistory(entries.collect())
+    }
+
+    #[test]
+    fn moves_survive_a_round_trip() {
+        let moves = vec![
+            (7, 7, StoneColor::Black),
+            (8, 8, StoneColor::White),
+            (0, 0, StoneColor::Black),
+            (14, 14, StoneColor::White),
+            (0, 14, StoneColor::Black),
+            (14, 0, StoneColor::White),
+        ];
+        let sgf = to_sgf(&history(&moves), 15, &RuleSet::default(), Variant::Standard);
+        assert_eq!(from_sgf(&sgf), Ok(moves));
+        assert_eq!(board_size(&sgf), Ok(15));
+        assert_eq!(recorded_result(&sgf), Ok(None));
+    }
+
+    #[test]
+    fn rows_count_down_from_the_top_in_sgf() {
+        let moves = [
+            (0, 0, StoneColor::Black),
+            (18, 18, StoneColor::White),
+            (3, 15, StoneColor::Black),
+        ];
+        let sgf = to_sgf(&history(&moves), 19, &RuleSet::default(), Variant::Standard);
+        assert_eq!(sgf, "(;FF[4]GM[4]SZ[19]RU[5 in a row];B[as];W[sa];B[dd])");
+    }
+
+    #[test]
+    fn a_resignation_becomes_the_result() {
+        let mut resigned = history(&[(7, 7, StoneColor::Black)]);
+        resigned.0.push(HistoryEntry::Resign(StoneColor::White));
+        let sgf = to_sgf(&resigned, 15, &RuleSet::default(), Variant::Standard);
+        assert_eq!(recorded_result(&sgf), Ok(Some("B+R".to_string())));
+        assert_eq!(from_sgf(&sgf), Ok(vec![(7, 7, StoneColor::Black)]));
+    }
+
+    #[test]
+    fn bad_records_are_refused() {
+        assert!(matches!(
+            from_sgf("(;GM[4];B[hh]"),
+            Err(SgfError::Malformed(_))
+        ));
+        assert!(matches!(
+            from_sgf("(;GM[4];B[hh](;W[ii]))"),
+            Err(SgfError::Malformed(_))
+        ));
+        assert!(matches!(
+            from_sgf("(;SZ[9];B[jj])"),
+            Err(SgfError::InvalidCoordinate(_))
+        ));
+        assert!(matches!(
+            board_size("(;SZ[4])"),
+            Err(SgfError::UnsupportedSize(_))
+        ));
+    }
+
+    #[test]
+    fn a_point_emptied_by_a_capture_can_be_played_again() {
+        // White's pair on e5 and f5 is taken by Black's g5, and White plays back into e5
+        let record = "(;SZ[9]RU[5 in a row, Pente captures];B[de];W[ee];B[ab];W[fe];B[ge];W[ee])";
+        let moves = from_sgf(record).unwrap();
+        assert_eq!(moves.len(), 6);
+        assert_eq!(moves[1], moves[5]);
+
+        let (rules, variant) = recorded_rules(record).unwrap().unwrap();
+        let mut board = crate::BoardState::for_variant(9, variant);
+        for (col, row, color) in moves {
+            crate::game_core::apply_move(&mut board, col, row, color, &rules, variant, 0).unwrap();
+        }
+        assert_eq!(board.get(4, 4), Some(StoneColor::White));
+        assert_eq!(board.get(5, 4), None);
+    }
+
+    #[test]
+    fn rules_survive_a_round_trip() {
+        let all = RuleSet {
+            win_length: 5,
+            overline_forbidden: [true, false],
+            renju: true,
+            swap2: true,
+            pie: true,
+            first_move_center: true,
+            early_draw: true,
+            repetition_draw: true,
+        };
+        let games = [
+            (RuleSet::default(), Variant::Standard),
+            (RuleSet::default(), Variant::Pente),
+            (all, Variant::Standard),
+            (
+                RuleSet {
+                    win_length: 6,
+                    overline_forbidden: [true; 2],
+                    ..RuleSet::default()
+                },
+                Variant::Toroidal,
+            ),
+            (
+                RuleSet {
+                    overline_forbidden: [false, true],
+                    ..RuleSet::default()
+                },
+                Variant::Pente,
+            ),
+        ];
+        for (rules, variant) in games {
+            let sgf = to_sgf(&history(&[]), 15, &rules, variant);
+            assert_eq!(recorded_rules(&sgf), Ok(Some((rules, variant))), "{sgf}");
+        }
+    }
+
+    #[test]
+    fn rules_in_other_words_are_not_read() {
+        for value in [
+            "Renju",
+            "5 in a row, house rules",
+            "4 in a row, Renju",
+            "2 in a row",
+        ] {
+            let record = format!("(;GM[4]RU[{value}];B[hh])");
+            assert_eq!(recorded_rules(&record), Ok(None), "{value}");
+        }
+        let record = "(;GM[4]RU[5 in a row, Pente captures, wrap-around board])";
+        assert_eq!(recorded_rules(record), Ok(None));
+        assert_eq!(recorded_rules("(;GM[4];B[hh])"), Ok(None));
+    }
+
+    #[test]
+    fn a_record_without_a_size_is_fifteen_by_fifteen() {
+        assert_eq!(board_size("(;GM[4];B[hh])"), Ok(15));
+        assert_eq!(
+            from_sgf("(;GM[4];B[hh])"),
+            Ok(vec![(7, 7, StoneColor::Black)])
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Color schemes for the window, board and stones, cycled through with P.
+
+use bevy::prelude::{Color, Resource};
+
+use crate::StoneColor;
+
+/// The colors everything on the board is drawn in.
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+pub struct Theme {
+    pub name: &'static str,
+    pub background: Color,
+    pub board: Color,
+    pub lines: Color,
+    pub black_stone: Color,
+    pub white_stone: Color,
+}
+
+/// The themes P steps through, in order.
+pub const PRESETS: [Theme; 3] = [
+    Theme {
+        name: "Classic wood",
+        background: Color::DARK_GRAY,
+        board: Color::rgb(0.92, 0.75, 0.45),
+        lines: Color::rgb(0.0, 0.0, 0.0),
+        black_stone: Color::rgb(0.0, 0.0, 0.0),
+        white_stone: Color::rgb(0.95, 0.95, 0.95),
+    },
+    Theme {
+        name: "High contrast",
+        background: Color::rgb(0.0, 0.0, 0.0),
+        board: Color::rgb(1.0, 0.85, 0.0),
+        lines: Color::rgb(0.0, 0.0, 0.0),
+        black_stone: Color::rgb(0.0, 0.0, 0.0),
+        white_stone: Color::rgb(1.0, 1.0, 1.0),
+    },
+    Theme {
+        name: "Dark mode",
+        background: Color::rgb(0.05, 0.05, 0.06),
+        board: Color::rgb(0.2, 0.21, 0.24),
+        lines: Color::rgb(0.5, 0.5, 0.55),
+        black_stone: Color::rgb(0.02, 0.02, 0.03),
+        white_stone: Color::rgb(0.78, 0.78, 0.8),
+    },
+];
+
+impl Default for Theme {
+    fn default() -> Self {
+        PRESETS[0]
+    }
+}
+
+impl Theme {
+    pub fn stone(&self, color: StoneColor) -> Color {
+        match color {
+            StoneColor::Black => self.black_stone,
+            StoneColor::White => self.white_stone,
+        }
+    }
+
+    /// The rim of a `color` stone, a shade darker than its face.
+    pub fn stone_edge(&self, color: StoneColor) -> Color {
+        let face = self.stone(color);
+        Color::rgb(face.r() * 0.75, face.g() * 0.75, face.b() * 0.75)
+    }
+
+    /// A text color that stands out on a stone of `color`: black on light stones, white on
+    /// dark ones.
+    pub fn label(&self, color: StoneColor) -> Color {
+        let stone = self.stone(color);
+        let luminance = 0.299 * stone.r() + 0.587 * stone.g() + 0.114 * stone.b();
+        if luminance > 0.5 {
+            Color::BLACK
+        } else {
+            Color::WHITE
+        }
+    }
+
+    /// The preset after this one, wrapping around to the first.
+    pub fn next(&self) -> Theme {
+        let index = PRESETS.iter().position(|preset| preset.name == self.name);
+        PRESETS[index.map_or(0, |index| (index + 1) % PRESETS.len())]
+    }
+}